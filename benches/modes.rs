@@ -0,0 +1,120 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    process::{Child, Command},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Connections sending requests at the same time.
+const CLIENTS: usize = 8;
+/// Requests sent at once by each connection, half `SET` and half `GET`.
+const PIPELINE: usize = 32;
+const KEYS: usize = 10_000;
+
+/// Server started for the benchmarks, killed when dropped.
+struct Server(Child);
+
+impl Server {
+    fn start(port: u16, args: &[&str]) -> Self {
+        let server = Self(
+            Command::new(env!("CARGO_BIN_EXE_cc-redis"))
+                .args(["--port", &port.to_string()])
+                .args(args)
+                .spawn()
+                .expect("the server should start"),
+        );
+        for _ in 0..100 {
+            if TcpStream::connect(("127.0.0.1", port)).is_ok() {
+                return server;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        panic!("the server doesn't accept connections on port {port}");
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn command(params: &[&str]) -> Vec<u8> {
+    let mut msg = format!("*{}\r\n", params.len());
+    for p in params {
+        msg.push_str(&format!("${}\r\n{p}\r\n", p.len()));
+    }
+    msg.into_bytes()
+}
+
+/// Pipelines of requests over keys spread across the keyspace, with the length of their replies.
+fn pipelines(client: usize) -> Vec<(Vec<u8>, usize)> {
+    (0..KEYS / PIPELINE)
+        .map(|batch| {
+            let mut msg = vec![];
+            for i in 0..PIPELINE / 2 {
+                let key = format!("key:{}", (client * 7919 + batch * PIPELINE + i) % KEYS);
+                msg.extend(command(&["SET", &key, "value"]));
+                msg.extend(command(&["GET", &key]));
+            }
+            // "+OK\r\n" and "$5\r\nvalue\r\n"
+            (msg, PIPELINE / 2 * (5 + 11))
+        })
+        .collect()
+}
+
+/// Sends `iters` pipelines over each connection, returning the time taken by the slowest.
+fn run(port: u16, iters: u64) -> Duration {
+    let clients: Vec<_> = (0..CLIENTS)
+        .map(|client| {
+            thread::spawn(move || {
+                let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+                stream.set_nodelay(true).unwrap();
+                let pipelines = pipelines(client);
+                let mut buf = vec![0; 64 * 1024];
+
+                let start = Instant::now();
+                for (msg, reply_len) in pipelines.iter().cycle().take(iters as usize) {
+                    stream.write_all(msg).unwrap();
+                    let mut read = 0;
+                    while read < *reply_len {
+                        read += stream.read(&mut buf).unwrap();
+                    }
+                }
+                start.elapsed()
+            })
+        })
+        .collect();
+
+    clients
+        .into_iter()
+        .map(|c| c.join().unwrap())
+        .max()
+        .unwrap()
+}
+
+/// Compares the default mode, where tasks on a multi-threaded runtime share the locked shards of
+/// the keyspace, with the thread-per-core mode.
+fn modes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("modes");
+    group.throughput(Throughput::Elements((CLIENTS * PIPELINE) as u64));
+    group.measurement_time(Duration::from_secs(10));
+
+    let modes: [(&str, u16, &[&str]); 2] = [
+        ("shared", 7401, &[]),
+        ("thread_per_core", 7402, &["--thread-per-core", "yes"]),
+    ];
+    for (name, port, args) in modes {
+        let _server = Server::start(port, args);
+        group.bench_function(BenchmarkId::new("set_get", name), |b| {
+            b.iter_custom(|iters| run(port, iters))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, modes);
+criterion_main!(benches);
@@ -0,0 +1,20 @@
+use crate::resp::protocol::Protocol;
+
+/// State of a single connection, shared by all the requests sent over it.
+#[derive(Debug, Default, Clone)]
+pub struct Client {
+    pub id: u64,
+    pub protocol: Protocol,
+    pub name: Option<String>,
+    /// Index of the selected database.
+    pub db: usize,
+}
+
+impl Client {
+    pub fn new(id: u64) -> Self {
+        Self {
+            id,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    sync::LazyLock,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Wall-clock time read once, then advanced with a monotonic clock.
+///
+/// Expirations are compared against this clock rather than `SystemTime::now()`, so a relative
+/// TTL lasts exactly as long as asked even if the system clock is stepped, e.g. by NTP. The
+/// price is that absolute times (`EXPIREAT`, `EXAT`) drift from the wall clock by as much as the
+/// system clock is adjusted while the server runs.
+struct Clock {
+    origin: SystemTime,
+    start: Instant,
+}
+
+impl Clock {
+    fn new() -> Self {
+        Self {
+            origin: SystemTime::now(),
+            start: Instant::now(),
+        }
+    }
+
+    fn now(&self) -> SystemTime {
+        self.origin + self.start.elapsed()
+    }
+}
+
+static CLOCK: LazyLock<Clock> = LazyLock::new(Clock::new);
+
+/// Current time, for everything that deals with expirations.
+pub fn now() -> SystemTime {
+    #[cfg(test)]
+    if let Some(now) = mock::now() {
+        return now;
+    }
+    CLOCK.now()
+}
+
+/// Milliseconds between the Unix epoch and `time`, negative for times before it.
+pub fn to_unix_ms(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_millis() as i64,
+        Err(e) => -(e.duration().as_millis() as i64),
+    }
+}
+
+/// Inverse of [`to_unix_ms`].
+pub fn from_unix_ms(ms: i64) -> SystemTime {
+    let d = Duration::from_millis(ms.unsigned_abs());
+    if ms >= 0 {
+        UNIX_EPOCH + d
+    } else {
+        UNIX_EPOCH - d
+    }
+}
+
+#[cfg(test)]
+pub use mock::MockClock;
+
+#[cfg(test)]
+mod mock {
+    use std::{
+        cell::Cell,
+        time::{Duration, SystemTime},
+    };
+
+    thread_local! {
+        static NOW: Cell<Option<SystemTime>> = const { Cell::new(None) };
+    }
+
+    pub fn now() -> Option<SystemTime> {
+        NOW.get()
+    }
+
+    /// Freezes [`super::now`] for the current thread until dropped, so that expiry tests don't
+    /// depend on how long they take to run.
+    pub struct MockClock;
+
+    impl MockClock {
+        pub fn at(now: SystemTime) -> Self {
+            NOW.set(Some(now));
+            MockClock
+        }
+
+        pub fn advance(&self, by: Duration) {
+            NOW.set(NOW.get().map(|now| now + by));
+        }
+    }
+
+    impl Drop for MockClock {
+        fn drop(&mut self) {
+            NOW.set(None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_monotonic() {
+        let clock = Clock::new();
+        let a = clock.now();
+        let b = clock.now();
+        assert!(b >= a);
+        assert!(a >= clock.origin);
+    }
+
+    #[test]
+    fn mock_clock() {
+        let t = UNIX_EPOCH + Duration::from_secs(1_000);
+        let clock = MockClock::at(t);
+        assert_eq!(now(), t);
+
+        clock.advance(Duration::from_millis(1_500));
+        assert_eq!(now(), t + Duration::from_millis(1_500));
+
+        drop(clock);
+        assert!(now() > t + Duration::from_secs(1_000_000));
+    }
+
+    #[test]
+    fn unix_ms_round_trip() {
+        for ms in [0, 1, -1, 1_700_000_000_123, -86_400_000] {
+            assert_eq!(to_unix_ms(from_unix_ms(ms)), ms);
+        }
+    }
+}
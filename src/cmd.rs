@@ -1,6 +1,7 @@
 pub mod request;
 pub mod response;
 mod types;
+mod registry;
 mod error;
 mod parser;
 mod execution;
@@ -1,9 +1,15 @@
 use thiserror::Error;
 
+use crate::{
+    db::WrongType,
+    resp::error::{ErrorCode, ErrorReply},
+};
+
 #[derive(Debug, Error, PartialEq)]
 pub enum ClientError {
-    #[error("unknown command '{0}'")]
-    UnknownCommand(String),
+    /// Name of the command and its quoted arguments.
+    #[error("unknown command '{0}', with args beginning with: {1}")]
+    UnknownCommand(String, String),
     #[error("wrong number of arguments for '{0}' command")]
     WrongNumberOfArguments(String),
     #[error("syntax error")]
@@ -12,6 +18,93 @@ pub enum ClientError {
     IntegerError,
     #[error("increment or decrement would overflow")]
     OverflowError,
-    #[error("operation against a key holding the wrong kind of value")]
+    #[error("Operation against a key holding the wrong kind of value")]
     WrongType,
+    #[error("Protocol version is not an integer or out of range")]
+    ProtocolVersionError,
+    #[error("sorry, this protocol version is not supported")]
+    NoProto,
+    #[error("invalid username-password pair or user is disabled.")]
+    WrongPass,
+    #[error("Client names cannot contain spaces, newlines or special characters.")]
+    InvalidClientName,
+    #[error("unknown subcommand '{0}'. Try {1} HELP.")]
+    UnknownSubcommand(String, String),
+    #[error("Unsupported option {0}")]
+    UnsupportedOption(String),
+    /// Options, as they're listed in the message.
+    #[error("{0} options at the same time are not compatible")]
+    IncompatibleOptions(String),
+    /// Name of the command.
+    #[error("invalid expire time in '{0}' command")]
+    InvalidExpireTime(String),
+    #[error("Invalid command specified")]
+    InvalidCommandSpecified,
+    #[error("Invalid number of arguments specified for command")]
+    InvalidArgumentsSpecified,
+    #[error("The command has no key arguments")]
+    NoKeyArguments,
+    #[error("DB index is out of range")]
+    DbIndexOutOfRange,
+    #[error("invalid first DB index")]
+    InvalidFirstDbIndex,
+    #[error("invalid second DB index")]
+    InvalidSecondDbIndex,
+    #[error("source and destination objects are the same")]
+    SameObject,
+    #[error("no such key")]
+    NoSuchKey,
+    #[error("invalid cursor")]
+    InvalidCursor,
+    #[error("unknown type name '{0}'")]
+    UnknownType(String),
+    #[error("command not allowed when used memory > 'maxmemory'.")]
+    OutOfMemory,
+}
+
+impl From<WrongType> for ClientError {
+    fn from(_: WrongType) -> Self {
+        ClientError::WrongType
+    }
+}
+
+impl ErrorReply for ClientError {
+    fn code(&self) -> ErrorCode {
+        match self {
+            ClientError::WrongType => ErrorCode::WrongType,
+            ClientError::NoProto => ErrorCode::NoProto,
+            ClientError::WrongPass => ErrorCode::WrongPass,
+            ClientError::OutOfMemory => ErrorCode::Oom,
+            _ => ErrorCode::Err,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reply_unknown_command() {
+        let e = ClientError::UnknownCommand("foo".to_string(), "'a' 'b' ".to_string());
+        assert_eq!(
+            e.reply(),
+            "ERR unknown command 'foo', with args beginning with: 'a' 'b' "
+        );
+    }
+
+    #[test]
+    fn reply_wrong_type() {
+        assert_eq!(
+            ClientError::WrongType.reply(),
+            "WRONGTYPE Operation against a key holding the wrong kind of value"
+        );
+    }
+
+    #[test]
+    fn reply_auth() {
+        assert!(ClientError::WrongPass.reply().starts_with("WRONGPASS "));
+        assert!(ClientError::NoProto.reply().starts_with("NOPROTO "));
+        assert!(ClientError::OutOfMemory.reply().starts_with("OOM "));
+    }
 }
@@ -1,2 +1,11 @@
 pub mod arithmetic;
 pub mod list;
+pub mod command;
+pub mod expire;
+pub mod database;
+pub mod info;
+pub mod keyspace;
+pub mod memory;
+pub mod object;
+pub mod scan;
+pub mod set;
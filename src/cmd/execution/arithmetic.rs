@@ -1,8 +1,10 @@
 use std::ops::Neg;
 
+use bytes::Bytes;
+
 use crate::{
     cmd::error::ClientError,
-    db::{Db, Object, Value},
+    db::{Database, Kind, Object, Value, detach},
 };
 
 pub enum Integer {
@@ -13,35 +15,24 @@ pub enum Integer {
 }
 
 impl Integer {
-    pub fn execute(&self, db: &Db, key: String) -> Result<i64, ClientError> {
-        let mut map = db.lock().unwrap();
+    pub fn execute(&self, db: &Database, key: Bytes) -> Result<i64, ClientError> {
+        let mut map = db.lock(&key);
         let (initial_value, operation) = self.operation();
 
-        match map.get(&key) {
+        match map.lookup(&key, Kind::String)? {
             None => {
-                map.insert(key, Object::new(Value::Integer(initial_value), None));
-                Ok(initial_value)
-            }
-
-            Some(obj) if obj.is_expired() => {
-                map.swap_remove(&key);
-                map.insert(key, Object::new(Value::Integer(initial_value), None));
-
+                map.insert(
+                    detach(key),
+                    Object::new(Value::Integer(initial_value), None),
+                );
                 Ok(initial_value)
             }
 
             Some(obj) => match obj.value {
                 Value::Integer(i) => {
-                    // value moved to avoid borrowing issues
-                    let exp = obj.expiration;
-
-                    operation(i).map_or(
-                        Err(ClientError::OverflowError),
-                        |v| {
-                            map.insert(key, Object::new(Value::Integer(v), exp));
-                            Ok(v)
-                        },
-                    )
+                    let v = operation(i).ok_or(ClientError::OverflowError)?;
+                    obj.value = Value::Integer(v);
+                    Ok(v)
                 }
                 _ => Err(ClientError::IntegerError),
             },
@@ -61,28 +52,27 @@ impl Integer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::{
-        sync::Mutex,
-        time::{Duration, SystemTime},
+    use crate::{
+        clock,
+        db::{Object, Value},
     };
-    use indexmap::IndexMap;
-    use crate::db::{Value, Object};
+    use std::time::Duration;
 
     #[test]
     fn incr_new_key() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Database::default();
         let result = Integer::Incr.execute(&db, "counter".into());
         assert_eq!(result, Ok(1));
     }
 
     #[test]
     fn incr_expired_key() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(
                 Value::Integer(5),
-                Some(SystemTime::now() - Duration::from_secs(10))
+                Some(clock::now() - Duration::from_secs(10)),
             ),
         );
         let result = Integer::Incr.execute(&db, "counter".into());
@@ -91,8 +81,8 @@ mod tests {
 
     #[test]
     fn incr_existing_integer() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::Integer(5), None)
         );
@@ -102,8 +92,8 @@ mod tests {
 
     #[test]
     fn incr_overflow() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::Integer(i64::MAX), None)
         );
@@ -113,8 +103,8 @@ mod tests {
 
     #[test]
     fn incr_non_integer_value() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::String("foo".into()), None)
         );
@@ -124,26 +114,26 @@ mod tests {
 
     #[test]
     fn incr_multiple_times() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Database::default();
         assert_eq!(Integer::Incr.execute(&db, "counter".into()), Ok(1));
         assert_eq!(Integer::Incr.execute(&db, "counter".into()), Ok(2));
     }
 
     #[test]
     fn decr_new_key() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Database::default();
         let result = Integer::Decr.execute(&db, "counter".into());
         assert_eq!(result, Ok(-1));
     }
 
     #[test]
     fn decr_expired_key() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(
                 Value::Integer(5),
-                Some(SystemTime::now() - Duration::from_secs(10))
+                Some(clock::now() - Duration::from_secs(10)),
             ),
         );
         let result = Integer::Decr.execute(&db, "counter".into());
@@ -152,8 +142,8 @@ mod tests {
 
     #[test]
     fn decr_existing_integer() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::Integer(5), None)
         );
@@ -163,8 +153,8 @@ mod tests {
 
     #[test]
     fn decr_underflow() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::Integer(i64::MIN), None)
         );
@@ -174,8 +164,8 @@ mod tests {
 
     #[test]
     fn decr_non_integer_value() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::String("foo".into()), None)
         );
@@ -185,33 +175,33 @@ mod tests {
 
     #[test]
     fn decr_multiple_times() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Database::default();
         assert_eq!(Integer::Decr.execute(&db, "counter".into()), Ok(-1));
         assert_eq!(Integer::Decr.execute(&db, "counter".into()), Ok(-2));
     }
 
     #[test]
     fn incrby_ok() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Database::default();
         let result = Integer::IncrBy(100).execute(&db, "counter".into());
         assert_eq!(result, Ok(100));
     }
 
     #[test]
     fn incrby_negative_ok() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Database::default();
         let result = Integer::IncrBy(-100).execute(&db, "counter".into());
         assert_eq!(result, Ok(-100));
     }
 
     #[test]
     fn incrby_expired_key() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(
                 Value::Integer(5),
-                Some(SystemTime::now() - Duration::from_secs(10))
+                Some(clock::now() - Duration::from_secs(10)),
             ),
         );
         let result = Integer::IncrBy(10).execute(&db, "counter".into());
@@ -220,8 +210,8 @@ mod tests {
 
     #[test]
     fn incrby_existing_integer() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::Integer(5), None)
         );
@@ -231,8 +221,8 @@ mod tests {
 
     #[test]
     fn incrby_negative_existing_integer() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::Integer(5), None)
         );
@@ -242,8 +232,8 @@ mod tests {
 
     #[test]
     fn incrby_overflow() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::Integer(i64::MAX), None)
         );
@@ -253,8 +243,8 @@ mod tests {
 
     #[test]
     fn incrby_underflow() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::Integer(i64::MIN), None)
         );
@@ -264,8 +254,8 @@ mod tests {
 
     #[test]
     fn incrby_non_integer_value() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::String("foo".into()), None)
         );
@@ -275,40 +265,40 @@ mod tests {
 
     #[test]
     fn incrby_multiple_times() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Database::default();
         assert_eq!(Integer::IncrBy(10).execute(&db, "counter".into()), Ok(10));
         assert_eq!(Integer::IncrBy(10).execute(&db, "counter".into()), Ok(20));
     }
 
     #[test]
     fn incrby_negative_multiple_times() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Database::default();
         assert_eq!(Integer::DecrBy(10).execute(&db, "counter".into()), Ok(-10));
         assert_eq!(Integer::DecrBy(10).execute(&db, "counter".into()), Ok(-20));
     }
 
     #[test]
     fn decrby_ok() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Database::default();
         let result = Integer::DecrBy(100).execute(&db, "counter".into());
         assert_eq!(result, Ok(-100));
     }
 
     #[test]
     fn decrby_negative_ok() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Database::default();
         let result = Integer::DecrBy(-100).execute(&db, "counter".into());
         assert_eq!(result, Ok(100));
     }
 
     #[test]
     fn decrby_expired_key() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(
                 Value::Integer(5),
-                Some(SystemTime::now() - Duration::from_secs(10))
+                Some(clock::now() - Duration::from_secs(10)),
             ),
         );
         let result = Integer::DecrBy(10).execute(&db, "counter".into());
@@ -317,8 +307,8 @@ mod tests {
 
     #[test]
     fn decrby_existing_integer() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::Integer(5), None)
         );
@@ -328,8 +318,8 @@ mod tests {
 
     #[test]
     fn decrby_negative_existing_integer() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::Integer(5), None)
         );
@@ -339,8 +329,8 @@ mod tests {
 
     #[test]
     fn decrby_underflow() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::Integer(i64::MIN), None)
         );
@@ -350,8 +340,8 @@ mod tests {
 
     #[test]
     fn decrby_overflow() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::Integer(i64::MAX), None)
         );
@@ -361,8 +351,8 @@ mod tests {
 
     #[test]
     fn decrby_non_integer_value() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
+        let db = Database::default();
+        db.lock(b"counter").insert(
             "counter".into(),
             Object::new(Value::String("foo".into()), None)
         );
@@ -372,14 +362,14 @@ mod tests {
 
     #[test]
     fn decrby_multiple_times() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Database::default();
         assert_eq!(Integer::DecrBy(10).execute(&db, "counter".into()), Ok(-10));
         assert_eq!(Integer::DecrBy(10).execute(&db, "counter".into()), Ok(-20));
     }
 
     #[test]
     fn decrby_negative_multiple_times() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Database::default();
         assert_eq!(Integer::DecrBy(-10).execute(&db, "counter".into()), Ok(10));
         assert_eq!(Integer::DecrBy(-10).execute(&db, "counter".into()), Ok(20));
     }
@@ -0,0 +1,211 @@
+use bytes::Bytes;
+
+use crate::cmd::{
+    error::ClientError,
+    parser::command::Command,
+    registry::{self, COMMANDS, CommandSpec},
+    response::Response,
+};
+
+impl Command {
+    pub fn execute(self) -> Result<Response, ClientError> {
+        match self {
+            Self::All => Ok(Response::Array(COMMANDS.iter().map(info).collect())),
+            Self::Count => Ok(Response::Integer(COMMANDS.len() as i64)),
+            Self::Info(names) if names.is_empty() => {
+                Ok(Response::Array(COMMANDS.iter().map(info).collect()))
+            }
+            Self::Info(names) => Ok(Response::Array(
+                names
+                    .iter()
+                    .map(|n| lookup(n).map_or(Response::Null, info))
+                    .collect(),
+            )),
+            Self::Docs(names) if names.is_empty() => {
+                Ok(Response::Map(COMMANDS.iter().map(docs).collect()))
+            }
+            Self::Docs(names) => Ok(Response::Map(
+                names.iter().filter_map(|n| lookup(n)).map(docs).collect(),
+            )),
+            Self::GetKeys(params) => get_keys(params),
+        }
+    }
+}
+
+fn lookup(name: &Bytes) -> Option<&'static CommandSpec> {
+    registry::lookup(&String::from_utf8_lossy(name).to_lowercase())
+}
+
+fn bulk(s: &'static str) -> Response {
+    Response::BulkString(Bytes::from(s))
+}
+
+/// Describes a command the way `COMMAND INFO` does.
+fn info(spec: &CommandSpec) -> Response {
+    Response::Array(vec![
+        bulk(spec.name),
+        Response::Integer(spec.arity as i64),
+        Response::Set(
+            spec.flags
+                .iter()
+                .map(|f| Response::SimpleString(f.to_string()))
+                .collect(),
+        ),
+        Response::Integer(spec.first_key as i64),
+        Response::Integer(spec.last_key as i64),
+        Response::Integer(spec.step as i64),
+        Response::Set(
+            spec.categories
+                .iter()
+                .map(|c| Response::SimpleString(c.to_string()))
+                .collect(),
+        ),
+        // tips
+        Response::Array(vec![]),
+        // key specifications
+        Response::Array(vec![]),
+        Response::Array(spec.subcommands.iter().map(info).collect()),
+    ])
+}
+
+/// Documents a command the way `COMMAND DOCS` does.
+fn docs(spec: &CommandSpec) -> (Response, Response) {
+    let mut fields = vec![
+        (bulk("summary"), bulk(spec.summary)),
+        (bulk("since"), bulk(spec.since)),
+        (bulk("group"), bulk(spec.group)),
+    ];
+    if !spec.subcommands.is_empty() {
+        fields.push((
+            bulk("subcommands"),
+            Response::Map(spec.subcommands.iter().map(docs).collect()),
+        ));
+    }
+    (bulk(spec.name), Response::Map(fields))
+}
+
+fn get_keys(params: Vec<Bytes>) -> Result<Response, ClientError> {
+    let spec = lookup(&params[0]).ok_or(ClientError::InvalidCommandSpecified)?;
+    spec.check_arity(params.len())
+        .map_err(|_| ClientError::InvalidArgumentsSpecified)?;
+
+    let keys = spec.keys_of(&params);
+    if keys.is_empty() {
+        return Err(ClientError::NoKeyArguments);
+    }
+    Ok(Response::Array(
+        keys.into_iter()
+            .cloned()
+            .map(Response::BulkString)
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::parser::to_params;
+
+    use super::*;
+
+    #[test]
+    fn count() {
+        assert_eq!(
+            Command::Count.execute().unwrap(),
+            Response::Integer(COMMANDS.len() as i64)
+        );
+    }
+
+    #[test]
+    fn info_get() {
+        let Response::Array(replies) = Command::Info(to_params(&["GET"])).execute().unwrap() else {
+            panic!("expected an array");
+        };
+        let Response::Array(fields) = &replies[0] else {
+            panic!("expected an array");
+        };
+        assert_eq!(fields.len(), 10);
+        assert_eq!(fields[0], bulk("get"));
+        assert_eq!(fields[1], Response::Integer(2));
+        assert_eq!(
+            fields[2],
+            Response::Set(vec![
+                Response::SimpleString("readonly".to_string()),
+                Response::SimpleString("fast".to_string()),
+            ])
+        );
+        assert_eq!(
+            fields[3..6],
+            [
+                Response::Integer(1),
+                Response::Integer(1),
+                Response::Integer(1)
+            ]
+        );
+    }
+
+    #[test]
+    fn info_unknown() {
+        assert_eq!(
+            Command::Info(to_params(&["foo"])).execute().unwrap(),
+            Response::Array(vec![Response::Null])
+        );
+    }
+
+    #[test]
+    fn info_all() {
+        let Response::Array(replies) = Command::Info(vec![]).execute().unwrap() else {
+            panic!("expected an array");
+        };
+        assert_eq!(replies.len(), COMMANDS.len());
+    }
+
+    #[test]
+    fn docs_skips_unknown() {
+        let Response::Map(docs) = Command::Docs(to_params(&["get", "foo"])).execute().unwrap()
+        else {
+            panic!("expected a map");
+        };
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].0, bulk("get"));
+    }
+
+    #[test]
+    fn get_keys_ok() {
+        assert_eq!(
+            Command::GetKeys(to_params(&["del", "a", "b"]))
+                .execute()
+                .unwrap(),
+            Response::Array(vec![bulk("a"), bulk("b")])
+        );
+    }
+
+    #[test]
+    fn get_keys_invalid_command() {
+        assert_eq!(
+            Command::GetKeys(to_params(&["foo", "a"]))
+                .execute()
+                .unwrap_err(),
+            ClientError::InvalidCommandSpecified
+        );
+    }
+
+    #[test]
+    fn get_keys_invalid_arguments() {
+        assert_eq!(
+            Command::GetKeys(to_params(&["get", "a", "b"]))
+                .execute()
+                .unwrap_err(),
+            ClientError::InvalidArgumentsSpecified
+        );
+    }
+
+    #[test]
+    fn get_keys_no_keys() {
+        assert_eq!(
+            Command::GetKeys(to_params(&["ping"]))
+                .execute()
+                .unwrap_err(),
+            ClientError::NoKeyArguments
+        );
+    }
+}
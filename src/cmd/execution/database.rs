@@ -0,0 +1,138 @@
+use crate::{
+    cmd::{
+        error::ClientError,
+        parser::database::{Flush, Move, SwapDb},
+    },
+    db::{Databases, Keyspace, detach},
+    lazyfree,
+};
+
+impl Move {
+    /// Moves the key from the database at `from`, returning whether it has been: it isn't when
+    /// it doesn't exist or the target database already has that key.
+    pub fn execute(self, dbs: &Databases, from: usize) -> Result<bool, ClientError> {
+        let to = dbs
+            .checked_index(self.db)
+            .ok_or(ClientError::DbIndexOutOfRange)?;
+        if to == from {
+            return Err(ClientError::SameObject);
+        }
+
+        let (mut source, mut target) = dbs.lock_pair(from, &self.key, to, &self.key);
+        if source.peek(&self.key).is_none() || target.peek(&self.key).is_some() {
+            return Ok(false);
+        }
+        let object = source.swap_remove(&self.key).expect("the key is live");
+        target.insert(detach(self.key), object);
+        Ok(true)
+    }
+}
+
+impl SwapDb {
+    pub fn execute(self, dbs: &Databases) -> Result<(), ClientError> {
+        let first = dbs.checked_index(self.first);
+        let second = dbs.checked_index(self.second);
+        let (Some(first), Some(second)) = (first, second) else {
+            return Err(ClientError::DbIndexOutOfRange);
+        };
+        dbs.swap(first, second);
+        Ok(())
+    }
+}
+
+impl Flush {
+    /// Frees the keys removed by a flush, on the lazyfree thread with `ASYNC` so that the client
+    /// doesn't wait for big databases to be deallocated.
+    pub fn free(self, removed: Vec<Keyspace>) {
+        match self {
+            Self::Sync => drop(removed),
+            Self::Async => {
+                let keys = removed.iter().map(Keyspace::len).sum::<usize>();
+                let memory = removed.iter().map(Keyspace::used_memory).sum();
+                lazyfree::free_later(removed, keys as u64, memory);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::db::{Object, Settings, Value};
+
+    use super::*;
+
+    fn dbs_with(key: &str, db: usize) -> Databases {
+        let dbs = Databases::new(3, 4, Settings::default());
+        dbs[db].lock(key.as_bytes()).insert(
+            Bytes::from(key.to_string()),
+            Object::new(Value::Integer(db as i64), None),
+        );
+        dbs
+    }
+
+    fn move_to(key: &str, db: i64) -> Move {
+        Move {
+            key: Bytes::from(key.to_string()),
+            db,
+        }
+    }
+
+    #[test]
+    fn move_ok() {
+        let dbs = dbs_with("key", 0);
+        assert_eq!(move_to("key", 2).execute(&dbs, 0), Ok(true));
+        assert!(dbs[0].is_empty());
+        assert_eq!(
+            dbs[2].lock(b"key").get(b"key").unwrap().value,
+            Value::Integer(0)
+        );
+
+        assert_eq!(move_to("key", 1).execute(&dbs, 2), Ok(true));
+        assert_eq!(dbs[1].len(), 1);
+    }
+
+    #[test]
+    fn move_not_moved() {
+        let dbs = dbs_with("key", 0);
+        assert_eq!(move_to("other", 1).execute(&dbs, 0), Ok(false));
+
+        dbs[1]
+            .lock(b"key")
+            .insert(Bytes::from("key"), Object::new(Value::Integer(1), None));
+        assert_eq!(move_to("key", 1).execute(&dbs, 0), Ok(false));
+        assert_eq!(dbs[0].len(), 1);
+    }
+
+    #[test]
+    fn move_invalid_db() {
+        let dbs = dbs_with("key", 0);
+        assert_eq!(
+            move_to("key", 3).execute(&dbs, 0),
+            Err(ClientError::DbIndexOutOfRange)
+        );
+        assert_eq!(
+            move_to("key", 0).execute(&dbs, 0),
+            Err(ClientError::SameObject)
+        );
+    }
+
+    #[test]
+    fn swapdb_ok() {
+        let dbs = dbs_with("key", 0);
+        let swap = SwapDb {
+            first: 0,
+            second: 1,
+        };
+        assert_eq!(swap.execute(&dbs), Ok(()));
+        assert!(dbs[0].is_empty());
+        assert_eq!(dbs[1].len(), 1);
+
+        let swap = SwapDb {
+            first: 1,
+            second: -1,
+        };
+        assert_eq!(swap.execute(&dbs), Err(ClientError::DbIndexOutOfRange));
+    }
+}
@@ -0,0 +1,312 @@
+use bytes::Bytes;
+
+use crate::{
+    clock::{self, from_unix_ms, to_unix_ms},
+    cmd::{
+        error::ClientError,
+        parser::expire::Expire as ExpireParser,
+        types::{EXPIRE, EXPIREAT, PEXPIRE, PEXPIREAT},
+    },
+    db::Database,
+};
+
+pub enum SetExpiration {
+    Expire,
+    PExpire,
+    ExpireAt,
+    PExpireAt,
+}
+
+impl SetExpiration {
+    /// Sets the expiration of a key, deleting it if the time is already in the past.
+    ///
+    /// Returns whether the key exists and the condition allowed the change.
+    pub fn execute(&self, db: &Database, parser: ExpireParser) -> Result<bool, ClientError> {
+        let now = to_unix_ms(clock::now());
+        let deadline = self
+            .deadline(parser.when, now)
+            .ok_or_else(|| ClientError::InvalidExpireTime(self.name().to_string()))?;
+
+        let mut map = db.lock(&parser.key);
+        let Some(o) = map.live(&parser.key) else {
+            return Ok(false);
+        };
+        if !parser
+            .condition
+            .allows(o.expiration().map(to_unix_ms), deadline)
+        {
+            return Ok(false);
+        }
+
+        if deadline <= now {
+            map.swap_remove(&parser.key);
+        } else {
+            map.set_expiration(&parser.key, Some(from_unix_ms(deadline)));
+        }
+        Ok(true)
+    }
+
+    /// Converts the time given by the client to milliseconds since the Unix epoch, `None` if
+    /// it overflows.
+    fn deadline(&self, when: i64, now: i64) -> Option<i64> {
+        match self {
+            SetExpiration::Expire => when.checked_mul(1000)?.checked_add(now),
+            SetExpiration::PExpire => when.checked_add(now),
+            SetExpiration::ExpireAt => when.checked_mul(1000),
+            SetExpiration::PExpireAt => Some(when),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SetExpiration::Expire => EXPIRE,
+            SetExpiration::PExpire => PEXPIRE,
+            SetExpiration::ExpireAt => EXPIREAT,
+            SetExpiration::PExpireAt => PEXPIREAT,
+        }
+    }
+}
+
+/// Queries on the expiration of a key, which reply `-2` when the key doesn't exist and `-1`
+/// when it has no expiration.
+pub enum GetExpiration {
+    Ttl,
+    PTtl,
+    ExpireTime,
+    PExpireTime,
+}
+
+impl GetExpiration {
+    pub fn execute(&self, db: &Database, key: Bytes) -> i64 {
+        let mut map = db.lock(&key);
+        let Some(o) = map.peek(&key) else {
+            return -2;
+        };
+        let Some(expiration) = o.expiration() else {
+            return -1;
+        };
+
+        let deadline = to_unix_ms(expiration);
+        let remaining = (deadline - to_unix_ms(clock::now())).max(0);
+        // seconds are rounded to the nearest, as in Redis
+        match self {
+            GetExpiration::Ttl => (remaining + 500) / 1000,
+            GetExpiration::PTtl => remaining,
+            GetExpiration::ExpireTime => (deadline + 500) / 1000,
+            GetExpiration::PExpireTime => deadline,
+        }
+    }
+}
+
+/// Removes the expiration of a key, returning whether it had one.
+pub fn persist(db: &Database, key: Bytes) -> bool {
+    let mut map = db.lock(&key);
+    if map.live(&key).is_none_or(|o| o.expiration().is_none()) {
+        return false;
+    }
+    map.set_expiration(&key, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    use crate::{
+        clock::MockClock,
+        cmd::parser::expire::Condition,
+        db::{Object, Value},
+    };
+
+    use super::*;
+
+    fn db_with(key: &str, expiration: Option<SystemTime>) -> Database {
+        let db = Database::default();
+        db.lock(key.as_bytes()).insert(
+            Bytes::copy_from_slice(key.as_bytes()),
+            Object::new(Value::String(Bytes::from("value")), expiration),
+        );
+        db
+    }
+
+    fn parser(when: i64, condition: Condition) -> ExpireParser {
+        ExpireParser {
+            key: Bytes::from("key"),
+            when,
+            condition,
+        }
+    }
+
+    #[test]
+    fn expire_missing_key() {
+        let db = Database::default();
+        let result = SetExpiration::Expire.execute(&db, parser(10, Condition::default()));
+        assert_eq!(result, Ok(false));
+    }
+
+    #[test]
+    fn expire_ok() {
+        let db = db_with("key", None);
+        let result = SetExpiration::Expire.execute(&db, parser(100, Condition::default()));
+        assert_eq!(result, Ok(true));
+
+        let ttl = GetExpiration::Ttl.execute(&db, Bytes::from("key"));
+        assert_eq!(ttl, 100);
+    }
+
+    #[test]
+    fn expire_list() {
+        let db = Database::default();
+        db.lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::List(VecDeque::from([Bytes::from("a")])), None),
+        );
+        let result = SetExpiration::PExpire.execute(&db, parser(100_000, Condition::default()));
+        assert_eq!(result, Ok(true));
+        assert!(GetExpiration::PTtl.execute(&db, Bytes::from("key")) > 99_000);
+    }
+
+    #[test]
+    fn expire_elapses() {
+        let clock = MockClock::at(UNIX_EPOCH + Duration::from_secs(1_000));
+        let db = db_with("key", None);
+        SetExpiration::PExpire
+            .execute(&db, parser(1_500, Condition::default()))
+            .unwrap();
+
+        clock.advance(Duration::from_millis(1_000));
+        assert_eq!(GetExpiration::PTtl.execute(&db, Bytes::from("key")), 500);
+        assert_eq!(GetExpiration::Ttl.execute(&db, Bytes::from("key")), 1);
+        assert_eq!(
+            GetExpiration::ExpireTime.execute(&db, Bytes::from("key")),
+            1_002
+        );
+
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(GetExpiration::PTtl.execute(&db, Bytes::from("key")), 0);
+
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(GetExpiration::PTtl.execute(&db, Bytes::from("key")), -2);
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn expire_negative_deletes() {
+        let db = db_with("key", None);
+        let result = SetExpiration::Expire.execute(&db, parser(-1, Condition::default()));
+        assert_eq!(result, Ok(true));
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn expireat_past_deletes() {
+        let db = db_with("key", None);
+        let result = SetExpiration::ExpireAt.execute(&db, parser(1, Condition::default()));
+        assert_eq!(result, Ok(true));
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn expire_condition_not_met() {
+        let db = db_with("key", None);
+        let xx = Condition {
+            xx: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            SetExpiration::Expire.execute(&db, parser(-1, xx)),
+            Ok(false)
+        );
+        assert_eq!(GetExpiration::Ttl.execute(&db, Bytes::from("key")), -1);
+    }
+
+    #[test]
+    fn expire_gt() {
+        let db = db_with("key", Some(clock::now() + Duration::from_secs(100)));
+        let gt = || Condition {
+            gt: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            SetExpiration::Expire.execute(&db, parser(50, gt())),
+            Ok(false)
+        );
+        assert_eq!(
+            SetExpiration::Expire.execute(&db, parser(200, gt())),
+            Ok(true)
+        );
+        assert_eq!(GetExpiration::Ttl.execute(&db, Bytes::from("key")), 200);
+    }
+
+    #[test]
+    fn expire_overflow() {
+        let db = db_with("key", None);
+        assert_eq!(
+            SetExpiration::Expire.execute(&db, parser(i64::MAX / 10, Condition::default())),
+            Err(ClientError::InvalidExpireTime(EXPIRE.to_string()))
+        );
+        assert_eq!(
+            SetExpiration::PExpire.execute(&db, parser(i64::MAX, Condition::default())),
+            Err(ClientError::InvalidExpireTime(PEXPIRE.to_string()))
+        );
+    }
+
+    #[test]
+    fn pexpireat_ok() {
+        let db = db_with("key", None);
+        let deadline = to_unix_ms(clock::now()) + 100_000;
+        let result = SetExpiration::PExpireAt.execute(&db, parser(deadline, Condition::default()));
+        assert_eq!(result, Ok(true));
+        assert_eq!(
+            GetExpiration::PExpireTime.execute(&db, Bytes::from("key")),
+            deadline
+        );
+        assert_eq!(
+            GetExpiration::ExpireTime.execute(&db, Bytes::from("key")),
+            (deadline + 500) / 1000
+        );
+    }
+
+    #[test]
+    fn ttl_missing_key() {
+        let db = Database::default();
+        assert_eq!(GetExpiration::Ttl.execute(&db, Bytes::from("key")), -2);
+        assert_eq!(
+            GetExpiration::PExpireTime.execute(&db, Bytes::from("key")),
+            -2
+        );
+    }
+
+    #[test]
+    fn ttl_expired_key() {
+        let db = db_with("key", Some(clock::now() - Duration::from_secs(1)));
+        assert_eq!(GetExpiration::PTtl.execute(&db, Bytes::from("key")), -2);
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn ttl_no_expiration() {
+        let db = db_with("key", None);
+        assert_eq!(GetExpiration::Ttl.execute(&db, Bytes::from("key")), -1);
+        assert_eq!(
+            GetExpiration::ExpireTime.execute(&db, Bytes::from("key")),
+            -1
+        );
+    }
+
+    #[test]
+    fn persist_ok() {
+        let db = db_with("key", Some(clock::now() + Duration::from_secs(100)));
+        assert!(persist(&db, Bytes::from("key")));
+        assert_eq!(GetExpiration::Ttl.execute(&db, Bytes::from("key")), -1);
+        assert!(!persist(&db, Bytes::from("key")));
+    }
+
+    #[test]
+    fn persist_missing_key() {
+        let db = Database::default();
+        assert!(!persist(&db, Bytes::from("key")));
+    }
+}
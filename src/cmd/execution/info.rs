@@ -0,0 +1,153 @@
+use std::sync::atomic::Ordering;
+
+use bytes::Bytes;
+
+use crate::{db::Databases, evict::Evictor, lazyfree, memory, stats::Stats};
+
+/// Sections of `INFO`, by name and title, in the order they're listed.
+const SECTIONS: &[(&str, &str)] = &[("memory", "Memory"), ("stats", "Stats")];
+
+/// Builds the text of `INFO`: the requested sections, every one if none is. Unknown sections
+/// are ignored, as in Redis.
+pub fn info(sections: &[Bytes], db: &Databases, stats: &Stats, evictor: &Evictor) -> String {
+    let requested: Vec<String> = sections
+        .iter()
+        .map(|s| String::from_utf8_lossy(s).to_lowercase())
+        .collect();
+    let all = requested.is_empty()
+        || requested
+            .iter()
+            .any(|s| matches!(s.as_str(), "all" | "everything" | "default"));
+
+    SECTIONS
+        .iter()
+        .filter(|(name, _)| all || requested.iter().any(|r| r == name))
+        .map(|(name, title)| {
+            let mut text = format!("# {title}\r\n");
+            for (field, value) in fields(name, db, stats, evictor) {
+                text.push_str(&format!("{field}:{value}\r\n"));
+            }
+            text
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+fn fields(
+    section: &str,
+    db: &Databases,
+    stats: &Stats,
+    evictor: &Evictor,
+) -> Vec<(&'static str, String)> {
+    match section {
+        "memory" => vec![
+            ("used_memory", memory::used_memory().to_string()),
+            ("used_memory_peak", memory::peak_memory().to_string()),
+            ("used_memory_dataset", db.used_memory().to_string()),
+            ("maxmemory", evictor.maxmemory().to_string()),
+            ("maxmemory_policy", evictor.policy().name().to_string()),
+            (
+                "lazyfree_pending_objects",
+                lazyfree::pending_objects().to_string(),
+            ),
+        ],
+        "stats" => vec![
+            ("expired_keys", db.expired_keys().to_string()),
+            (
+                "expired_stale_perc",
+                format!("{:.2}", stats.expired_stale_ratio() * 100.0),
+            ),
+            (
+                "expire_cycle_cpu_milliseconds",
+                (stats.expire_cycle_cpu_micros.load(Ordering::Relaxed) / 1000).to_string(),
+            ),
+            (
+                "evicted_keys",
+                stats.evicted_keys.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "client_query_buffer_limit_disconnections",
+                stats
+                    .query_buffer_limit_disconnections
+                    .load(Ordering::Relaxed)
+                    .to_string(),
+            ),
+            ("lazyfreed_objects", lazyfree::freed_objects().to_string()),
+        ],
+        s => unreachable!("section '{s}' is listed but not handled"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{cmd::parser::to_params, config::Config, evict::Policy};
+
+    use super::*;
+
+    #[test]
+    fn info_stats() {
+        let db = Databases::default();
+        let stats = Stats::default();
+        stats.record_expire_cycle(Duration::from_millis(3), 20, 10);
+
+        let text = info(&to_params(&["STATS"]), &db, &stats, &Evictor::default());
+        assert!(text.starts_with("# Stats\r\n"));
+        assert!(text.contains("\r\nexpired_keys:0\r\n"));
+        assert!(text.contains("\r\nexpired_stale_perc:2.50\r\n"));
+        assert!(text.contains("\r\nexpire_cycle_cpu_milliseconds:3\r\n"));
+        assert!(text.contains("\r\nevicted_keys:0\r\n"));
+    }
+
+    /// Names of the fields of `text`, whose values may change between calls.
+    fn field_names(text: &str) -> Vec<&str> {
+        text.lines()
+            .map(|line| line.split(':').next().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn info_all_sections() {
+        let db = Databases::default();
+        let stats = Stats::default();
+        let evictor = Evictor::default();
+        let text = info(&[], &db, &stats, &evictor);
+
+        for sections in [["all"], ["default"]] {
+            let all = info(&to_params(&sections), &db, &stats, &evictor);
+            assert_eq!(field_names(&all), field_names(&text));
+        }
+        assert!(text.starts_with("# Memory\r\n"));
+        assert!(text.contains("\r\n\r\n# Stats\r\n"));
+    }
+
+    #[test]
+    fn info_memory() {
+        let db = Databases::default();
+        let evictor = Evictor::new(&Config {
+            maxmemory: 1024,
+            maxmemory_policy: Policy::AllKeysLfu,
+            ..Default::default()
+        });
+        let text = info(&to_params(&["memory"]), &db, &Stats::default(), &evictor);
+        assert!(text.starts_with("# Memory\r\nused_memory:"));
+        assert!(text.contains("\r\nused_memory_dataset:0\r\n"));
+        assert!(text.contains("\r\nmaxmemory:1024\r\n"));
+        assert!(text.contains("\r\nmaxmemory_policy:allkeys-lfu\r\n"));
+        assert!(text.contains("\r\nlazyfree_pending_objects:"));
+    }
+
+    #[test]
+    fn info_unknown_section() {
+        assert_eq!(
+            info(
+                &to_params(&["foo"]),
+                &Databases::default(),
+                &Stats::default(),
+                &Evictor::default()
+            ),
+            ""
+        );
+    }
+}
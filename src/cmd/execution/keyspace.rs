@@ -0,0 +1,287 @@
+use bytes::Bytes;
+
+use crate::{
+    cmd::{error::ClientError, parser::keyspace::CopyKey},
+    db::{Database, Databases, Keyspace, Object, detach},
+    lazyfree,
+};
+
+/// Returns the name of the type of the value of `key`, `none` if it doesn't exist.
+pub fn type_of(db: &Database, key: &[u8]) -> &'static str {
+    db.lock(key)
+        .peek(key)
+        .map_or("none", |o| o.value.kind().name())
+}
+
+/// Renames `key` to `new_key`, keeping its expiration. An existing `new_key` is overwritten,
+/// unless `nx` is set.
+///
+/// Returns whether the key has been renamed, which it isn't when `nx` prevents it.
+pub fn rename(db: &Database, key: Bytes, new_key: Bytes, nx: bool) -> Result<bool, ClientError> {
+    let mut shards = db.lock_keys(&[key.clone(), new_key.clone()]);
+    if shards.shard(&key).peek(&key).is_none() {
+        return Err(ClientError::NoSuchKey);
+    }
+    if key == new_key {
+        // nothing to do, but `RENAMENX` reports that the new key exists
+        return Ok(!nx);
+    }
+    if nx && shards.shard(&new_key).peek(&new_key).is_some() {
+        return Ok(false);
+    }
+
+    let object = shards
+        .shard(&key)
+        .swap_remove(&key)
+        .expect("the key is live");
+    shards.shard(&new_key).insert(detach(new_key), object);
+    Ok(true)
+}
+
+/// Records an access to each of `keys`, returning the number of keys that exist.
+pub fn touch(db: &Database, keys: &[Bytes]) -> usize {
+    let mut shards = db.lock_keys(keys);
+    keys.iter()
+        .filter(|k| shards.shard(k).live(k).is_some())
+        .count()
+}
+
+/// Removes `keys` like `DEL`, but frees their values once the shards are unlocked, in the
+/// background for the big ones. Returns the number of keys removed.
+pub fn unlink(db: &Database, keys: &[Bytes]) -> usize {
+    let removed: Vec<Object> = {
+        let mut shards = db.lock_keys(keys);
+        keys.iter()
+            .filter_map(|k| shards.shard(k).remove_live(k))
+            .collect()
+    };
+
+    let unlinked = removed.len();
+    removed.into_iter().for_each(lazyfree::free);
+    unlinked
+}
+
+impl CopyKey {
+    /// Copies the value of the source key of the database at `current`, along with its
+    /// expiration, returning whether it has been: it isn't when the source doesn't exist, or
+    /// when the destination does and `REPLACE` isn't given.
+    pub fn execute(self, dbs: &Databases, current: usize) -> Result<bool, ClientError> {
+        let to = match self.db {
+            None => current,
+            Some(db) => dbs
+                .checked_index(db)
+                .ok_or(ClientError::DbIndexOutOfRange)?,
+        };
+
+        if to == current {
+            if self.source == self.destination {
+                return Err(ClientError::SameObject);
+            }
+            let keys = [self.source.clone(), self.destination.clone()];
+            let mut shards = dbs[current].lock_keys(&keys);
+            let object = shards.shard(&self.source).live(&self.source).cloned();
+            Ok(copy_to(
+                shards.shard(&self.destination),
+                self.destination,
+                object,
+                self.replace,
+            ))
+        } else {
+            let (mut source, mut target) =
+                dbs.lock_pair(current, &self.source, to, &self.destination);
+            let object = source.live(&self.source).cloned();
+            Ok(copy_to(&mut target, self.destination, object, self.replace))
+        }
+    }
+}
+
+fn copy_to(target: &mut Keyspace, key: Bytes, object: Option<Object>, replace: bool) -> bool {
+    let Some(object) = object else {
+        return false;
+    };
+    if !replace && target.peek(&key).is_some() {
+        return false;
+    }
+    target.insert(detach(key), object);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use crate::{
+        clock::{self, MockClock},
+        db::{Settings, Value},
+    };
+
+    use super::*;
+
+    fn insert(db: &Database, key: &str, value: Value, expiration: Option<Duration>) {
+        db.lock(key.as_bytes()).insert(
+            Bytes::from(key.to_string()),
+            Object::new(value, expiration.map(|e| clock::now() + e)),
+        );
+    }
+
+    fn value(db: &Database, key: &str) -> Option<Value> {
+        db.lock(key.as_bytes())
+            .live(key.as_bytes())
+            .map(|o| o.value.clone())
+    }
+
+    fn copy(source: &str, destination: &str, db: Option<i64>, replace: bool) -> CopyKey {
+        CopyKey {
+            source: Bytes::from(source.to_string()),
+            destination: Bytes::from(destination.to_string()),
+            db,
+            replace,
+        }
+    }
+
+    #[test]
+    fn type_of_values() {
+        let db = Database::default();
+        insert(&db, "s", Value::Integer(1), None);
+        insert(&db, "l", Value::List(VecDeque::new()), None);
+
+        assert_eq!(type_of(&db, b"s"), "string");
+        assert_eq!(type_of(&db, b"l"), "list");
+        assert_eq!(type_of(&db, b"missing"), "none");
+    }
+
+    #[test]
+    fn rename_keeps_expiration() {
+        let settings = Settings {
+            expiry_index: true,
+            ..Default::default()
+        };
+        let db = Database::new(4, settings);
+        insert(&db, "a", Value::Integer(1), Some(Duration::from_secs(100)));
+        insert(&db, "b", Value::Integer(2), None);
+
+        assert_eq!(rename(&db, "a".into(), "b".into(), false), Ok(true));
+        assert_eq!(value(&db, "a"), None);
+        assert_eq!(value(&db, "b"), Some(Value::Integer(1)));
+        assert!(db.lock(b"b").get(b"b").unwrap().expiration().is_some());
+        assert_eq!(db.len(), 1);
+    }
+
+    #[test]
+    fn rename_nx() {
+        let db = Database::default();
+        insert(&db, "a", Value::Integer(1), None);
+        insert(&db, "b", Value::Integer(2), None);
+
+        assert_eq!(rename(&db, "a".into(), "b".into(), true), Ok(false));
+        assert_eq!(rename(&db, "a".into(), "c".into(), true), Ok(true));
+        assert_eq!(value(&db, "c"), Some(Value::Integer(1)));
+    }
+
+    #[test]
+    fn rename_same_or_missing_key() {
+        let db = Database::default();
+        insert(&db, "a", Value::Integer(1), None);
+
+        assert_eq!(rename(&db, "a".into(), "a".into(), false), Ok(true));
+        assert_eq!(rename(&db, "a".into(), "a".into(), true), Ok(false));
+        assert_eq!(value(&db, "a"), Some(Value::Integer(1)));
+        assert_eq!(
+            rename(&db, "x".into(), "y".into(), false),
+            Err(ClientError::NoSuchKey)
+        );
+    }
+
+    #[test]
+    fn touch_updates_access_time() {
+        let clock = MockClock::at(UNIX_EPOCH + Duration::from_secs(1_000_000));
+        let db = Database::default();
+        insert(&db, "a", Value::Integer(1), None);
+        insert(&db, "b", Value::Integer(1), None);
+        clock.advance(Duration::from_secs(10));
+
+        let keys = ["a", "missing", "a"].map(Bytes::from);
+        assert_eq!(touch(&db, &keys), 2);
+        assert_eq!(type_of(&db, b"b"), "string");
+        let idle_time = |key: &[u8]| db.lock(key).get(key).unwrap().idle_time();
+        assert_eq!(idle_time(b"a"), Duration::ZERO);
+        assert_eq!(idle_time(b"b"), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn unlink_keys() {
+        let db = Database::default();
+        let list = (0..1000).map(|i| Bytes::from(i.to_string())).collect();
+        insert(&db, "a", Value::List(list), None);
+        insert(&db, "b", Value::Integer(1), None);
+
+        let keys = ["a", "b", "missing", "a"].map(Bytes::from);
+        assert_eq!(unlink(&db, &keys), 2);
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn unlink_expired_key() {
+        let db = Database::default();
+        db.lock(b"a").insert(
+            Bytes::from("a"),
+            Object::new(
+                Value::Integer(1),
+                Some(clock::now() - Duration::from_secs(1)),
+            ),
+        );
+
+        assert_eq!(unlink(&db, &[Bytes::from("a")]), 0);
+        assert!(db.is_empty());
+        assert_eq!(db.expired_keys(), 1);
+    }
+
+    #[test]
+    fn copy_in_database() {
+        let dbs = Databases::default();
+        let list = Value::List(VecDeque::from([Bytes::from("x")]));
+        insert(&dbs[0], "a", list.clone(), Some(Duration::from_secs(100)));
+        insert(&dbs[0], "b", Value::Integer(2), None);
+
+        assert_eq!(copy("a", "b", None, false).execute(&dbs, 0), Ok(false));
+        assert_eq!(copy("a", "b", None, true).execute(&dbs, 0), Ok(true));
+        assert_eq!(copy("missing", "c", None, true).execute(&dbs, 0), Ok(false));
+        assert_eq!(value(&dbs[0], "b"), Some(list.clone()));
+        assert!(dbs[0].lock(b"b").get(b"b").unwrap().expiration().is_some());
+
+        // the copy is independent of the source
+        let mut source = dbs[0].lock(b"a");
+        let Value::List(l) = &mut source.live(b"a").unwrap().value else {
+            panic!("expected a list");
+        };
+        l.push_back(Bytes::from("y"));
+        drop(source);
+        assert_eq!(value(&dbs[0], "b"), Some(list));
+    }
+
+    #[test]
+    fn copy_to_database() {
+        let dbs = Databases::default();
+        insert(&dbs[1], "a", Value::Integer(1), None);
+
+        assert_eq!(copy("a", "a", Some(3), false).execute(&dbs, 1), Ok(true));
+        assert_eq!(value(&dbs[3], "a"), Some(Value::Integer(1)));
+        assert_eq!(value(&dbs[1], "a"), Some(Value::Integer(1)));
+
+        assert_eq!(
+            copy("a", "a", None, false).execute(&dbs, 1),
+            Err(ClientError::SameObject)
+        );
+        assert_eq!(
+            copy("a", "a", Some(1), false).execute(&dbs, 1),
+            Err(ClientError::SameObject)
+        );
+        assert_eq!(
+            copy("a", "b", Some(16), false).execute(&dbs, 1),
+            Err(ClientError::DbIndexOutOfRange)
+        );
+    }
+}
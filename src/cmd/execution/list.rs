@@ -1,13 +1,13 @@
 use std::collections::VecDeque;
 
-use indexmap::map::Entry;
+use bytes::Bytes;
 
 use crate::{
     cmd::error::ClientError,
-    db::{Db, Object, Value},
+    db::{Database, Kind, Object, Value, detach},
 };
 
-type PushOp = Box<dyn Fn(&mut VecDeque<String>, String)>;
+type PushOp = Box<dyn Fn(&mut VecDeque<Bytes>, Bytes)>;
 
 pub enum List {
     LPush,
@@ -15,36 +15,35 @@ pub enum List {
 }
 
 impl List {
-    pub fn execute(&self, db: &Db, key: String, values: Vec<String>) -> Result<usize, ClientError> {
-        let mut map = db.lock().unwrap();
+    pub fn execute(
+        &self,
+        db: &Database,
+        key: Bytes,
+        values: Vec<Bytes>,
+    ) -> Result<usize, ClientError> {
+        let mut map = db.lock(&key);
         let push = self.operation();
 
-        if let Some(o) = map.get(&key) {
-            if o.is_expired() {
-                map.swap_remove(&key);
+        if let Some(Value::List(l)) = map.lookup(&key, Kind::List)?.map(|o| &mut o.value) {
+            let capacity = l.capacity();
+            let mut grown = 0;
+            for v in values {
+                grown += v.len();
+                push(l, detach(v));
             }
+            grown += (l.capacity() - capacity) * size_of::<Bytes>();
+            let len = l.len();
+            map.grow(&key, grown);
+            return Ok(len);
         }
 
-        match map.entry(key) {
-            Entry::Vacant(e) => {
-                let mut l = VecDeque::with_capacity(values.len());
-                for v in values {
-                    push(&mut l, v);
-                }
-                let len = l.len();
-                e.insert(Object::new(Value::List(l), None));
-                Ok(len)
-            }
-            Entry::Occupied(mut e) => match &mut e.get_mut().value {
-                Value::List(l) => {
-                    for v in values {
-                        push(l, v);
-                    }
-                    Ok(l.len())
-                }
-                _ => Err(ClientError::WrongType),
-            },
+        let mut l = VecDeque::with_capacity(values.len());
+        for v in values {
+            push(&mut l, detach(v));
         }
+        let len = l.len();
+        map.insert(detach(key), Object::new(Value::List(l), None));
+        Ok(len)
     }
 
     pub fn operation(&self) -> PushOp {
@@ -58,22 +57,20 @@ impl List {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use indexmap::IndexMap;
-    use std::{
-        sync::Mutex,
-        time::{Duration, SystemTime},
-    };
+    use std::time::Duration;
+
+    use crate::clock;
 
-    fn empty_db() -> Db {
-        Db::new(Mutex::new(IndexMap::new()))
+    fn empty_db() -> Database {
+        Database::default()
     }
 
-    fn assert_list(db: &Db, key: &str, expected: &[&str]) {
-        let map = db.lock().unwrap();
-        match &map.get(key).unwrap().value {
+    fn assert_list(db: &Database, key: &str, expected: &[&str]) {
+        let map = db.lock(key.as_bytes());
+        match &map.get(key.as_bytes()).unwrap().value {
             Value::List(l) => {
-                let got: Vec<&str> = l.iter().map(String::as_str).collect();
-                assert_eq!(got, expected);
+                let expected: Vec<&[u8]> = expected.iter().map(|e| e.as_bytes()).collect();
+                assert_eq!(l, &expected);
             }
             _ => panic!("expected list"),
         }
@@ -102,9 +99,9 @@ mod tests {
     #[test]
     fn lpush_existing_list() {
         let db = empty_db();
-        db.lock().unwrap().insert(
+        db.lock(b"k").insert(
             "k".into(),
-            Object::new(Value::List(VecDeque::from(vec!["x".to_string()])), None),
+            Object::new(Value::List(VecDeque::from(vec![Bytes::from("x")])), None),
         );
         let result = List::LPush.execute(&db, "k".into(), vec!["a".into(), "b".into()]);
         assert_eq!(result, Ok(3));
@@ -114,7 +111,7 @@ mod tests {
     #[test]
     fn lpush_wrong_type_string() {
         let db = empty_db();
-        db.lock().unwrap().insert(
+        db.lock(b"k").insert(
             "k".into(),
             Object::new(Value::String("foo".into()), None),
         );
@@ -125,8 +122,7 @@ mod tests {
     #[test]
     fn lpush_wrong_type_integer() {
         let db = empty_db();
-        db.lock()
-            .unwrap()
+        db.lock(b"k")
             .insert("k".into(), Object::new(Value::Integer(5), None));
         let result = List::LPush.execute(&db, "k".into(), vec!["v".into()]);
         assert_eq!(result, Err(ClientError::WrongType));
@@ -135,11 +131,11 @@ mod tests {
     #[test]
     fn lpush_expired_key() {
         let db = empty_db();
-        db.lock().unwrap().insert(
+        db.lock(b"k").insert(
             "k".into(),
             Object::new(
-                Value::List(VecDeque::from(vec!["old".to_string()])),
-                Some(SystemTime::now() - Duration::from_secs(10)),
+                Value::List(VecDeque::from(vec![Bytes::from("old")])),
+                Some(clock::now() - Duration::from_secs(10)),
             ),
         );
         let result = List::LPush.execute(&db, "k".into(), vec!["new".into()]);
@@ -178,19 +174,38 @@ mod tests {
     #[test]
     fn rpush_existing_list() {
         let db = empty_db();
-        db.lock().unwrap().insert(
+        db.lock(b"k").insert(
             "k".into(),
-            Object::new(Value::List(VecDeque::from(vec!["x".to_string()])), None),
+            Object::new(Value::List(VecDeque::from(vec![Bytes::from("x")])), None),
         );
         let result = List::RPush.execute(&db, "k".into(), vec!["a".into(), "b".into()]);
         assert_eq!(result, Ok(3));
         assert_list(&db, "k", &["x", "a", "b"]);
     }
 
+    #[test]
+    fn push_accounts_for_memory() {
+        let db = empty_db();
+        List::RPush
+            .execute(&db, "k".into(), vec!["a".into()])
+            .unwrap();
+        let values = (0..100).map(|i| Bytes::from(i.to_string())).collect();
+        List::LPush.execute(&db, "k".into(), values).unwrap();
+
+        let map = db.lock(b"k");
+        let object = map.get(b"k").unwrap();
+        let usage = map.memory_usage(b"k").unwrap();
+        assert_eq!(
+            object.memory_usage() - size_of::<Object>(),
+            object.value.memory_usage()
+        );
+        assert_eq!(map.used_memory(), usage);
+    }
+
     #[test]
     fn rpush_wrong_type_string() {
         let db = empty_db();
-        db.lock().unwrap().insert(
+        db.lock(b"k").insert(
             "k".into(),
             Object::new(Value::String("foo".into()), None),
         );
@@ -201,8 +216,7 @@ mod tests {
     #[test]
     fn rpush_wrong_type_integer() {
         let db = empty_db();
-        db.lock()
-            .unwrap()
+        db.lock(b"k")
             .insert("k".into(), Object::new(Value::Integer(5), None));
         let result = List::RPush.execute(&db, "k".into(), vec!["v".into()]);
         assert_eq!(result, Err(ClientError::WrongType));
@@ -211,11 +225,11 @@ mod tests {
     #[test]
     fn rpush_expired_key() {
         let db = empty_db();
-        db.lock().unwrap().insert(
+        db.lock(b"k").insert(
             "k".into(),
             Object::new(
-                Value::List(VecDeque::from(vec!["old".to_string()])),
-                Some(SystemTime::now() - Duration::from_secs(10)),
+                Value::List(VecDeque::from(vec![Bytes::from("old")])),
+                Some(clock::now() - Duration::from_secs(10)),
             ),
         );
         let result = List::RPush.execute(&db, "k".into(), vec!["new".into()]);
@@ -0,0 +1,138 @@
+use bytes::Bytes;
+
+use crate::{
+    cmd::{parser::memory::Memory, response::Response},
+    db::{Database, Databases},
+    memory,
+};
+
+impl Memory {
+    /// Reports on the memory of the key of the database at `current`, or of the whole server.
+    pub fn execute(self, dbs: &Databases, current: usize) -> Response {
+        match self {
+            Self::Usage(key) => {
+                let mut shard = dbs[current].lock(&key);
+                if shard.peek(&key).is_none() {
+                    return Response::Null;
+                }
+                shard
+                    .memory_usage(&key)
+                    .map_or(Response::Null, |m| Response::Integer(m as i64))
+            }
+            Self::Stats => stats(dbs),
+        }
+    }
+}
+
+/// Builds the reply of `MEMORY STATS`, with the fields of Redis the server can measure.
+///
+/// The dataset is what the keyspaces account for, and the overhead the rest of the memory
+/// allocated. Unlike Redis, bytes per key only count the dataset, since there's no measure of the
+/// memory used at startup to leave out.
+fn stats(dbs: &Databases) -> Response {
+    let peak = memory::peak_memory();
+    let total = memory::used_memory();
+    let dataset = dbs.used_memory();
+    let keys = dbs.iter().map(Database::len).sum::<usize>();
+
+    let field = |name: &'static str| Response::BulkString(Bytes::from(name));
+    let bytes = |b: usize| Response::Integer(b as i64);
+    Response::Map(vec![
+        (field("peak.allocated"), bytes(peak)),
+        (field("total.allocated"), bytes(total)),
+        (
+            field("overhead.total"),
+            bytes(total.saturating_sub(dataset)),
+        ),
+        (field("keys.count"), bytes(keys)),
+        (
+            field("keys.bytes-per-key"),
+            bytes(dataset.checked_div(keys).unwrap_or(0)),
+        ),
+        (field("dataset.bytes"), bytes(dataset)),
+        (
+            field("dataset.percentage"),
+            Response::Double(percentage(dataset, total)),
+        ),
+        (
+            field("peak.percentage"),
+            Response::Double(percentage(total, peak)),
+        ),
+    ])
+}
+
+fn percentage(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+    part as f64 * 100.0 / whole as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        clock,
+        db::{Object, Value},
+    };
+
+    use super::*;
+
+    fn insert(dbs: &Databases, db: usize, key: &str, expiration: Option<Duration>) {
+        dbs[db].lock(key.as_bytes()).insert(
+            Bytes::from(key.to_string()),
+            Object::new(
+                Value::String(Bytes::from("value")),
+                expiration.map(|e| clock::now() - e),
+            ),
+        );
+    }
+
+    fn usage(key: &str) -> Memory {
+        Memory::Usage(Bytes::from(key.to_string()))
+    }
+
+    fn stat(stats: &Response, name: &str) -> i64 {
+        let Response::Map(fields) = stats else {
+            panic!("expected a map");
+        };
+        fields
+            .iter()
+            .find_map(|(k, v)| match (k, v) {
+                (Response::BulkString(k), Response::Integer(v)) if k == name => Some(*v),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn memory_usage() {
+        let dbs = Databases::default();
+        insert(&dbs, 1, "key", None);
+        insert(&dbs, 1, "expired", Some(Duration::from_secs(1)));
+
+        let expected = dbs[1].lock(b"key").memory_usage(b"key").unwrap();
+        assert_eq!(
+            usage("key").execute(&dbs, 1),
+            Response::Integer(expected as i64)
+        );
+        assert_eq!(usage("key").execute(&dbs, 0), Response::Null);
+        assert_eq!(usage("expired").execute(&dbs, 1), Response::Null);
+        assert_eq!(dbs[1].len(), 1);
+    }
+
+    #[test]
+    fn memory_stats() {
+        let dbs = Databases::default();
+        insert(&dbs, 0, "a", None);
+        insert(&dbs, 2, "b", None);
+
+        let stats = Memory::Stats.execute(&dbs, 0);
+        let dataset = dbs.used_memory() as i64;
+        assert_eq!(stat(&stats, "keys.count"), 2);
+        assert_eq!(stat(&stats, "dataset.bytes"), dataset);
+        assert_eq!(stat(&stats, "keys.bytes-per-key"), dataset / 2);
+        assert!(stat(&stats, "peak.allocated") > 0);
+    }
+}
@@ -0,0 +1,134 @@
+use bytes::Bytes;
+
+use crate::{
+    cmd::{parser::object::Object, response::Response},
+    db::{Database, Value},
+};
+
+/// Length up to which Redis embeds strings in their object, reporting them as `embstr`.
+const EMBSTR_MAX_LEN: usize = 44;
+
+impl Object {
+    /// Reports on the object of the key, without counting it as an access. Missing keys get
+    /// `Null`.
+    ///
+    /// Both the access time and the access counter are always kept, so unlike Redis, idle time
+    /// and frequency are reported whatever the eviction policy.
+    pub fn execute(self, db: &Database) -> Response {
+        let (Self::Encoding(key) | Self::Freq(key) | Self::IdleTime(key) | Self::RefCount(key)) =
+            &self;
+        let mut shard = db.lock(key);
+        let Some(object) = shard.peek(key) else {
+            return Response::Null;
+        };
+
+        match self {
+            Self::Encoding(_) => Response::BulkString(Bytes::from(encoding(&object.value))),
+            Self::Freq(_) => Response::Integer(object.frequency() as i64),
+            Self::IdleTime(_) => Response::Integer(object.idle_time().as_secs() as i64),
+            // values aren't shared between keys
+            Self::RefCount(_) => Response::Integer(1),
+        }
+    }
+}
+
+/// Name of the encoding of a value, as Redis names the closest one it has: `int` for integers,
+/// `embstr` or `raw` for strings depending on their length, and `quicklist` for lists.
+fn encoding(value: &Value) -> &'static str {
+    match value {
+        Value::Integer(_) => "int",
+        Value::String(s) if s.len() <= EMBSTR_MAX_LEN => "embstr",
+        Value::String(_) => "raw",
+        Value::List(_) => "quicklist",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use crate::{
+        clock::{self, MockClock},
+        db::Object as DbObject,
+    };
+
+    use super::*;
+
+    fn insert(db: &Database, key: &str, value: Value) {
+        db.lock(key.as_bytes())
+            .insert(Bytes::from(key.to_string()), DbObject::new(value, None));
+    }
+
+    fn key(key: &str) -> Bytes {
+        Bytes::from(key.to_string())
+    }
+
+    #[test]
+    fn object_encoding() {
+        let db = Database::default();
+        insert(&db, "int", Value::Integer(1));
+        insert(&db, "short", Value::String(Bytes::from("a".repeat(44))));
+        insert(&db, "long", Value::String(Bytes::from("a".repeat(45))));
+        insert(&db, "list", Value::List(VecDeque::new()));
+
+        for (k, encoding) in [
+            ("int", "int"),
+            ("short", "embstr"),
+            ("long", "raw"),
+            ("list", "quicklist"),
+        ] {
+            assert_eq!(
+                Object::Encoding(key(k)).execute(&db),
+                Response::BulkString(Bytes::from(encoding))
+            );
+        }
+        assert_eq!(
+            Object::Encoding(key("missing")).execute(&db),
+            Response::Null
+        );
+    }
+
+    #[test]
+    fn object_access_metadata() {
+        let clock = MockClock::at(UNIX_EPOCH + Duration::from_secs(1_000_000));
+        let db = Database::default();
+        insert(&db, "k", Value::Integer(1));
+        clock.advance(Duration::from_secs(30));
+
+        // looking at the object isn't an access
+        for _ in 0..2 {
+            assert_eq!(
+                Object::IdleTime(key("k")).execute(&db),
+                Response::Integer(30)
+            );
+            assert_eq!(Object::Freq(key("k")).execute(&db), Response::Integer(5));
+        }
+        db.lock(b"k").live(b"k");
+        assert_eq!(
+            Object::IdleTime(key("k")).execute(&db),
+            Response::Integer(0)
+        );
+        assert_eq!(
+            Object::RefCount(key("k")).execute(&db),
+            Response::Integer(1)
+        );
+        assert_eq!(Object::Freq(key("missing")).execute(&db), Response::Null);
+    }
+
+    #[test]
+    fn object_of_expired_key() {
+        let db = Database::default();
+        db.lock(b"k").insert(
+            key("k"),
+            DbObject::new(
+                Value::Integer(1),
+                Some(clock::now() - Duration::from_secs(1)),
+            ),
+        );
+        assert_eq!(Object::RefCount(key("k")).execute(&db), Response::Null);
+        assert!(db.is_empty());
+    }
+}
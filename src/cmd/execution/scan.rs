@@ -0,0 +1,163 @@
+use bytes::Bytes;
+
+use crate::{cmd::parser::scan::Scan, db::Database, glob::matches};
+
+impl Scan {
+    /// Returns the cursor to continue from and the keys found among those looked at, see
+    /// [`Database::scan`].
+    pub fn execute(self, db: &Database) -> (u64, Vec<Bytes>) {
+        let mut keys = vec![];
+        let cursor = db.scan(self.cursor, self.count, |k, o| {
+            if self.pattern.as_ref().is_none_or(|p| matches(p, k))
+                && self
+                    .kind
+                    .as_ref()
+                    .is_none_or(|t| o.value.kind().name() == t)
+            {
+                keys.push(k.clone());
+            }
+        });
+        (cursor, keys)
+    }
+}
+
+/// Returns every key matching `pattern`, scanning the whole database at once.
+pub fn keys(db: &Database, pattern: &[u8]) -> Vec<Bytes> {
+    let mut keys = vec![];
+    db.scan(0, usize::MAX, |k, _| {
+        if matches(pattern, k) {
+            keys.push(k.clone());
+        }
+    });
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, time::Duration};
+
+    use crate::{
+        clock,
+        db::{Object, Settings, Value},
+    };
+
+    use super::*;
+
+    fn db_with(keys: &[&str]) -> Database {
+        let db = Database::new(4, Settings::default());
+        for key in keys {
+            let value = match key.strip_prefix("list:") {
+                Some(_) => Value::List(Default::default()),
+                None => Value::Integer(0),
+            };
+            db.lock(key.as_bytes())
+                .insert(Bytes::from(key.to_string()), Object::new(value, None));
+        }
+        db
+    }
+
+    fn scan(cursor: u64, count: usize) -> Scan {
+        Scan {
+            cursor,
+            pattern: None,
+            count,
+            kind: None,
+        }
+    }
+
+    fn sorted(keys: Vec<Bytes>) -> Vec<Bytes> {
+        let mut keys = keys;
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn keys_matching() {
+        let db = db_with(&["user:1", "user:2", "item:1"]);
+        assert_eq!(
+            sorted(keys(&db, b"user:*")),
+            vec![Bytes::from("user:1"), Bytes::from("user:2")]
+        );
+        assert_eq!(keys(&db, b"*").len(), 3);
+        assert!(keys(&db, b"foo").is_empty());
+    }
+
+    #[test]
+    fn keys_skips_expired() {
+        let db = db_with(&["live"]);
+        db.lock(b"expired").insert(
+            Bytes::from("expired"),
+            Object::new(
+                Value::Integer(0),
+                Some(clock::now() - Duration::from_secs(1)),
+            ),
+        );
+        assert_eq!(keys(&db, b"*"), vec![Bytes::from("live")]);
+    }
+
+    #[test]
+    fn scan_returns_every_key() {
+        let names: Vec<String> = (0..100).map(|i| format!("key:{i}")).collect();
+        let db = db_with(&names.iter().map(String::as_str).collect::<Vec<_>>());
+
+        let mut found = HashSet::new();
+        let mut cursor = 0;
+        let mut calls = 0;
+        loop {
+            let (next, keys) = scan(cursor, 7).execute(&db);
+            assert!(keys.len() <= 7);
+            found.extend(keys);
+            calls += 1;
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(found.len(), 100);
+        assert!(calls > 100 / 7);
+    }
+
+    #[test]
+    fn scan_survives_deletions() {
+        let names: Vec<String> = (0..200).map(|i| format!("key:{i}")).collect();
+        let db = db_with(&names.iter().map(String::as_str).collect::<Vec<_>>());
+
+        let mut found = HashSet::new();
+        let mut cursor = 0;
+        let mut deleted = HashSet::new();
+        loop {
+            let (next, keys) = scan(cursor, 5).execute(&db);
+            // deletes keys that have been returned, moving the last ones of their shards
+            for key in keys.iter().take(2) {
+                db.lock(key).swap_remove(key);
+                deleted.insert(key.clone());
+            }
+            found.extend(keys);
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+        assert_eq!(found.len(), 200);
+        assert!(!deleted.is_empty());
+    }
+
+    #[test]
+    fn scan_filters() {
+        let db = db_with(&["a:1", "a:2", "b:1", "list:a"]);
+        let (cursor, keys) = Scan {
+            pattern: Some(Bytes::from("a:*")),
+            ..scan(0, 100)
+        }
+        .execute(&db);
+        assert_eq!(cursor, 0);
+        assert_eq!(sorted(keys), vec![Bytes::from("a:1"), Bytes::from("a:2")]);
+
+        let (_, keys) = Scan {
+            kind: Some("list".to_string()),
+            ..scan(0, 100)
+        }
+        .execute(&db);
+        assert_eq!(keys, vec![Bytes::from("list:a")]);
+    }
+}
@@ -0,0 +1,498 @@
+use bytes::Bytes;
+
+use crate::{
+    clock,
+    cmd::{
+        error::ClientError,
+        parser::set::{DelEx, GetEx, GetExExpiration, MSet, Set},
+    },
+    db::{Database, Kind, Object, WrongType, detach, digest},
+};
+
+#[derive(Debug, PartialEq)]
+pub struct SetOutcome {
+    /// Whether the condition allowed the value to be stored.
+    pub stored: bool,
+    /// Previous value of the key, only looked up for `GET`.
+    pub previous: Option<Bytes>,
+}
+
+impl Set {
+    pub fn execute(self, db: &Database) -> Result<SetOutcome, ClientError> {
+        let mut map = db.lock(&self.key);
+
+        // values of any type are overwritten, unless they have to be returned or compared
+        let existing = map.live(&self.key);
+        let previous = match &existing {
+            Some(o) if self.options.get => Some(o.value.to_bytes().ok_or(WrongType)?),
+            _ => None,
+        };
+        let allowed = match &self.options.condition {
+            None => true,
+            Some(c) => c.allows(existing.as_ref().map(|o| &o.value))?,
+        };
+        let kept_expiration = existing.and_then(|o| o.expiration());
+
+        if !allowed {
+            return Ok(SetOutcome {
+                stored: false,
+                previous,
+            });
+        }
+
+        let expiration = if self.options.keep_ttl {
+            kept_expiration
+        } else {
+            self.expiration
+        };
+        if expiration.is_some_and(|e| e <= clock::now()) {
+            // a time in the past deletes the key, as EXPIRE does
+            map.swap_remove(&self.key);
+        } else {
+            map.insert(
+                detach(self.key),
+                Object::new(self.value.detach(), expiration),
+            );
+        }
+
+        Ok(SetOutcome {
+            stored: true,
+            previous,
+        })
+    }
+}
+
+impl GetEx {
+    pub fn execute(self, db: &Database) -> Result<Option<Bytes>, ClientError> {
+        let mut map = db.lock(&self.key);
+        let Some(o) = map.lookup(&self.key, Kind::String)? else {
+            return Ok(None);
+        };
+        let value = o.value.to_bytes();
+
+        match self.expiration {
+            GetExExpiration::Keep => {}
+            GetExExpiration::Persist => {
+                map.set_expiration(&self.key, None);
+            }
+            GetExExpiration::Set(e) if e <= clock::now() => {
+                map.swap_remove(&self.key);
+            }
+            GetExExpiration::Set(e) => {
+                map.set_expiration(&self.key, Some(e));
+            }
+        }
+        Ok(value)
+    }
+}
+
+impl DelEx {
+    /// Removes the key if the condition allows it, returning whether it was removed.
+    pub fn execute(self, db: &Database) -> Result<bool, ClientError> {
+        let mut map = db.lock(&self.key);
+        let Some(o) = map.live(&self.key) else {
+            return Ok(false);
+        };
+        if let Some(c) = &self.condition
+            && !c.allows(Some(&o.value))?
+        {
+            return Ok(false);
+        }
+
+        map.swap_remove(&self.key);
+        Ok(true)
+    }
+}
+
+impl MSet {
+    /// Sets every key at once: the shards of all the keys are locked before the first is set.
+    pub fn execute(self, db: &Database) {
+        let keys: Vec<Bytes> = self.pairs.iter().map(|(k, _)| k.clone()).collect();
+        let mut shards = db.lock_keys(&keys);
+
+        for (key, value) in self.pairs {
+            shards
+                .shard(&key)
+                .insert(detach(key), Object::new(value.detach(), None));
+        }
+    }
+}
+
+/// Returns the digest of a string value (see [`digest`]).
+pub fn digest_of(db: &Database, key: Bytes) -> Result<Option<String>, ClientError> {
+    let mut map = db.lock(&key);
+    Ok(map
+        .lookup(&key, Kind::String)?
+        .and_then(|o| o.value.to_bytes())
+        .map(|b| digest(&b)))
+}
+
+/// Removes a key holding a string, returning its value.
+pub fn getdel(db: &Database, key: Bytes) -> Result<Option<Bytes>, ClientError> {
+    let mut map = db.lock(&key);
+    if map.lookup(&key, Kind::String)?.is_none() {
+        return Ok(None);
+    }
+    Ok(map.swap_remove(&key).and_then(|o| o.value.to_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    use crate::{
+        cmd::parser::set::{Condition, Options},
+        db::Value,
+    };
+
+    use super::*;
+
+    fn empty_db() -> Database {
+        Database::default()
+    }
+
+    fn set(key: &'static str, value: &'static str, options: Options) -> Set {
+        Set {
+            key: Bytes::from(key),
+            value: Value::from_bytes(Bytes::from(value)),
+            expiration: None,
+            options,
+        }
+    }
+
+    fn expiration(db: &Database, key: &str) -> Option<SystemTime> {
+        db.lock(key.as_bytes())
+            .get(key.as_bytes())
+            .unwrap()
+            .expiration()
+    }
+
+    #[test]
+    fn set_ok() {
+        let db = empty_db();
+        let outcome = set("key", "value", Options::default())
+            .execute(&db)
+            .unwrap();
+        assert_eq!(
+            outcome,
+            SetOutcome {
+                stored: true,
+                previous: None
+            }
+        );
+    }
+
+    #[test]
+    fn set_nx() {
+        let db = empty_db();
+        let nx = || Options {
+            condition: Some(Condition::Nx),
+            ..Default::default()
+        };
+        assert!(set("key", "a", nx()).execute(&db).unwrap().stored);
+        assert!(!set("key", "b", nx()).execute(&db).unwrap().stored);
+        assert_eq!(
+            GetEx {
+                key: Bytes::from("key"),
+                expiration: GetExExpiration::Keep
+            }
+            .execute(&db),
+            Ok(Some(Bytes::from("a")))
+        );
+    }
+
+    #[test]
+    fn set_xx() {
+        let db = empty_db();
+        let xx = || Options {
+            condition: Some(Condition::Xx),
+            ..Default::default()
+        };
+        assert!(!set("key", "a", xx()).execute(&db).unwrap().stored);
+        assert!(db.is_empty());
+
+        set("key", "a", Options::default()).execute(&db).unwrap();
+        assert!(set("key", "b", xx()).execute(&db).unwrap().stored);
+    }
+
+    #[test]
+    fn set_get() {
+        let db = empty_db();
+        let get = || Options {
+            get: true,
+            ..Default::default()
+        };
+        assert_eq!(set("key", "1", get()).execute(&db).unwrap().previous, None);
+        assert_eq!(
+            set("key", "2", get()).execute(&db).unwrap().previous,
+            Some(Bytes::from("1"))
+        );
+    }
+
+    #[test]
+    fn set_nx_get_existing() {
+        let db = empty_db();
+        set("key", "a", Options::default()).execute(&db).unwrap();
+        let outcome = set(
+            "key",
+            "b",
+            Options {
+                condition: Some(Condition::Nx),
+                get: true,
+                ..Default::default()
+            },
+        )
+        .execute(&db)
+        .unwrap();
+        assert_eq!(
+            outcome,
+            SetOutcome {
+                stored: false,
+                previous: Some(Bytes::from("a"))
+            }
+        );
+    }
+
+    #[test]
+    fn set_get_wrong_type() {
+        let db = empty_db();
+        db.lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::List(VecDeque::from([Bytes::from("a")])), None),
+        );
+        let get = Options {
+            get: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            set("key", "a", get).execute(&db),
+            Err(ClientError::WrongType)
+        );
+    }
+
+    #[test]
+    fn set_overwrites_other_types() {
+        let db = empty_db();
+        db.lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::List(VecDeque::from([Bytes::from("a")])), None),
+        );
+        assert!(
+            set("key", "a", Options::default())
+                .execute(&db)
+                .unwrap()
+                .stored
+        );
+    }
+
+    #[test]
+    fn set_keep_ttl() {
+        let db = empty_db();
+        let deadline = clock::now() + Duration::from_secs(100);
+        let mut first = set("key", "a", Options::default());
+        first.expiration = Some(deadline);
+        first.execute(&db).unwrap();
+
+        let keep_ttl = Options {
+            keep_ttl: true,
+            ..Default::default()
+        };
+        set("key", "b", keep_ttl).execute(&db).unwrap();
+        assert_eq!(expiration(&db, "key"), Some(deadline));
+
+        set("key", "c", Options::default()).execute(&db).unwrap();
+        assert_eq!(expiration(&db, "key"), None);
+    }
+
+    #[test]
+    fn set_past_expiration_deletes() {
+        let db = empty_db();
+        set("key", "a", Options::default()).execute(&db).unwrap();
+
+        let mut past = set("key", "b", Options::default());
+        past.expiration = Some(UNIX_EPOCH + Duration::from_secs(1));
+        assert!(past.execute(&db).unwrap().stored);
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn set_ifeq() {
+        let db = empty_db();
+        let ifeq = |v: &'static str| Options {
+            condition: Some(Condition::IfEq(Bytes::from(v))),
+            get: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            set("key", "b", ifeq("a")).execute(&db),
+            Ok(SetOutcome {
+                stored: false,
+                previous: None
+            })
+        );
+        set("key", "a", Options::default()).execute(&db).unwrap();
+        assert!(!set("key", "b", ifeq("x")).execute(&db).unwrap().stored);
+        assert_eq!(
+            set("key", "b", ifeq("a")).execute(&db),
+            Ok(SetOutcome {
+                stored: true,
+                previous: Some(Bytes::from("a"))
+            })
+        );
+    }
+
+    #[test]
+    fn set_ifne_missing_key() {
+        let db = empty_db();
+        let ifne = Options {
+            condition: Some(Condition::IfNe(Bytes::from("a"))),
+            ..Default::default()
+        };
+        assert!(set("key", "a", ifne).execute(&db).unwrap().stored);
+    }
+
+    #[test]
+    fn set_ifdeq() {
+        let db = empty_db();
+        set("key", "a", Options::default()).execute(&db).unwrap();
+        let ifdeq = Options {
+            condition: Some(Condition::IfDeq(Bytes::from(digest(b"a")))),
+            ..Default::default()
+        };
+        assert!(set("key", "b", ifdeq).execute(&db).unwrap().stored);
+        assert_eq!(digest_of(&db, Bytes::from("key")), Ok(Some(digest(b"b"))));
+    }
+
+    #[test]
+    fn set_ifeq_wrong_type() {
+        let db = empty_db();
+        db.lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::List(VecDeque::from([Bytes::from("a")])), None),
+        );
+        let ifeq = Options {
+            condition: Some(Condition::IfEq(Bytes::from("a"))),
+            ..Default::default()
+        };
+        assert_eq!(
+            set("key", "a", ifeq).execute(&db),
+            Err(ClientError::WrongType)
+        );
+    }
+
+    #[test]
+    fn delex_no_condition() {
+        let db = empty_db();
+        db.lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::List(VecDeque::from([Bytes::from("a")])), None),
+        );
+        let delex = || DelEx {
+            key: Bytes::from("key"),
+            condition: None,
+        };
+        assert_eq!(delex().execute(&db), Ok(true));
+        assert_eq!(delex().execute(&db), Ok(false));
+    }
+
+    #[test]
+    fn delex_ifeq() {
+        let db = empty_db();
+        set("key", "a", Options::default()).execute(&db).unwrap();
+        let delex = |v: &'static str| DelEx {
+            key: Bytes::from("key"),
+            condition: Some(Condition::IfEq(Bytes::from(v))),
+        };
+        assert_eq!(delex("b").execute(&db), Ok(false));
+        assert_eq!(delex("a").execute(&db), Ok(true));
+        assert!(db.is_empty());
+    }
+
+    #[test]
+    fn digest_missing_key() {
+        assert_eq!(digest_of(&empty_db(), Bytes::from("key")), Ok(None));
+    }
+
+    #[test]
+    fn getex_persist() {
+        let db = empty_db();
+        let mut first = set("key", "a", Options::default());
+        first.expiration = Some(clock::now() + Duration::from_secs(100));
+        first.execute(&db).unwrap();
+
+        let getex = GetEx {
+            key: Bytes::from("key"),
+            expiration: GetExExpiration::Persist,
+        };
+        assert_eq!(getex.execute(&db), Ok(Some(Bytes::from("a"))));
+        assert_eq!(expiration(&db, "key"), None);
+    }
+
+    #[test]
+    fn getex_set_expiration() {
+        let db = empty_db();
+        set("key", "a", Options::default()).execute(&db).unwrap();
+
+        let deadline = clock::now() + Duration::from_secs(100);
+        let getex = GetEx {
+            key: Bytes::from("key"),
+            expiration: GetExExpiration::Set(deadline),
+        };
+        assert_eq!(getex.execute(&db), Ok(Some(Bytes::from("a"))));
+        assert_eq!(expiration(&db, "key"), Some(deadline));
+    }
+
+    #[test]
+    fn getex_missing_key() {
+        let getex = GetEx {
+            key: Bytes::from("key"),
+            expiration: GetExExpiration::Persist,
+        };
+        assert_eq!(getex.execute(&empty_db()), Ok(None));
+    }
+
+    #[test]
+    fn getdel_ok() {
+        let db = empty_db();
+        set("key", "a", Options::default()).execute(&db).unwrap();
+        assert_eq!(getdel(&db, Bytes::from("key")), Ok(Some(Bytes::from("a"))));
+        assert_eq!(getdel(&db, Bytes::from("key")), Ok(None));
+    }
+
+    #[test]
+    fn getdel_wrong_type() {
+        let db = empty_db();
+        db.lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::List(VecDeque::from([Bytes::from("a")])), None),
+        );
+        assert_eq!(getdel(&db, Bytes::from("key")), Err(ClientError::WrongType));
+        assert_eq!(db.len(), 1);
+    }
+
+    #[test]
+    fn mset_ok() {
+        let db = empty_db();
+        set("a", "old", Options::default()).execute(&db).unwrap();
+        db.lock(b"a")
+            .set_expiration(b"a", Some(clock::now() + Duration::from_secs(100)));
+
+        MSet {
+            pairs: vec![
+                (Bytes::from("a"), Value::Integer(1)),
+                (Bytes::from("b"), Value::String(Bytes::from("x"))),
+            ],
+        }
+        .execute(&db);
+
+        assert_eq!(db.lock(b"a").get(b"a").unwrap().value, Value::Integer(1));
+        assert_eq!(expiration(&db, "a"), None);
+        assert_eq!(
+            db.lock(b"b").get(b"b").unwrap().value,
+            Value::String(Bytes::from("x"))
+        );
+    }
+}
@@ -1,3 +1,40 @@
+use std::{str, str::FromStr};
+
+use bytes::Bytes;
+
+use crate::cmd::{error::ClientError, registry};
+
 pub mod set;
 pub mod arithmetic;
 pub mod list;
+pub mod hello;
+pub mod command;
+pub mod expire;
+pub mod database;
+pub mod keyspace;
+pub mod scan;
+pub mod object;
+pub mod memory;
+
+/// Parses a number out of a parameter, whose bytes aren't guaranteed to be valid UTF-8.
+pub fn parse_number<T: FromStr>(param: &[u8]) -> Option<T> {
+    str::from_utf8(param).ok()?.parse().ok()
+}
+
+/// Looks up the subcommand of `container` named by the first of `params`, which don't include
+/// the container itself, and checks the arity of the call. Returns the lowercase name of the
+/// subcommand.
+pub fn subcommand(container: &str, params: &[Bytes]) -> Result<String, ClientError> {
+    let name = String::from_utf8_lossy(&params[0]).to_lowercase();
+    let spec = registry::lookup(container)
+        .and_then(|c| c.subcommand(&name))
+        .ok_or_else(|| ClientError::UnknownSubcommand(name.clone(), container.to_uppercase()))?;
+    spec.check_arity(params.len() + 1)?;
+    Ok(name)
+}
+
+/// Builds parameters out of string literals, for tests.
+#[cfg(test)]
+pub(crate) fn to_params(params: &[&'static str]) -> Vec<Bytes> {
+    params.iter().map(|p| Bytes::from(*p)).collect()
+}
@@ -1,20 +1,20 @@
-use crate::cmd::{error::ClientError, types::INCRBY};
+use bytes::Bytes;
+
+use crate::cmd::{error::ClientError, parser::parse_number, types::INCRBY};
 
 #[derive(Debug, PartialEq)]
 pub struct Integer {
-    pub key: String,
+    pub key: Bytes,
     pub value: i64,
 }
 
 impl Integer {
-    pub fn parse(params: &[String]) -> Result<Self, ClientError> {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
         if params.len() != 2 {
             return Err(ClientError::WrongNumberOfArguments(INCRBY.to_string()));
         }
 
-        let value = params[1]
-            .parse::<i64>()
-            .map_err(|_| ClientError::IntegerError)?;
+        let value = parse_number::<i64>(&params[1]).ok_or(ClientError::IntegerError)?;
 
         Ok(Self {
             key: params[0].to_owned(),
@@ -30,17 +30,17 @@ mod tests {
     #[test]
     fn parse_ok() {
         let expected = Integer{
-            key: "key".to_string(),
+            key: Bytes::from("key"),
             value: 100,
         };
-        let params = &["key".to_string(), "100".to_string()];
+        let params = &[Bytes::from("key"), Bytes::from("100")];
         let i = Integer::parse(params).unwrap();
         assert_eq!(i, expected);
     }
 
     #[test]
     fn parse_err() {
-        let params = &["key".to_string(), "not_an_i64".to_string()];
+        let params = &[Bytes::from("key"), Bytes::from("not_an_i64")];
         assert!(Integer::parse(params).is_err());
     }
 }
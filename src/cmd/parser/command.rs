@@ -0,0 +1,91 @@
+use bytes::Bytes;
+
+use crate::cmd::{
+    error::ClientError,
+    parser::subcommand,
+    types::{COMMAND, COUNT, DOCS, GETKEYS, INFO},
+};
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    All,
+    Count,
+    /// Names of the commands to describe, all of them when empty.
+    Info(Vec<Bytes>),
+    /// Names of the commands to document, all of them when empty.
+    Docs(Vec<Bytes>),
+    /// Full call, command name included, to extract the keys from.
+    GetKeys(Vec<Bytes>),
+}
+
+impl Command {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
+        if params.is_empty() {
+            return Ok(Self::All);
+        }
+
+        let name = subcommand(COMMAND, params)?;
+        let args = params[1..].to_vec();
+        Ok(match name.as_str() {
+            COUNT => Self::Count,
+            INFO => Self::Info(args),
+            DOCS => Self::Docs(args),
+            GETKEYS => Self::GetKeys(args),
+            _ => unreachable!("every subcommand in the registry is handled"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_no_args() {
+        assert_eq!(Command::parse(&[]).unwrap(), Command::All);
+    }
+
+    #[test]
+    fn parse_count() {
+        assert_eq!(
+            Command::parse(&[Bytes::from("COUNT")]).unwrap(),
+            Command::Count
+        );
+    }
+
+    #[test]
+    fn parse_count_too_many_args() {
+        assert_eq!(
+            Command::parse(&[Bytes::from("count"), Bytes::from("x")]).unwrap_err(),
+            ClientError::WrongNumberOfArguments("command|count".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_info() {
+        assert_eq!(
+            Command::parse(&[Bytes::from("info"), Bytes::from("get")]).unwrap(),
+            Command::Info(vec![Bytes::from("get")])
+        );
+        assert_eq!(
+            Command::parse(&[Bytes::from("info")]).unwrap(),
+            Command::Info(vec![])
+        );
+    }
+
+    #[test]
+    fn parse_getkeys_no_command() {
+        assert_eq!(
+            Command::parse(&[Bytes::from("getkeys")]).unwrap_err(),
+            ClientError::WrongNumberOfArguments("command|getkeys".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_unknown_subcommand() {
+        assert_eq!(
+            Command::parse(&[Bytes::from("foo")]).unwrap_err(),
+            ClientError::UnknownSubcommand("foo".to_string(), "COMMAND".to_string())
+        );
+    }
+}
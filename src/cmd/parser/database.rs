@@ -0,0 +1,115 @@
+use bytes::Bytes;
+
+use crate::cmd::{error::ClientError, parser::parse_number};
+
+#[derive(Debug, PartialEq)]
+pub struct Move {
+    pub key: Bytes,
+    /// Index of the target database, as given by the client.
+    pub db: i64,
+}
+
+impl Move {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
+        Ok(Self {
+            key: params[0].to_owned(),
+            db: parse_number(&params[1]).ok_or(ClientError::IntegerError)?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SwapDb {
+    pub first: i64,
+    pub second: i64,
+}
+
+impl SwapDb {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
+        Ok(Self {
+            first: parse_number(&params[0]).ok_or(ClientError::InvalidFirstDbIndex)?,
+            second: parse_number(&params[1]).ok_or(ClientError::InvalidSecondDbIndex)?,
+        })
+    }
+}
+
+/// Whether `FLUSHDB` and `FLUSHALL` free the removed keys before replying, or in the background.
+#[derive(Debug, Default, PartialEq)]
+pub enum Flush {
+    #[default]
+    Sync,
+    Async,
+}
+
+impl Flush {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
+        let [mode] = params else {
+            return if params.is_empty() {
+                Ok(Self::default())
+            } else {
+                Err(ClientError::SyntaxError)
+            };
+        };
+        match mode.to_ascii_lowercase().as_slice() {
+            b"sync" => Ok(Self::Sync),
+            b"async" => Ok(Self::Async),
+            _ => Err(ClientError::SyntaxError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::parser::to_params;
+
+    use super::*;
+
+    #[test]
+    fn parse_move() {
+        assert_eq!(
+            Move::parse(&to_params(&["key", "-1"])),
+            Ok(Move {
+                key: Bytes::from("key"),
+                db: -1
+            })
+        );
+        assert_eq!(
+            Move::parse(&to_params(&["key", "one"])),
+            Err(ClientError::IntegerError)
+        );
+    }
+
+    #[test]
+    fn parse_swapdb() {
+        assert_eq!(
+            SwapDb::parse(&to_params(&["0", "1"])),
+            Ok(SwapDb {
+                first: 0,
+                second: 1
+            })
+        );
+        assert_eq!(
+            SwapDb::parse(&to_params(&["a", "1"])),
+            Err(ClientError::InvalidFirstDbIndex)
+        );
+        assert_eq!(
+            SwapDb::parse(&to_params(&["0", "b"])),
+            Err(ClientError::InvalidSecondDbIndex)
+        );
+    }
+
+    #[test]
+    fn parse_flush() {
+        assert_eq!(Flush::parse(&[]), Ok(Flush::Sync));
+        assert_eq!(Flush::parse(&to_params(&["ASYNC"])), Ok(Flush::Async));
+        assert_eq!(Flush::parse(&to_params(&["sync"])), Ok(Flush::Sync));
+        assert_eq!(
+            Flush::parse(&to_params(&["lazy"])),
+            Err(ClientError::SyntaxError)
+        );
+        assert_eq!(
+            Flush::parse(&to_params(&["async", "sync"])),
+            Err(ClientError::SyntaxError)
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use bytes::Bytes;
+
+use crate::cmd::{error::ClientError, parser::parse_number};
+
+#[derive(Debug, PartialEq)]
+pub struct Expire {
+    pub key: Bytes,
+    /// Time as given by the client, its unit and whether it's relative depend on the command.
+    pub when: i64,
+    pub condition: Condition,
+}
+
+/// `NX`, `XX`, `GT` and `LT` options. `XX` can be combined with either `GT` or `LT`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Condition {
+    /// Only when the key has no expiration.
+    pub nx: bool,
+    /// Only when the key has an expiration.
+    pub xx: bool,
+    /// Only when the new expiration is later than the current one.
+    pub gt: bool,
+    /// Only when the new expiration is sooner than the current one.
+    pub lt: bool,
+}
+
+impl Condition {
+    /// Whether a key expiring at `current` (never if `None`) can be set to expire at `new`.
+    ///
+    /// Keys without an expiration are treated as expiring in an infinite time.
+    pub fn allows(&self, current: Option<i64>, new: i64) -> bool {
+        match current {
+            None => !self.xx && !self.gt,
+            Some(current) => !self.nx && (!self.gt || new > current) && (!self.lt || new < current),
+        }
+    }
+}
+
+impl Expire {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
+        let when = parse_number::<i64>(&params[1]).ok_or(ClientError::IntegerError)?;
+
+        let mut condition = Condition::default();
+        for option in &params[2..] {
+            match option.to_ascii_lowercase().as_slice() {
+                b"nx" => condition.nx = true,
+                b"xx" => condition.xx = true,
+                b"gt" => condition.gt = true,
+                b"lt" => condition.lt = true,
+                _ => {
+                    return Err(ClientError::UnsupportedOption(
+                        String::from_utf8_lossy(option).to_string(),
+                    ));
+                }
+            }
+        }
+
+        if condition.nx && (condition.xx || condition.gt || condition.lt) {
+            return Err(ClientError::IncompatibleOptions(
+                "NX and XX, GT or LT".to_string(),
+            ));
+        }
+        if condition.gt && condition.lt {
+            return Err(ClientError::IncompatibleOptions("GT and LT".to_string()));
+        }
+
+        Ok(Self {
+            key: params[0].to_owned(),
+            when,
+            condition,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::parser::to_params;
+
+    use super::*;
+
+    #[test]
+    fn parse_ok() {
+        assert_eq!(
+            Expire::parse(&to_params(&["key", "-10"])).unwrap(),
+            Expire {
+                key: Bytes::from("key"),
+                when: -10,
+                condition: Condition::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_conditions() {
+        let expire = Expire::parse(&to_params(&["key", "10", "xx", "GT"])).unwrap();
+        assert_eq!(
+            expire.condition,
+            Condition {
+                xx: true,
+                gt: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_not_integer() {
+        assert_eq!(
+            Expire::parse(&to_params(&["key", "1.5"])).unwrap_err(),
+            ClientError::IntegerError
+        );
+    }
+
+    #[test]
+    fn parse_unsupported_option() {
+        assert_eq!(
+            Expire::parse(&to_params(&["key", "10", "foo"])).unwrap_err(),
+            ClientError::UnsupportedOption("foo".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_nx_incompatible() {
+        for option in ["xx", "gt", "lt"] {
+            let params = to_params(&["key", "10", "nx", option]);
+            assert_eq!(
+                Expire::parse(&params).unwrap_err(),
+                ClientError::IncompatibleOptions("NX and XX, GT or LT".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn parse_gt_lt_incompatible() {
+        assert_eq!(
+            Expire::parse(&to_params(&["key", "10", "gt", "lt"])).unwrap_err(),
+            ClientError::IncompatibleOptions("GT and LT".to_string())
+        );
+    }
+
+    #[test]
+    fn condition_none() {
+        let c = Condition::default();
+        assert!(c.allows(None, 10));
+        assert!(c.allows(Some(20), 10));
+    }
+
+    #[test]
+    fn condition_nx_xx() {
+        let nx = Condition {
+            nx: true,
+            ..Default::default()
+        };
+        assert!(nx.allows(None, 10));
+        assert!(!nx.allows(Some(20), 10));
+
+        let xx = Condition {
+            xx: true,
+            ..Default::default()
+        };
+        assert!(!xx.allows(None, 10));
+        assert!(xx.allows(Some(20), 10));
+    }
+
+    #[test]
+    fn condition_gt_lt() {
+        let gt = Condition {
+            gt: true,
+            ..Default::default()
+        };
+        assert!(!gt.allows(None, 10));
+        assert!(gt.allows(Some(5), 10));
+        assert!(!gt.allows(Some(10), 10));
+
+        let lt = Condition {
+            lt: true,
+            ..Default::default()
+        };
+        assert!(lt.allows(None, 10));
+        assert!(lt.allows(Some(20), 10));
+        assert!(!lt.allows(Some(10), 10));
+    }
+}
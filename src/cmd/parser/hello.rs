@@ -0,0 +1,130 @@
+use bytes::Bytes;
+
+use crate::{
+    cmd::{error::ClientError, parser::parse_number},
+    resp::protocol::Protocol,
+};
+
+/// User that authenticates a connection when no ACL is configured.
+const DEFAULT_USER: &str = "default";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Hello {
+    pub protocol: Option<Protocol>,
+    pub name: Option<String>,
+}
+
+impl Hello {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
+        let mut hello = Hello::default();
+        let Some(version) = params.first() else {
+            return Ok(hello);
+        };
+
+        let version = parse_number::<i64>(version).ok_or(ClientError::ProtocolVersionError)?;
+        hello.protocol = Some(Protocol::try_from(version).map_err(|_| ClientError::NoProto)?);
+
+        let mut i = 1;
+        while i < params.len() {
+            let remaining = params.len() - i - 1;
+            match params[i].to_ascii_lowercase().as_slice() {
+                b"auth" if remaining >= 2 => {
+                    // there are no ACLs: the default user is the only one and it has no password
+                    if params[i + 1] != DEFAULT_USER.as_bytes() {
+                        return Err(ClientError::WrongPass);
+                    }
+                    i += 3;
+                }
+                b"setname" if remaining >= 1 => {
+                    // only printable ASCII characters are allowed, so the name is valid UTF-8
+                    let name = &params[i + 1];
+                    if name.iter().any(|b| !(b'!'..=b'~').contains(b)) {
+                        return Err(ClientError::InvalidClientName);
+                    }
+                    hello.name =
+                        (!name.is_empty()).then(|| String::from_utf8_lossy(name).into_owned());
+                    i += 2;
+                }
+                _ => return Err(ClientError::SyntaxError),
+            }
+        }
+
+        Ok(hello)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::parser::to_params;
+
+    use super::*;
+
+    #[test]
+    fn parse_no_args() {
+        assert_eq!(Hello::parse(&[]).unwrap(), Hello::default());
+    }
+
+    #[test]
+    fn parse_version() {
+        let hello = Hello::parse(&to_params(&["3"])).unwrap();
+        assert_eq!(hello.protocol, Some(Protocol::Resp3));
+
+        let hello = Hello::parse(&to_params(&["2"])).unwrap();
+        assert_eq!(hello.protocol, Some(Protocol::Resp2));
+    }
+
+    #[test]
+    fn parse_version_not_integer() {
+        assert_eq!(
+            Hello::parse(&to_params(&["three"])).unwrap_err(),
+            ClientError::ProtocolVersionError
+        );
+    }
+
+    #[test]
+    fn parse_version_unsupported() {
+        assert_eq!(
+            Hello::parse(&to_params(&["4"])).unwrap_err(),
+            ClientError::NoProto
+        );
+    }
+
+    #[test]
+    fn parse_auth_and_setname() {
+        let params = to_params(&["3", "AUTH", "default", "secret", "SETNAME", "conn"]);
+        assert_eq!(
+            Hello::parse(&params).unwrap(),
+            Hello {
+                protocol: Some(Protocol::Resp3),
+                name: Some("conn".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_auth_unknown_user() {
+        let params = to_params(&["3", "auth", "someone", "secret"]);
+        assert_eq!(Hello::parse(&params).unwrap_err(), ClientError::WrongPass);
+    }
+
+    #[test]
+    fn parse_auth_missing_password() {
+        let params = to_params(&["3", "auth", "default"]);
+        assert_eq!(Hello::parse(&params).unwrap_err(), ClientError::SyntaxError);
+    }
+
+    #[test]
+    fn parse_setname_invalid() {
+        let params = to_params(&["2", "setname", "my conn"]);
+        assert_eq!(
+            Hello::parse(&params).unwrap_err(),
+            ClientError::InvalidClientName
+        );
+    }
+
+    #[test]
+    fn parse_unknown_option() {
+        let params = to_params(&["2", "foo"]);
+        assert_eq!(Hello::parse(&params).unwrap_err(), ClientError::SyntaxError);
+    }
+}
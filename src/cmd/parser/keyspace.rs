@@ -0,0 +1,78 @@
+use bytes::Bytes;
+
+use crate::cmd::{error::ClientError, parser::parse_number};
+
+#[derive(Debug, PartialEq)]
+pub struct CopyKey {
+    pub source: Bytes,
+    pub destination: Bytes,
+    /// Index of the target database as given by the client, the selected one if `None`.
+    pub db: Option<i64>,
+    /// Whether an existing destination key is overwritten.
+    pub replace: bool,
+}
+
+impl CopyKey {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
+        let mut copy = Self {
+            source: params[0].to_owned(),
+            destination: params[1].to_owned(),
+            db: None,
+            replace: false,
+        };
+
+        let mut options = params[2..].iter();
+        while let Some(option) = options.next() {
+            match option.to_ascii_lowercase().as_slice() {
+                b"replace" => copy.replace = true,
+                b"db" => {
+                    let db = options.next().ok_or(ClientError::SyntaxError)?;
+                    copy.db = Some(parse_number(db).ok_or(ClientError::IntegerError)?);
+                }
+                _ => return Err(ClientError::SyntaxError),
+            }
+        }
+
+        Ok(copy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::parser::to_params;
+
+    use super::*;
+
+    #[test]
+    fn parse_copy() {
+        assert_eq!(
+            CopyKey::parse(&to_params(&["a", "b"])),
+            Ok(CopyKey {
+                source: Bytes::from("a"),
+                destination: Bytes::from("b"),
+                db: None,
+                replace: false,
+            })
+        );
+        assert_eq!(
+            CopyKey::parse(&to_params(&["a", "b", "REPLACE", "db", "3"])),
+            Ok(CopyKey {
+                source: Bytes::from("a"),
+                destination: Bytes::from("b"),
+                db: Some(3),
+                replace: true,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_copy_errors() {
+        for (params, error) in [
+            (&["a", "b", "db"][..], ClientError::SyntaxError),
+            (&["a", "b", "db", "x"], ClientError::IntegerError),
+            (&["a", "b", "nx"], ClientError::SyntaxError),
+        ] {
+            assert_eq!(CopyKey::parse(&to_params(params)), Err(error));
+        }
+    }
+}
@@ -1,13 +1,15 @@
+use bytes::Bytes;
+
 use crate::cmd::{error::ClientError, types::LPUSH};
 
 #[derive(Debug, PartialEq)]
 pub struct List {
-    pub key: String,
-    pub values: Vec<String>,
+    pub key: Bytes,
+    pub values: Vec<Bytes>,
 }
 
 impl List {
-    pub fn parse(params: &[String]) -> Result<Self, ClientError> {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
         if params.len() < 2 {
             return Err(ClientError::WrongNumberOfArguments(LPUSH.to_string()));
         }
@@ -24,20 +26,20 @@ mod tests {
 
     #[test]
     fn parse_ok() {
-        let params = &["key".to_string(), "a".to_string(), "b".to_string()];
+        let params = &[Bytes::from("key"), Bytes::from("a"), Bytes::from("b")];
         let l = List::parse(params).unwrap();
         assert_eq!(
             l,
             List {
-                key: "key".to_string(),
-                values: vec!["a".to_string(), "b".to_string()],
+                key: Bytes::from("key"),
+                values: vec![Bytes::from("a"), Bytes::from("b")],
             }
         );
     }
 
     #[test]
     fn parse_too_few_args() {
-        let params = &["key".to_string()];
+        let params = &[Bytes::from("key")];
         assert_eq!(
             List::parse(params).unwrap_err(),
             ClientError::WrongNumberOfArguments(LPUSH.to_string())
@@ -0,0 +1,91 @@
+use bytes::Bytes;
+
+use crate::cmd::{
+    error::ClientError,
+    parser::{parse_number, subcommand},
+    types::{MEMORY, STATS, USAGE},
+};
+
+/// Subcommands of `MEMORY`.
+#[derive(Debug, PartialEq)]
+pub enum Memory {
+    /// Key to report the memory of.
+    Usage(Bytes),
+    Stats,
+}
+
+impl Memory {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
+        let name = subcommand(MEMORY, params)?;
+        Ok(match name.as_str() {
+            USAGE => {
+                parse_samples(&params[2..])?;
+                Self::Usage(params[1].clone())
+            }
+            STATS => Self::Stats,
+            _ => unreachable!("every subcommand in the registry is handled"),
+        })
+    }
+}
+
+/// Checks the `SAMPLES` option of `MEMORY USAGE`, which Redis uses to estimate the size of
+/// collections from a few elements. It's accepted for compatibility, but has no use here since
+/// the memory of every value is kept up to date as it changes.
+fn parse_samples(options: &[Bytes]) -> Result<(), ClientError> {
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        if !option.eq_ignore_ascii_case(b"samples") {
+            return Err(ClientError::SyntaxError);
+        }
+        let samples = options.next().ok_or(ClientError::SyntaxError)?;
+        let samples: i64 = parse_number(samples).ok_or(ClientError::IntegerError)?;
+        if samples < 0 {
+            return Err(ClientError::SyntaxError);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::parser::to_params;
+
+    use super::*;
+
+    #[test]
+    fn parse_usage() {
+        let usage = Ok(Memory::Usage(Bytes::from("k")));
+        assert_eq!(Memory::parse(&to_params(&["usage", "k"])), usage);
+        assert_eq!(
+            Memory::parse(&to_params(&["USAGE", "k", "SAMPLES", "0"])),
+            usage
+        );
+        assert_eq!(
+            Memory::parse(&to_params(&["usage", "k", "samples", "5", "samples", "10"])),
+            usage
+        );
+    }
+
+    #[test]
+    fn parse_usage_errors() {
+        for (params, e) in [
+            (&["usage", "k", "samples"][..], ClientError::SyntaxError),
+            (&["usage", "k", "samples", "-1"], ClientError::SyntaxError),
+            (&["usage", "k", "samples", "x"], ClientError::IntegerError),
+            (&["usage", "k", "foo", "1"], ClientError::SyntaxError),
+        ] {
+            assert_eq!(Memory::parse(&to_params(params)), Err(e));
+        }
+    }
+
+    #[test]
+    fn parse_stats() {
+        assert_eq!(Memory::parse(&to_params(&["stats"])), Ok(Memory::Stats));
+        assert_eq!(
+            Memory::parse(&to_params(&["stats", "x"])),
+            Err(ClientError::WrongNumberOfArguments(
+                "memory|stats".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use bytes::Bytes;
+
+use crate::cmd::{
+    error::ClientError,
+    parser::subcommand,
+    types::{ENCODING, FREQ, IDLETIME, OBJECT, REFCOUNT},
+};
+
+/// Subcommands of `OBJECT`, with the key they look at.
+#[derive(Debug, PartialEq)]
+pub enum Object {
+    Encoding(Bytes),
+    Freq(Bytes),
+    IdleTime(Bytes),
+    RefCount(Bytes),
+}
+
+impl Object {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
+        let name = subcommand(OBJECT, params)?;
+        let key = params[1].clone();
+        Ok(match name.as_str() {
+            ENCODING => Self::Encoding(key),
+            FREQ => Self::Freq(key),
+            IDLETIME => Self::IdleTime(key),
+            REFCOUNT => Self::RefCount(key),
+            _ => unreachable!("every subcommand in the registry is handled"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::parser::to_params;
+
+    use super::*;
+
+    #[test]
+    fn parse_subcommands() {
+        assert_eq!(
+            Object::parse(&to_params(&["ENCODING", "k"])),
+            Ok(Object::Encoding(Bytes::from("k")))
+        );
+        assert_eq!(
+            Object::parse(&to_params(&["idletime", "k"])),
+            Ok(Object::IdleTime(Bytes::from("k")))
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Object::parse(&to_params(&["freq"])),
+            Err(ClientError::WrongNumberOfArguments(
+                "object|freq".to_string()
+            ))
+        );
+        assert_eq!(
+            Object::parse(&to_params(&["refcount", "a", "b"])),
+            Err(ClientError::WrongNumberOfArguments(
+                "object|refcount".to_string()
+            ))
+        );
+        assert_eq!(
+            Object::parse(&to_params(&["foo", "k"])),
+            Err(ClientError::UnknownSubcommand(
+                "foo".to_string(),
+                "OBJECT".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use bytes::Bytes;
+
+use crate::cmd::{error::ClientError, parser::parse_number};
+
+/// Keys looked at by a call, unless `COUNT` says otherwise.
+const DEFAULT_COUNT: usize = 10;
+
+/// Types `TYPE` accepts: all those of Redis, although some can't be stored yet.
+const TYPE_NAMES: &[&str] = &["string", "list", "set", "zset", "hash", "stream"];
+
+#[derive(Debug, PartialEq)]
+pub struct Scan {
+    pub cursor: u64,
+    /// Glob-style pattern of the keys to return.
+    pub pattern: Option<Bytes>,
+    /// Number of keys to look at, which bounds the time taken by a call rather than the number
+    /// of keys returned.
+    pub count: usize,
+    /// Name of the type of the keys to return, in lowercase.
+    pub kind: Option<String>,
+}
+
+impl Scan {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
+        let mut scan = Self {
+            cursor: parse_number(&params[0]).ok_or(ClientError::InvalidCursor)?,
+            pattern: None,
+            count: DEFAULT_COUNT,
+            kind: None,
+        };
+
+        let mut options = params[1..].iter();
+        while let Some(option) = options.next() {
+            let value = options.next().ok_or(ClientError::SyntaxError)?;
+            match option.to_ascii_lowercase().as_slice() {
+                b"match" => scan.pattern = Some(value.to_owned()),
+                b"count" => {
+                    let count: i64 = parse_number(value).ok_or(ClientError::IntegerError)?;
+                    if count < 1 {
+                        return Err(ClientError::SyntaxError);
+                    }
+                    scan.count = count as usize;
+                }
+                b"type" => {
+                    let name = String::from_utf8_lossy(value).to_lowercase();
+                    if !TYPE_NAMES.contains(&name.as_str()) {
+                        return Err(ClientError::UnknownType(
+                            String::from_utf8_lossy(value).to_string(),
+                        ));
+                    }
+                    scan.kind = Some(name);
+                }
+                _ => return Err(ClientError::SyntaxError),
+            }
+        }
+
+        Ok(scan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::parser::to_params;
+
+    use super::*;
+
+    #[test]
+    fn parse_defaults() {
+        assert_eq!(
+            Scan::parse(&to_params(&["0"])),
+            Ok(Scan {
+                cursor: 0,
+                pattern: None,
+                count: 10,
+                kind: None,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_options() {
+        assert_eq!(
+            Scan::parse(&to_params(&[
+                "17", "MATCH", "user:*", "count", "100", "TYPE", "List"
+            ])),
+            Ok(Scan {
+                cursor: 17,
+                pattern: Some(Bytes::from("user:*")),
+                count: 100,
+                kind: Some("list".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (params, error) in [
+            (&["-1"][..], ClientError::InvalidCursor),
+            (&["a"], ClientError::InvalidCursor),
+            (&["0", "count"], ClientError::SyntaxError),
+            (&["0", "count", "0"], ClientError::SyntaxError),
+            (&["0", "count", "x"], ClientError::IntegerError),
+            (&["0", "foo", "1"], ClientError::SyntaxError),
+            (
+                &["0", "type", "Foo"],
+                ClientError::UnknownType("Foo".to_string()),
+            ),
+        ] {
+            assert_eq!(Scan::parse(&to_params(params)), Err(error));
+        }
+    }
+}
@@ -1,42 +1,228 @@
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use bytes::Bytes;
+
 use crate::{
-    cmd::{error::ClientError, types::SET},
-    db::Value,
+    clock::{self, to_unix_ms},
+    cmd::{
+        error::ClientError,
+        parser::parse_number,
+        types::{GETEX, MSET, SET},
+    },
+    db::{Value, WrongType, digest},
 };
 
 #[derive(Debug, PartialEq)]
 pub struct Set {
-    pub key: String,
+    pub key: Bytes,
     pub value: Value,
     pub expiration: Option<SystemTime>,
+    pub options: Options,
+}
+
+/// Options of `SET` besides the expiration.
+#[derive(Debug, Default, PartialEq)]
+pub struct Options {
+    pub condition: Option<Condition>,
+    /// Reply with the previous value.
+    pub get: bool,
+    /// Retain the expiration of the previous value.
+    pub keep_ttl: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Condition {
+    /// Only write the key if it doesn't exist.
+    Nx,
+    /// Only write the key if it exists.
+    Xx,
+    /// Only write the key if it holds this value.
+    IfEq(Bytes),
+    /// Only write the key if it doesn't exist or holds another value.
+    IfNe(Bytes),
+    /// Only write the key if the digest of its value is this one.
+    IfDeq(Bytes),
+    /// Only write the key if it doesn't exist or the digest of its value is another one.
+    IfDne(Bytes),
+}
+
+impl Condition {
+    /// Parses the conditions comparing the current value, `None` for other options.
+    fn parse_comparison(option: &[u8], value: &Bytes) -> Option<Self> {
+        let value = value.to_owned();
+        match option {
+            b"ifeq" => Some(Condition::IfEq(value)),
+            b"ifne" => Some(Condition::IfNe(value)),
+            b"ifdeq" => Some(Condition::IfDeq(value)),
+            b"ifdne" => Some(Condition::IfDne(value)),
+            _ => None,
+        }
+    }
+
+    /// Whether a key holding `current`, `None` if it doesn't exist, can be written.
+    ///
+    /// Comparisons are only possible with strings.
+    pub fn allows(&self, current: Option<&Value>) -> Result<bool, WrongType> {
+        let bytes = || current.map(|v| v.to_bytes().ok_or(WrongType)).transpose();
+        let digest_is = |expected: &Bytes| -> Result<bool, WrongType> {
+            Ok(bytes()?.is_some_and(|b| digest(&b).as_bytes().eq_ignore_ascii_case(expected)))
+        };
+
+        Ok(match self {
+            Condition::Nx => current.is_none(),
+            Condition::Xx => current.is_some(),
+            Condition::IfEq(v) => bytes()?.as_ref() == Some(v),
+            Condition::IfNe(v) => bytes()?.as_ref() != Some(v),
+            Condition::IfDeq(d) => digest_is(d)?,
+            Condition::IfDne(d) => !digest_is(d)?,
+        })
+    }
 }
 
 impl Set {
-    pub fn parse(params: &[String]) -> Result<Self, ClientError> {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
         if params.len() < 2 {
             return Err(ClientError::WrongNumberOfArguments(SET.to_string()));
         }
-        if params.len() == 3 || params.len() > 4 {
-            return Err(ClientError::SyntaxError);
-        }
 
-        let key = params[0].to_owned();
-        let value = params[1]
-            .parse::<i64>()
-            .map_or(Value::String(params[1].to_owned()), Value::Integer);
+        let mut options = Options::default();
+        let mut expiration = None;
+        // an expiration option can be repeated, but not mixed with another one
+        let mut expiration_option: Option<Vec<u8>> = None;
 
-        let expiration = if params.len() == 4 {
-            match Expiration::try_from((params[2].to_owned(), params[3].to_owned())) {
-                Ok(exp) => Some(exp.0),
-                Err(e) => return Err(e),
+        let mut i = 2;
+        while i < params.len() {
+            let option = params[i].to_ascii_lowercase();
+            let has_value = i + 1 < params.len();
+            match option.as_slice() {
+                b"nx" if matches!(options.condition, None | Some(Condition::Nx)) => {
+                    options.condition = Some(Condition::Nx)
+                }
+                b"xx" if matches!(options.condition, None | Some(Condition::Xx)) => {
+                    options.condition = Some(Condition::Xx)
+                }
+                b"ifeq" | b"ifne" | b"ifdeq" | b"ifdne"
+                    if has_value && options.condition.is_none() =>
+                {
+                    options.condition = Condition::parse_comparison(&option, &params[i + 1]);
+                    i += 1;
+                }
+                b"get" => options.get = true,
+                b"keepttl" if expiration_option.is_none() => options.keep_ttl = true,
+                b"ex" | b"px" | b"exat" | b"pxat"
+                    if has_value
+                        && !options.keep_ttl
+                        && expiration_option.as_ref().is_none_or(|o| *o == option) =>
+                {
+                    expiration = Some(Expiration::parse(&option, &params[i + 1], SET)?.0);
+                    expiration_option = Some(option);
+                    i += 1;
+                }
+                _ => return Err(ClientError::SyntaxError),
             }
-        } else {
-            None
+            i += 1;
+        }
+
+        Ok(Self {
+            key: params[0].to_owned(),
+            value: Value::from_bytes(params[1].to_owned()),
+            expiration,
+            options,
+        })
+    }
+
+    /// Parses `SETEX` and `PSETEX`, whose `option` is either `ex` or `px`.
+    pub fn parse_setex(params: &[Bytes], option: &[u8], name: &str) -> Result<Self, ClientError> {
+        let expiration = Expiration::parse(option, &params[1], name)?;
+        Ok(Self {
+            key: params[0].to_owned(),
+            value: Value::from_bytes(params[2].to_owned()),
+            expiration: Some(expiration.0),
+            options: Options::default(),
+        })
+    }
+
+    /// Parses `SETNX`, `GETSET` and the like, which take a key and a value and have `options`
+    /// built in.
+    pub fn parse_with(params: &[Bytes], options: Options) -> Self {
+        Self {
+            key: params[0].to_owned(),
+            value: Value::from_bytes(params[1].to_owned()),
+            expiration: None,
+            options,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DelEx {
+    pub key: Bytes,
+    /// Only `IFEQ`, `IFNE`, `IFDEQ` and `IFDNE` are accepted.
+    pub condition: Option<Condition>,
+}
+
+impl DelEx {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
+        let condition = match &params[1..] {
+            [] => None,
+            [option, value] => Some(
+                Condition::parse_comparison(&option.to_ascii_lowercase(), value)
+                    .ok_or(ClientError::SyntaxError)?,
+            ),
+            _ => return Err(ClientError::SyntaxError),
         };
+
         Ok(Self {
-            key,
-            value,
+            key: params[0].to_owned(),
+            condition,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MSet {
+    pub pairs: Vec<(Bytes, Value)>,
+}
+
+impl MSet {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
+        if !params.len().is_multiple_of(2) {
+            return Err(ClientError::WrongNumberOfArguments(MSET.to_string()));
+        }
+
+        Ok(Self {
+            pairs: params
+                .chunks_exact(2)
+                .map(|pair| (pair[0].clone(), Value::from_bytes(pair[1].clone())))
+                .collect(),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GetEx {
+    pub key: Bytes,
+    pub expiration: GetExExpiration,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GetExExpiration {
+    Keep,
+    Persist,
+    Set(SystemTime),
+}
+
+impl GetEx {
+    pub fn parse(params: &[Bytes]) -> Result<Self, ClientError> {
+        let expiration = match &params[1..] {
+            [] => GetExExpiration::Keep,
+            [option] if option.eq_ignore_ascii_case(b"persist") => GetExExpiration::Persist,
+            [option, value] => GetExExpiration::Set(Expiration::parse(option, value, GETEX)?.0),
+            _ => return Err(ClientError::SyntaxError),
+        };
+
+        Ok(Self {
+            key: params[0].to_owned(),
             expiration,
         })
     }
@@ -45,82 +231,81 @@ impl Set {
 #[derive(Debug, PartialEq)]
 struct Expiration(SystemTime);
 
-impl TryFrom<(String, String)> for Expiration {
-    type Error = ClientError;
+impl Expiration {
+    /// Parses an `EX`, `PX`, `EXAT` or `PXAT` option of the command `name`.
+    ///
+    /// Times must be positive, and representable in milliseconds since the Unix epoch.
+    fn parse(option: &[u8], value: &[u8], name: &str) -> Result<Self, ClientError> {
+        let time = parse_number::<i64>(value).ok_or(ClientError::IntegerError)?;
 
-    fn try_from((option, value): (String, String)) -> Result<Self, Self::Error> {
-        let to_add = value
-            .parse::<u64>()
-            .map_err(|_| ClientError::IntegerError)?;
+        let (unit, absolute) = match option.to_ascii_lowercase().as_slice() {
+            b"ex" => (1000, false),
+            b"px" => (1, false),
+            b"exat" => (1000, true),
+            b"pxat" => (1, true),
+            _ => return Err(ClientError::SyntaxError),
+        };
 
-        match option.to_lowercase().as_str() {
-            "ex" => {
-                let desired = SystemTime::now()
-                    .checked_add(Duration::from_secs(to_add))
-                    .ok_or(ClientError::IntegerError)?;
-                Ok(Expiration(desired))
-            }
-            "px" => {
-                let desired = SystemTime::now()
-                    .checked_add(Duration::from_millis(to_add))
-                    .ok_or(ClientError::IntegerError)?;
-                Ok(Expiration(desired))
-            }
-            "exat" => {
-                let desired = UNIX_EPOCH
-                    .checked_add(Duration::from_secs(to_add))
-                    .ok_or(ClientError::IntegerError)?;
-                Ok(Expiration(desired))
-            }
-            "pxat" => {
-                let desired = UNIX_EPOCH
-                    .checked_add(Duration::from_millis(to_add))
-                    .ok_or(ClientError::IntegerError)?;
-                Ok(Expiration(desired))
-            }
-            _ => Err(ClientError::SyntaxError),
+        let invalid = || ClientError::InvalidExpireTime(name.to_string());
+        if time <= 0 {
+            return Err(invalid());
+        }
+        let millis = time.checked_mul(unit).ok_or_else(invalid)?;
+        let duration = Duration::from_millis(millis as u64);
+
+        if absolute {
+            return Ok(Expiration(UNIX_EPOCH + duration));
         }
+        let now = clock::now();
+        to_unix_ms(now).checked_add(millis).ok_or_else(invalid)?;
+        Ok(Expiration(now + duration))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::{
+        clock::{MockClock, from_unix_ms},
+        cmd::parser::to_params,
+    };
+
     use super::*;
 
     #[test]
     fn parse_one_arg() {
         assert_eq!(
             ClientError::WrongNumberOfArguments(SET.to_string()),
-            Set::parse(&["".to_string()]).unwrap_err()
+            Set::parse(&[Bytes::new()]).unwrap_err()
         );
     }
 
     #[test]
     fn parse_three_args() {
-        let params = &["".to_string(), "".to_string(), "".to_string()];
+        let params = &[Bytes::new(), Bytes::new(), Bytes::new()];
         assert_eq!(ClientError::SyntaxError, Set::parse(params).unwrap_err());
     }
 
     #[test]
     fn parse_five_args() {
         let params = &[
-            "".to_string(),
-            "".to_string(),
-            "".to_string(),
-            "".to_string(),
-            "".to_string(),
+            Bytes::new(),
+            Bytes::new(),
+            Bytes::new(),
+            Bytes::new(),
+            Bytes::new(),
         ];
         assert_eq!(ClientError::SyntaxError, Set::parse(params).unwrap_err());
     }
 
     #[test]
     fn parse_two_args() {
-        let params = &["key".to_string(), "value".to_string()];
+        let params = &[Bytes::from("key"), Bytes::from("value")];
         assert_eq!(
             Set {
-                key: "key".to_string(),
-                value: Value::String("value".to_string()),
-                expiration: None
+                key: Bytes::from("key"),
+                value: Value::String(Bytes::from("value")),
+                expiration: None,
+                options: Options::default(),
             },
             Set::parse(params).unwrap()
         );
@@ -128,30 +313,43 @@ mod tests {
 
     #[test]
     fn parse_two_args_num() {
-        let params = &["key".to_string(), "1".to_string()];
+        let params = &[Bytes::from("key"), Bytes::from("1")];
         assert_eq!(
             Set {
-                key: "key".to_string(),
+                key: Bytes::from("key"),
                 value: Value::Integer(1),
-                expiration: None
+                expiration: None,
+                options: Options::default(),
             },
             Set::parse(params).unwrap()
         );
     }
 
+    #[test]
+    fn parse_two_args_padded_num() {
+        for value in ["007", "+5"] {
+            let params = &[Bytes::from("key"), Bytes::from(value)];
+            assert_eq!(
+                Set::parse(params).unwrap().value,
+                Value::String(Bytes::from(value))
+            );
+        }
+    }
+
     #[test]
     fn parse_four_args() {
         let params = &[
-            "key".to_string(),
-            "value".to_string(),
-            "exat".to_string(),
-            "10".to_string(),
+            Bytes::from("key"),
+            Bytes::from("value"),
+            Bytes::from("exat"),
+            Bytes::from("10"),
         ];
         assert_eq!(
             Set {
-                key: "key".to_string(),
-                value: Value::String("value".to_string()),
-                expiration: Some(UNIX_EPOCH.checked_add(Duration::from_secs(10)).unwrap())
+                key: Bytes::from("key"),
+                value: Value::String(Bytes::from("value")),
+                expiration: Some(UNIX_EPOCH.checked_add(Duration::from_secs(10)).unwrap()),
+                options: Options::default(),
             },
             Set::parse(params).unwrap()
         );
@@ -160,16 +358,17 @@ mod tests {
     #[test]
     fn parse_four_args_num() {
         let params = &[
-            "key".to_string(),
-            "-1".to_string(),
-            "exat".to_string(),
-            "10".to_string(),
+            Bytes::from("key"),
+            Bytes::from("-1"),
+            Bytes::from("exat"),
+            Bytes::from("10"),
         ];
         assert_eq!(
             Set {
-                key: "key".to_string(),
+                key: Bytes::from("key"),
                 value: Value::Integer(-1),
-                expiration: Some(UNIX_EPOCH.checked_add(Duration::from_secs(10)).unwrap())
+                expiration: Some(UNIX_EPOCH.checked_add(Duration::from_secs(10)).unwrap()),
+                options: Options::default(),
             },
             Set::parse(params).unwrap()
         );
@@ -178,19 +377,226 @@ mod tests {
     #[test]
     fn parse_four_args_err() {
         let params = &[
-            "key".to_string(),
-            "value".to_string(),
-            "NOTVALID".to_string(),
-            "10".to_string(),
+            Bytes::from("key"),
+            Bytes::from("value"),
+            Bytes::from("NOTVALID"),
+            Bytes::from("10"),
         ];
         assert_eq!(ClientError::SyntaxError, Set::parse(params).unwrap_err());
     }
 
+    #[test]
+    fn parse_nx_px() {
+        let set = Set::parse(&to_params(&["key", "value", "NX", "PX", "100"])).unwrap();
+        assert_eq!(set.options.condition, Some(Condition::Nx));
+        assert!(set.expiration.is_some());
+    }
+
+    #[test]
+    fn parse_options_any_order() {
+        let set = Set::parse(&to_params(&["key", "value", "keepttl", "get", "xx"])).unwrap();
+        assert_eq!(
+            set.options,
+            Options {
+                condition: Some(Condition::Xx),
+                get: true,
+                keep_ttl: true,
+            }
+        );
+        assert_eq!(set.expiration, None);
+    }
+
+    #[test]
+    fn parse_repeated_options() {
+        let params = to_params(&["key", "value", "nx", "nx", "exat", "1", "exat", "2"]);
+        let set = Set::parse(&params).unwrap();
+        assert_eq!(set.options.condition, Some(Condition::Nx));
+        assert_eq!(set.expiration, Some(UNIX_EPOCH + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn parse_conflicting_options() {
+        for options in [
+            &["nx", "xx"][..],
+            &["xx", "nx"],
+            &["ex", "10", "px", "100"],
+            &["keepttl", "ex", "10"],
+            &["pxat", "10", "keepttl"],
+            &["ex"],
+        ] {
+            let mut params = vec!["key", "value"];
+            params.extend_from_slice(options);
+            assert_eq!(
+                Set::parse(&to_params(&params)).unwrap_err(),
+                ClientError::SyntaxError,
+                "{options:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_invalid_expire_time() {
+        for time in ["0", "-1"] {
+            let params = to_params(&["key", "value", "ex", time]);
+            assert_eq!(
+                Set::parse(&params).unwrap_err(),
+                ClientError::InvalidExpireTime(SET.to_string())
+            );
+        }
+        let params = to_params(&["key", "value", "ex", "9223372036854775807"]);
+        assert_eq!(
+            Set::parse(&params).unwrap_err(),
+            ClientError::InvalidExpireTime(SET.to_string())
+        );
+    }
+
+    #[test]
+    fn parse_setex() {
+        let set = Set::parse_setex(&to_params(&["key", "10", "value"]), b"ex", "setex").unwrap();
+        assert_eq!(set.value, Value::String(Bytes::from("value")));
+        assert!(set.expiration.is_some());
+
+        assert_eq!(
+            Set::parse_setex(&to_params(&["key", "0", "value"]), b"px", "psetex").unwrap_err(),
+            ClientError::InvalidExpireTime("psetex".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_ifeq() {
+        let set = Set::parse(&to_params(&["key", "new", "IFEQ", "old", "get"])).unwrap();
+        assert_eq!(
+            set.options.condition,
+            Some(Condition::IfEq(Bytes::from("old")))
+        );
+        assert!(set.options.get);
+    }
+
+    #[test]
+    fn parse_comparison_conflicts() {
+        for options in [
+            &["ifeq", "a", "ifne", "b"][..],
+            &["ifeq", "a", "ifeq", "b"],
+            &["nx", "ifdeq", "a"],
+            &["ifdne", "a", "xx"],
+            &["ifeq"],
+        ] {
+            let mut params = vec!["key", "value"];
+            params.extend_from_slice(options);
+            assert_eq!(
+                Set::parse(&to_params(&params)).unwrap_err(),
+                ClientError::SyntaxError,
+                "{options:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_delex() {
+        let delex = |params: &[&'static str]| DelEx::parse(&to_params(params));
+        assert_eq!(delex(&["key"]).unwrap().condition, None);
+        assert_eq!(
+            delex(&["key", "IFDNE", "abc"]).unwrap().condition,
+            Some(Condition::IfDne(Bytes::from("abc")))
+        );
+        assert_eq!(
+            delex(&["key", "nx", "a"]).unwrap_err(),
+            ClientError::SyntaxError
+        );
+        assert_eq!(
+            delex(&["key", "ifeq"]).unwrap_err(),
+            ClientError::SyntaxError
+        );
+    }
+
+    #[test]
+    fn parse_mset() {
+        let mset = MSet::parse(&to_params(&["a", "1", "b", "x"])).unwrap();
+        assert_eq!(
+            mset.pairs,
+            vec![
+                (Bytes::from("a"), Value::Integer(1)),
+                (Bytes::from("b"), Value::String(Bytes::from("x"))),
+            ]
+        );
+        assert_eq!(
+            MSet::parse(&to_params(&["a", "1", "b"])).unwrap_err(),
+            ClientError::WrongNumberOfArguments(MSET.to_string())
+        );
+    }
+
+    #[test]
+    fn condition_allows() {
+        let value = Value::from_bytes(Bytes::from("a"));
+        let eq = Condition::IfEq(Bytes::from("a"));
+        assert_eq!(eq.allows(Some(&value)), Ok(true));
+        assert_eq!(eq.allows(None), Ok(false));
+
+        let ne = Condition::IfNe(Bytes::from("a"));
+        assert_eq!(ne.allows(Some(&value)), Ok(false));
+        assert_eq!(ne.allows(None), Ok(true));
+    }
+
+    #[test]
+    fn condition_allows_integers() {
+        let value = Value::from_bytes(Bytes::from("10"));
+        assert_eq!(
+            Condition::IfEq(Bytes::from("10")).allows(Some(&value)),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn condition_allows_digest() {
+        let value = Value::from_bytes(Bytes::from("a"));
+        let d = Bytes::from(digest(b"a").to_uppercase());
+        assert_eq!(Condition::IfDeq(d.clone()).allows(Some(&value)), Ok(true));
+        assert_eq!(Condition::IfDne(d.clone()).allows(Some(&value)), Ok(false));
+        assert_eq!(Condition::IfDeq(d.clone()).allows(None), Ok(false));
+        assert_eq!(Condition::IfDne(d).allows(None), Ok(true));
+    }
+
+    #[test]
+    fn condition_allows_wrong_type() {
+        let list = Value::List(Default::default());
+        assert_eq!(
+            Condition::IfEq(Bytes::from("a")).allows(Some(&list)),
+            Err(WrongType)
+        );
+        assert_eq!(Condition::Xx.allows(Some(&list)), Ok(true));
+    }
+
+    #[test]
+    fn parse_getex() {
+        let getex = |params: &[&'static str]| GetEx::parse(&to_params(params));
+        assert_eq!(getex(&["key"]).unwrap().expiration, GetExExpiration::Keep);
+        assert_eq!(
+            getex(&["key", "PERSIST"]).unwrap().expiration,
+            GetExExpiration::Persist
+        );
+        assert_eq!(
+            getex(&["key", "exat", "1"]).unwrap().expiration,
+            GetExExpiration::Set(UNIX_EPOCH + Duration::from_secs(1))
+        );
+        assert_eq!(
+            getex(&["key", "ex", "0"]).unwrap_err(),
+            ClientError::InvalidExpireTime(GETEX.to_string())
+        );
+        assert_eq!(
+            getex(&["key", "persist", "ex", "1"]).unwrap_err(),
+            ClientError::SyntaxError
+        );
+        assert_eq!(
+            getex(&["key", "foo"]).unwrap_err(),
+            ClientError::SyntaxError
+        );
+    }
+
     #[test]
     fn expiration_no_number() {
         assert_eq!(
             ClientError::IntegerError,
-            Expiration::try_from(("".to_string(), "hola".to_string())).unwrap_err()
+            Expiration::parse(b"", b"hola", SET).unwrap_err()
         );
     }
 
@@ -198,8 +604,7 @@ mod tests {
     fn expiration_out_of_range_number() {
         assert_eq!(
             ClientError::IntegerError,
-            Expiration::try_from(("".to_string(), "1000000000000000000000".to_string()))
-                .unwrap_err()
+            Expiration::parse(b"", b"1000000000000000000000", SET).unwrap_err()
         );
     }
 
@@ -207,47 +612,45 @@ mod tests {
     fn expiration_wrong_command() {
         assert_eq!(
             ClientError::SyntaxError,
-            Expiration::try_from(("".to_string(), "100".to_string())).unwrap_err()
+            Expiration::parse(b"", b"100", SET).unwrap_err()
         );
     }
 
     #[test]
     fn expiration_ex_ok() {
-        let before = SystemTime::now();
-        let expiration = Expiration::try_from(("ex".to_string(), "1".to_string())).unwrap();
-        let after = SystemTime::now();
+        let now = UNIX_EPOCH + Duration::from_secs(1_000);
+        let _clock = MockClock::at(now);
 
-        let min_expected = before.checked_add(Duration::from_secs(1)).unwrap();
-        let max_expected = after.checked_add(Duration::from_secs(1)).unwrap();
-
-        assert!(expiration.0 >= min_expected);
-        assert!(expiration.0 <= max_expected);
+        let expiration = Expiration::parse(b"ex", b"1", SET).unwrap();
+        assert_eq!(expiration, Expiration(now + Duration::from_secs(1)));
     }
 
-    // to test the error case of px, `System::now()` has to be mocked
     #[test]
     fn expiration_px_ok() {
-        let before = SystemTime::now();
-        let expiration = Expiration::try_from(("px".to_string(), "100".to_string())).unwrap();
-        let after = SystemTime::now();
+        let now = UNIX_EPOCH + Duration::from_secs(1_000);
+        let _clock = MockClock::at(now);
+
+        let expiration = Expiration::parse(b"px", b"100", SET).unwrap();
+        assert_eq!(expiration, Expiration(now + Duration::from_millis(100)));
+    }
 
-        let min_expected = before.checked_add(Duration::from_millis(100)).unwrap();
-        let max_expected = after.checked_add(Duration::from_millis(100)).unwrap();
+    #[test]
+    fn expiration_px_out_of_range() {
+        let _clock = MockClock::at(from_unix_ms(i64::MAX - 10));
 
-        assert!(expiration.0 >= min_expected);
-        assert!(expiration.0 <= max_expected);
+        let err = Expiration::parse(b"px", b"100", SET).unwrap_err();
+        assert_eq!(ClientError::InvalidExpireTime(SET.to_string()), err);
     }
 
     #[test]
     fn expiration_ex_out_of_range() {
-        let err = Expiration::try_from(("ex".to_string(), "18446744073709551615".to_string()))
-            .unwrap_err();
+        let err = Expiration::parse(b"ex", b"18446744073709551615", SET).unwrap_err();
         assert_eq!(ClientError::IntegerError, err);
     }
 
     #[test]
     fn expiration_exat_ok() {
-        let exp = Expiration::try_from(("exat".to_string(), "1".to_string())).unwrap();
+        let exp = Expiration::parse(b"exat", b"1", SET).unwrap();
         assert_eq!(
             Expiration(UNIX_EPOCH.checked_add(Duration::from_secs(1)).unwrap()),
             exp
@@ -256,15 +659,14 @@ mod tests {
 
     #[test]
     fn expiration_exat_out_of_range() {
-        let err = Expiration::try_from(("exat".to_string(), "18446744073709551615".to_string()))
-            .unwrap_err();
+        let err = Expiration::parse(b"exat", b"18446744073709551615", SET).unwrap_err();
         assert_eq!(ClientError::IntegerError, err);
     }
 
     // to test the error case of pxat, `System::now()` has to be mocked
     #[test]
     fn expiration_pxat_ok() {
-        let exp = Expiration::try_from(("pxat".to_string(), "1".to_string())).unwrap();
+        let exp = Expiration::parse(b"pxat", b"1", SET).unwrap();
         assert_eq!(
             Expiration(UNIX_EPOCH.checked_add(Duration::from_millis(1)).unwrap()),
             exp
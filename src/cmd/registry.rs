@@ -0,0 +1,613 @@
+use std::{collections::HashMap, fmt::Display, sync::LazyLock};
+
+use bytes::Bytes;
+
+use crate::cmd::{
+    error::ClientError,
+    types::{
+        COMMAND, COPY, DBSIZE, DECR, DECRBY, DEL, DELEX, DIGEST, ECHO, EXISTS, EXPIRE, EXPIREAT,
+        EXPIRETIME, FLUSHALL, FLUSHDB, GET, GETDEL, GETEX, GETSET, HELLO, INCR, INCRBY, INFO, KEYS,
+        LPUSH, MEMORY, MOVE, MSET, OBJECT, PERSIST, PEXPIRE, PEXPIREAT, PEXPIRETIME, PING, PSETEX,
+        PTTL, RANDOMKEY, RENAME, RENAMENX, RPUSH, SCAN, SELECT, SET, SETEX, SETNX, SWAPDB, TOUCH,
+        TTL, TYPE, UNLINK,
+    },
+};
+
+/// Properties of a command, as reported by `COMMAND`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Flag {
+    Write,
+    ReadOnly,
+    DenyOom,
+    Fast,
+    NoScript,
+    Loading,
+    Stale,
+    NoAuth,
+}
+
+impl Display for Flag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Flag::Write => "write",
+            Flag::ReadOnly => "readonly",
+            Flag::DenyOom => "denyoom",
+            Flag::Fast => "fast",
+            Flag::NoScript => "noscript",
+            Flag::Loading => "loading",
+            Flag::Stale => "stale",
+            Flag::NoAuth => "no_auth",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// ACL categories a command belongs to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Category {
+    Keyspace,
+    Read,
+    Write,
+    String,
+    List,
+    Fast,
+    Slow,
+    Connection,
+    Dangerous,
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Category::Keyspace => "keyspace",
+            Category::Read => "read",
+            Category::Write => "write",
+            Category::String => "string",
+            Category::List => "list",
+            Category::Fast => "fast",
+            Category::Slow => "slow",
+            Category::Connection => "connection",
+            Category::Dangerous => "dangerous",
+        };
+        write!(f, "@{}", name)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CommandSpec {
+    /// Full name, e.g. `command|info` for subcommands.
+    pub name: &'static str,
+    /// Number of parameters, the command name included. Negative values are minimums: `-2`
+    /// means at least two parameters.
+    pub arity: i32,
+    pub flags: &'static [Flag],
+    /// Position of the first key in the parameters, `0` if the command takes no keys.
+    pub first_key: i32,
+    /// Position of the last key, negative values count from the end: `-1` is the last parameter.
+    pub last_key: i32,
+    /// Distance between two keys.
+    pub step: i32,
+    pub categories: &'static [Category],
+    pub group: &'static str,
+    pub since: &'static str,
+    pub summary: &'static str,
+    pub subcommands: &'static [CommandSpec],
+}
+
+impl CommandSpec {
+    const fn new(name: &'static str, arity: i32) -> Self {
+        Self {
+            name,
+            arity,
+            flags: &[],
+            first_key: 0,
+            last_key: 0,
+            step: 0,
+            categories: &[],
+            group: "",
+            since: "",
+            summary: "",
+            subcommands: &[],
+        }
+    }
+
+    const fn flags(self, flags: &'static [Flag]) -> Self {
+        Self { flags, ..self }
+    }
+
+    const fn keys(self, first_key: i32, last_key: i32, step: i32) -> Self {
+        Self {
+            first_key,
+            last_key,
+            step,
+            ..self
+        }
+    }
+
+    const fn categories(self, categories: &'static [Category]) -> Self {
+        Self { categories, ..self }
+    }
+
+    const fn docs(self, group: &'static str, since: &'static str, summary: &'static str) -> Self {
+        Self {
+            group,
+            since,
+            summary,
+            ..self
+        }
+    }
+
+    const fn subcommands(self, subcommands: &'static [CommandSpec]) -> Self {
+        Self {
+            subcommands,
+            ..self
+        }
+    }
+
+    /// Checks a call made of `argc` parameters, the command name included, against the arity.
+    pub fn check_arity(&self, argc: usize) -> Result<(), ClientError> {
+        let arity = self.arity.unsigned_abs() as usize;
+        if (self.arity >= 0 && argc != arity) || argc < arity {
+            return Err(ClientError::WrongNumberOfArguments(self.name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns the keys among the parameters of a call to the command, or to its subcommand for
+    /// a container like `OBJECT`.
+    pub fn keys_of<'a>(&self, params: &'a [Bytes]) -> Vec<&'a Bytes> {
+        if let Some(sub) = params.get(1).filter(|_| !self.subcommands.is_empty())
+            && let Some(spec) = self.subcommand(&String::from_utf8_lossy(sub).to_lowercase())
+        {
+            return spec.keys_of(params);
+        }
+        if self.first_key <= 0 {
+            return vec![];
+        }
+
+        let last_key = if self.last_key < 0 {
+            params.len() as i32 + self.last_key
+        } else {
+            self.last_key
+        };
+        (self.first_key..=last_key)
+            .step_by(self.step.max(1) as usize)
+            .filter_map(|i| params.get(i as usize))
+            .collect()
+    }
+
+    pub fn subcommand(&self, name: &str) -> Option<&'static CommandSpec> {
+        self.subcommands
+            .iter()
+            .find(|s| s.name.split_once('|').is_some_and(|(_, sub)| sub == name))
+    }
+}
+
+use Category as C;
+use Flag as F;
+
+static COMMAND_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("command|count", 2)
+        .flags(&[F::Loading, F::Stale])
+        .categories(&[C::Slow, C::Connection])
+        .docs("server", "2.8.13", "Returns a count of commands."),
+    CommandSpec::new("command|info", -2)
+        .flags(&[F::Loading, F::Stale])
+        .categories(&[C::Slow, C::Connection])
+        .docs(
+            "server",
+            "2.8.13",
+            "Returns information about one, multiple or all commands.",
+        ),
+    CommandSpec::new("command|docs", -2)
+        .flags(&[F::Loading, F::Stale])
+        .categories(&[C::Slow, C::Connection])
+        .docs(
+            "server",
+            "7.0.0",
+            "Returns documentary information about one, multiple or all commands.",
+        ),
+    CommandSpec::new("command|getkeys", -3)
+        .flags(&[F::Loading, F::Stale])
+        .categories(&[C::Slow, C::Connection])
+        .docs(
+            "server",
+            "2.8.13",
+            "Extracts the key names from an arbitrary command.",
+        ),
+];
+
+static OBJECT_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("object|encoding", 3)
+        .flags(&[F::ReadOnly])
+        .keys(2, 2, 1)
+        .categories(&[C::Keyspace, C::Read, C::Slow])
+        .docs(
+            "generic",
+            "2.2.3",
+            "Returns the internal encoding of a Redis object.",
+        ),
+    CommandSpec::new("object|freq", 3)
+        .flags(&[F::ReadOnly])
+        .keys(2, 2, 1)
+        .categories(&[C::Keyspace, C::Read, C::Slow])
+        .docs(
+            "generic",
+            "4.0.0",
+            "Returns the logarithmic access frequency counter of a Redis object.",
+        ),
+    CommandSpec::new("object|idletime", 3)
+        .flags(&[F::ReadOnly])
+        .keys(2, 2, 1)
+        .categories(&[C::Keyspace, C::Read, C::Slow])
+        .docs(
+            "generic",
+            "2.2.3",
+            "Returns the time since the last access to a Redis object.",
+        ),
+    CommandSpec::new("object|refcount", 3)
+        .flags(&[F::ReadOnly])
+        .keys(2, 2, 1)
+        .categories(&[C::Keyspace, C::Read, C::Slow])
+        .docs(
+            "generic",
+            "2.2.3",
+            "Returns the reference count of a value of a key.",
+        ),
+];
+
+static MEMORY_SUBCOMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("memory|usage", -3)
+        .flags(&[F::ReadOnly])
+        .keys(2, 2, 1)
+        .categories(&[C::Read, C::Slow])
+        .docs("server", "4.0.0", "Estimates the memory usage of a key."),
+    CommandSpec::new("memory|stats", 2)
+        .categories(&[C::Slow])
+        .docs("server", "4.0.0", "Returns details about memory usage."),
+];
+
+/// Every command the server knows about.
+pub static COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new(PING, -1)
+        .flags(&[F::Fast])
+        .categories(&[C::Fast, C::Connection])
+        .docs("connection", "1.0.0", "Returns the server's liveliness response."),
+    CommandSpec::new(ECHO, 2)
+        .flags(&[F::Fast])
+        .categories(&[C::Fast, C::Connection])
+        .docs("connection", "1.0.0", "Returns the given string."),
+    CommandSpec::new(HELLO, -1)
+        .flags(&[F::NoScript, F::Loading, F::Stale, F::Fast, F::NoAuth])
+        .categories(&[C::Fast, C::Connection])
+        .docs("connection", "6.0.0", "Handshakes with the Redis server."),
+    CommandSpec::new(COMMAND, -1)
+        .flags(&[F::Loading, F::Stale])
+        .categories(&[C::Slow, C::Connection])
+        .docs("server", "2.8.13", "Returns detailed information about all commands.")
+        .subcommands(COMMAND_SUBCOMMANDS),
+    CommandSpec::new(INFO, -1)
+        .flags(&[F::Loading, F::Stale])
+        .categories(&[C::Slow, C::Dangerous])
+        .docs("server", "1.0.0", "Returns information and statistics about the server."),
+    CommandSpec::new(MEMORY, -2)
+        .categories(&[C::Slow])
+        .docs("server", "4.0.0", "A container for memory diagnostics commands.")
+        .subcommands(MEMORY_SUBCOMMANDS),
+    CommandSpec::new(SELECT, 2)
+        .flags(&[F::Loading, F::Stale, F::Fast])
+        .categories(&[C::Fast, C::Connection])
+        .docs("connection", "1.0.0", "Changes the selected database."),
+    CommandSpec::new(SWAPDB, 3)
+        .flags(&[F::Write, F::Fast])
+        .categories(&[C::Keyspace, C::Write, C::Fast, C::Dangerous])
+        .docs("server", "4.0.0", "Swaps two Redis databases."),
+    CommandSpec::new(FLUSHDB, -1)
+        .flags(&[F::Write])
+        .categories(&[C::Keyspace, C::Write, C::Slow, C::Dangerous])
+        .docs("server", "1.0.0", "Remove all keys from the current database."),
+    CommandSpec::new(FLUSHALL, -1)
+        .flags(&[F::Write])
+        .categories(&[C::Keyspace, C::Write, C::Slow, C::Dangerous])
+        .docs("server", "1.0.0", "Removes all keys from all databases."),
+    CommandSpec::new(DBSIZE, 1)
+        .flags(&[F::ReadOnly, F::Fast])
+        .categories(&[C::Keyspace, C::Read, C::Fast])
+        .docs("server", "1.0.0", "Returns the number of keys in the database."),
+    CommandSpec::new(GET, 2)
+        .flags(&[F::ReadOnly, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Read, C::String, C::Fast])
+        .docs("string", "1.0.0", "Returns the string value of a key."),
+    CommandSpec::new(SET, -3)
+        .flags(&[F::Write, F::DenyOom])
+        .keys(1, 1, 1)
+        .categories(&[C::Write, C::String, C::Slow])
+        .docs("string", "1.0.0", "Sets the string value of a key, ignoring its type. The key is created if it doesn't exist."),
+    CommandSpec::new(MSET, -3)
+        .flags(&[F::Write, F::DenyOom])
+        .keys(1, -1, 2)
+        .categories(&[C::Write, C::String, C::Slow])
+        .docs("string", "1.0.1", "Atomically creates or modifies the string values of one or more keys."),
+    CommandSpec::new(GETSET, 3)
+        .flags(&[F::Write, F::DenyOom, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Write, C::String, C::Fast])
+        .docs("string", "1.0.0", "Returns the previous string value of a key after setting it to a new value."),
+    CommandSpec::new(GETDEL, 2)
+        .flags(&[F::Write, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Write, C::String, C::Fast])
+        .docs("string", "6.2.0", "Returns the string value of a key after deleting the key."),
+    CommandSpec::new(GETEX, -2)
+        .flags(&[F::Write, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Write, C::String, C::Fast])
+        .docs("string", "6.2.0", "Returns the string value of a key after setting its expiration time."),
+    CommandSpec::new(SETNX, 3)
+        .flags(&[F::Write, F::DenyOom, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Write, C::String, C::Fast])
+        .docs("string", "1.0.0", "Set the string value of a key only when the key doesn't exist."),
+    CommandSpec::new(SETEX, 4)
+        .flags(&[F::Write, F::DenyOom])
+        .keys(1, 1, 1)
+        .categories(&[C::Write, C::String, C::Slow])
+        .docs("string", "2.0.0", "Sets the string value and expiration time of a key. Creates the key if it doesn't exist."),
+    CommandSpec::new(PSETEX, 4)
+        .flags(&[F::Write, F::DenyOom])
+        .keys(1, 1, 1)
+        .categories(&[C::Write, C::String, C::Slow])
+        .docs("string", "2.6.0", "Sets both string value and expiration time in milliseconds of a key. The key is created if it doesn't exist."),
+    CommandSpec::new(DIGEST, 2)
+        .flags(&[F::ReadOnly, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Read, C::String, C::Fast])
+        .docs("string", "8.4.0", "Returns the XXH3 hash of a string value."),
+    CommandSpec::new(DELEX, -2)
+        .flags(&[F::Write, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Keyspace, C::Write, C::Fast])
+        .docs("string", "8.4.0", "Conditionally removes the specified key based on value or digest comparison."),
+    CommandSpec::new(DEL, -2)
+        .flags(&[F::Write])
+        .keys(1, -1, 1)
+        .categories(&[C::Keyspace, C::Write, C::Slow])
+        .docs("generic", "1.0.0", "Deletes one or more keys."),
+    CommandSpec::new(EXISTS, -2)
+        .flags(&[F::ReadOnly, F::Fast])
+        .keys(1, -1, 1)
+        .categories(&[C::Keyspace, C::Read, C::Fast])
+        .docs("generic", "1.0.0", "Determines whether one or more keys exist."),
+    CommandSpec::new(EXPIRE, -3)
+        .flags(&[F::Write, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Keyspace, C::Write, C::Fast])
+        .docs("generic", "1.0.0", "Sets the expiration time of a key in seconds."),
+    CommandSpec::new(PEXPIRE, -3)
+        .flags(&[F::Write, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Keyspace, C::Write, C::Fast])
+        .docs("generic", "2.6.0", "Sets the expiration time of a key in milliseconds."),
+    CommandSpec::new(EXPIREAT, -3)
+        .flags(&[F::Write, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Keyspace, C::Write, C::Fast])
+        .docs("generic", "1.2.0", "Sets the expiration time of a key to a Unix timestamp."),
+    CommandSpec::new(PEXPIREAT, -3)
+        .flags(&[F::Write, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Keyspace, C::Write, C::Fast])
+        .docs("generic", "2.6.0", "Sets the expiration time of a key to a Unix milliseconds timestamp."),
+    CommandSpec::new(TTL, 2)
+        .flags(&[F::ReadOnly, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Keyspace, C::Read, C::Fast])
+        .docs("generic", "1.0.0", "Returns the expiration time in seconds of a key."),
+    CommandSpec::new(PTTL, 2)
+        .flags(&[F::ReadOnly, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Keyspace, C::Read, C::Fast])
+        .docs("generic", "2.6.0", "Returns the expiration time in milliseconds of a key."),
+    CommandSpec::new(EXPIRETIME, 2)
+        .flags(&[F::ReadOnly, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Keyspace, C::Read, C::Fast])
+        .docs("generic", "7.0.0", "Returns the expiration time of a key as a Unix timestamp."),
+    CommandSpec::new(PEXPIRETIME, 2)
+        .flags(&[F::ReadOnly, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Keyspace, C::Read, C::Fast])
+        .docs("generic", "7.0.0", "Returns the expiration time of a key as a Unix milliseconds timestamp."),
+    CommandSpec::new(PERSIST, 2)
+        .flags(&[F::Write, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Keyspace, C::Write, C::Fast])
+        .docs("generic", "2.2.0", "Removes the expiration time of a key."),
+    CommandSpec::new(MOVE, 3)
+        .flags(&[F::Write, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Keyspace, C::Write, C::Fast])
+        .docs("generic", "1.0.0", "Moves a key to another database."),
+    CommandSpec::new(KEYS, 2)
+        .flags(&[F::ReadOnly])
+        .categories(&[C::Keyspace, C::Read, C::Slow, C::Dangerous])
+        .docs("generic", "1.0.0", "Returns all key names that match a pattern."),
+    CommandSpec::new(SCAN, -2)
+        .flags(&[F::ReadOnly])
+        .categories(&[C::Keyspace, C::Read, C::Slow])
+        .docs("generic", "2.8.0", "Iterates over the key names in the database."),
+    CommandSpec::new(TYPE, 2)
+        .flags(&[F::ReadOnly, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Keyspace, C::Read, C::Fast])
+        .docs("generic", "1.0.0", "Determines the type of value stored at a key."),
+    CommandSpec::new(RENAME, 3)
+        .flags(&[F::Write])
+        .keys(1, 2, 1)
+        .categories(&[C::Keyspace, C::Write, C::Slow])
+        .docs("generic", "1.0.0", "Renames a key and overwrites the destination."),
+    CommandSpec::new(RENAMENX, 3)
+        .flags(&[F::Write, F::Fast])
+        .keys(1, 2, 1)
+        .categories(&[C::Keyspace, C::Write, C::Fast])
+        .docs("generic", "1.0.0", "Renames a key only when the target key name doesn't exist."),
+    CommandSpec::new(COPY, -3)
+        .flags(&[F::Write, F::DenyOom])
+        .keys(1, 2, 1)
+        .categories(&[C::Keyspace, C::Write, C::Slow])
+        .docs("generic", "6.2.0", "Copies the value of a key to a new key."),
+    CommandSpec::new(TOUCH, -2)
+        .flags(&[F::ReadOnly, F::Fast])
+        .keys(1, -1, 1)
+        .categories(&[C::Keyspace, C::Read, C::Fast])
+        .docs("generic", "3.2.1", "Returns the number of existing keys out of those specified after updating the time they were last accessed."),
+    CommandSpec::new(RANDOMKEY, 1)
+        .flags(&[F::ReadOnly])
+        .categories(&[C::Keyspace, C::Read, C::Slow])
+        .docs("generic", "1.0.0", "Returns a random key name from the database."),
+    CommandSpec::new(UNLINK, -2)
+        .flags(&[F::Write, F::Fast])
+        .keys(1, -1, 1)
+        .categories(&[C::Keyspace, C::Write, C::Fast])
+        .docs("generic", "4.0.0", "Asynchronously deletes one or more keys."),
+    CommandSpec::new(OBJECT, -2)
+        .categories(&[C::Slow])
+        .docs("generic", "2.2.3", "A container for object introspection commands.")
+        .subcommands(OBJECT_SUBCOMMANDS),
+    CommandSpec::new(INCR, 2)
+        .flags(&[F::Write, F::DenyOom, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Write, C::String, C::Fast])
+        .docs("string", "1.0.0", "Increments the integer value of a key by one. Uses 0 as initial value if the key doesn't exist."),
+    CommandSpec::new(DECR, 2)
+        .flags(&[F::Write, F::DenyOom, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Write, C::String, C::Fast])
+        .docs("string", "1.0.0", "Decrements the integer value of a key by one. Uses 0 as initial value if the key doesn't exist."),
+    CommandSpec::new(INCRBY, 3)
+        .flags(&[F::Write, F::DenyOom, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Write, C::String, C::Fast])
+        .docs("string", "1.0.0", "Increments the integer value of a key by a number. Uses 0 as initial value if the key doesn't exist."),
+    CommandSpec::new(DECRBY, 3)
+        .flags(&[F::Write, F::DenyOom, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Write, C::String, C::Fast])
+        .docs("string", "1.0.0", "Decrements a number from the integer value of a key. Uses 0 as initial value if the key doesn't exist."),
+    CommandSpec::new(LPUSH, -3)
+        .flags(&[F::Write, F::DenyOom, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Write, C::List, C::Fast])
+        .docs("list", "1.0.0", "Prepends one or more elements to a list. Creates the key if it doesn't exist."),
+    CommandSpec::new(RPUSH, -3)
+        .flags(&[F::Write, F::DenyOom, F::Fast])
+        .keys(1, 1, 1)
+        .categories(&[C::Write, C::List, C::Fast])
+        .docs("list", "1.0.0", "Appends one or more elements to a list. Creates the key if it doesn't exist."),
+];
+
+static INDEX: LazyLock<HashMap<&'static str, &'static CommandSpec>> =
+    LazyLock::new(|| COMMANDS.iter().map(|c| (c.name, c)).collect());
+
+/// Looks up a command by its lowercase name.
+pub fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    INDEX.get(name).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cmd::{
+        parser::to_params,
+        types::{COUNT, DOCS, GETKEYS},
+    };
+
+    use super::*;
+
+    #[test]
+    fn lookup_ok() {
+        assert_eq!(lookup(GET).unwrap().name, GET);
+        assert!(lookup("GET").is_none());
+        assert!(lookup("unknown").is_none());
+    }
+
+    #[test]
+    fn names_are_unique() {
+        assert_eq!(INDEX.len(), COMMANDS.len());
+    }
+
+    #[test]
+    fn check_arity_fixed() {
+        let get = lookup(GET).unwrap();
+        assert!(get.check_arity(2).is_ok());
+        assert_eq!(
+            get.check_arity(1).unwrap_err(),
+            ClientError::WrongNumberOfArguments(GET.to_string())
+        );
+        assert!(get.check_arity(3).is_err());
+    }
+
+    #[test]
+    fn check_arity_minimum() {
+        let del = lookup(DEL).unwrap();
+        assert!(del.check_arity(2).is_ok());
+        assert!(del.check_arity(4).is_ok());
+        assert!(del.check_arity(1).is_err());
+    }
+
+    #[test]
+    fn keys_of_single_key() {
+        let set = lookup(SET).unwrap();
+        assert_eq!(
+            set.keys_of(&to_params(&["set", "k", "v", "ex", "10"])),
+            vec!["k"]
+        );
+    }
+
+    #[test]
+    fn keys_of_all_keys() {
+        let del = lookup(DEL).unwrap();
+        assert_eq!(
+            del.keys_of(&to_params(&["del", "a", "b", "c"])),
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn keys_of_every_other_param() {
+        let mset = lookup(MSET).unwrap();
+        assert_eq!(
+            mset.keys_of(&to_params(&["mset", "a", "1", "b", "2"])),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn keys_of_subcommand() {
+        let object = lookup(OBJECT).unwrap();
+        assert_eq!(
+            object.keys_of(&to_params(&["object", "ENCODING", "k"])),
+            vec!["k"]
+        );
+        let unknown = to_params(&["object", "foo", "k"]);
+        assert!(object.keys_of(&unknown).is_empty());
+        let command = to_params(&["command", "info", "get"]);
+        assert!(lookup(COMMAND).unwrap().keys_of(&command).is_empty());
+    }
+
+    #[test]
+    fn keys_of_no_keys() {
+        let ping = lookup(PING).unwrap();
+        assert!(ping.keys_of(&to_params(&["ping", "hello"])).is_empty());
+    }
+
+    #[test]
+    fn subcommand_ok() {
+        let command = lookup(COMMAND).unwrap();
+        assert_eq!(command.subcommand(INFO).unwrap().name, "command|info");
+        assert_eq!(command.subcommand(COUNT).unwrap().name, "command|count");
+        assert_eq!(command.subcommand(DOCS).unwrap().name, "command|docs");
+        assert_eq!(command.subcommand(GETKEYS).unwrap().name, "command|getkeys");
+        assert!(command.subcommand("foo").is_none());
+    }
+}
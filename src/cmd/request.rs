@@ -1,34 +1,130 @@
+use bytes::Bytes;
+
 use crate::{
+    client::Client,
     cmd::{
         error::ClientError,
-        execution::{arithmetic::Integer, list::List},
+        execution::{
+            arithmetic::Integer,
+            expire::{GetExpiration, SetExpiration, persist},
+            info::info,
+            keyspace::{rename, touch, type_of, unlink},
+            list::List,
+            scan::keys,
+            set::{digest_of, getdel},
+        },
         parser::{
-            arithmetic::Integer as IntegerParser, list::List as ListParser, set::Set as SetParser,
+            arithmetic::Integer as IntegerParser,
+            command::Command as CommandParser,
+            database::{Flush, Move as MoveParser, SwapDb as SwapDbParser},
+            expire::Expire as ExpireParser,
+            hello::Hello as HelloParser,
+            keyspace::CopyKey,
+            list::List as ListParser,
+            memory::Memory as MemoryParser,
+            object::Object as ObjectParser,
+            parse_number,
+            scan::Scan as ScanParser,
+            set::{
+                Condition as SetCondition, DelEx as DelExParser, GetEx as GetExParser,
+                MSet as MSetParser, Options as SetOptions, Set as SetParser,
+            },
         },
+        registry::{self, Flag},
         response::Response,
-        types::{DECR, DECRBY, DEL, ECHO, EXISTS, GET, INCR, INCRBY, LPUSH, PING, RPUSH, SET},
+        types::{
+            COMMAND, COPY, DBSIZE, DECR, DECRBY, DEL, DELEX, DIGEST, ECHO, EXISTS, EXPIRE,
+            EXPIREAT, EXPIRETIME, FLUSHALL, FLUSHDB, GET, GETDEL, GETEX, GETSET, HELLO, INCR,
+            INCRBY, INFO, KEYS, LPUSH, MEMORY, MOVE, MSET, OBJECT, PERSIST, PEXPIRE, PEXPIREAT,
+            PEXPIRETIME, PING, PSETEX, PTTL, RANDOMKEY, RENAME, RENAMENX, RPUSH, SCAN, SELECT, SET,
+            SETEX, SETNX, SWAPDB, TOUCH, TTL, TYPE, UNLINK,
+        },
     },
-    db::{Db, Object},
+    db::{Database, Databases, Kind},
+    evict::Evictor,
+    stats::Stats,
 };
 
 #[derive(Debug, PartialEq)]
 pub enum Request {
-    Ping(Option<String>),
-    Echo(String),
-    Get(String),
+    Ping(Option<Bytes>),
+    Echo(Bytes),
+    Get(Bytes),
     Set(SetParser),
-    Exists(Vec<String>),
-    Del(Vec<String>),
-    Incr(String),
-    Decr(String),
+    MSet(MSetParser),
+    Exists(Vec<Bytes>),
+    Del(Vec<Bytes>),
+    Incr(Bytes),
+    Decr(Bytes),
     IncrBy(IntegerParser),
     DecrBy(IntegerParser),
     LPush(ListParser),
     RPush(ListParser),
+    Hello(HelloParser),
+    Command(CommandParser),
+    Expire(ExpireParser),
+    PExpire(ExpireParser),
+    ExpireAt(ExpireParser),
+    PExpireAt(ExpireParser),
+    Ttl(Bytes),
+    PTtl(Bytes),
+    ExpireTime(Bytes),
+    PExpireTime(Bytes),
+    Persist(Bytes),
+    SetNx(SetParser),
+    GetDel(Bytes),
+    GetEx(GetExParser),
+    DelEx(DelExParser),
+    Digest(Bytes),
+    /// Sections to report, the default ones when empty.
+    Info(Vec<Bytes>),
+    /// Index of the database, as given by the client.
+    Select(i64),
+    Move(MoveParser),
+    SwapDb(SwapDbParser),
+    FlushDb(Flush),
+    FlushAll(Flush),
+    /// Pattern of the keys.
+    Keys(Bytes),
+    Scan(ScanParser),
+    Type(Bytes),
+    /// Key and new name.
+    Rename(Bytes, Bytes),
+    RenameNx(Bytes, Bytes),
+    Copy(CopyKey),
+    Touch(Vec<Bytes>),
+    RandomKey,
+    DbSize,
+    Unlink(Vec<Bytes>),
+    Object(ObjectParser),
+    Memory(MemoryParser),
 }
 
 impl Request {
-    pub fn execute(self, db: &Db) -> Response {
+    /// Parses a request like [`Request::try_from`], refusing the commands that may use more
+    /// memory when memory is over `maxmemory`, as Redis does with the commands flagged `denyoom`.
+    pub fn parse(params: Vec<Bytes>, out_of_memory: bool) -> Result<Self, ClientError> {
+        let deny_oom = out_of_memory
+            && params
+                .first()
+                .and_then(|name| registry::lookup(&String::from_utf8_lossy(name).to_lowercase()))
+                .is_some_and(|spec| spec.flags.contains(&Flag::DenyOom));
+
+        let request = Self::try_from(params)?;
+        if deny_oom {
+            return Err(ClientError::OutOfMemory);
+        }
+        Ok(request)
+    }
+
+    pub fn execute(
+        self,
+        dbs: &Databases,
+        stats: &Stats,
+        evictor: &Evictor,
+        client: &mut Client,
+    ) -> Response {
+        let db = &dbs[client.db];
         match self {
             Self::Ping(val) => val.map_or(
                 Response::SimpleString("PONG".to_string()),
@@ -38,60 +134,89 @@ impl Request {
             Self::Echo(val) => Response::BulkString(val),
 
             Self::Set(parser) => {
-                let mut map = db.lock().unwrap();
-                map.insert(parser.key, Object::new(parser.value, parser.expiration));
+                let get = parser.options.get;
+                match parser.execute(db) {
+                    Err(e) => Response::error(&e),
+                    Ok(outcome) if get => outcome
+                        .previous
+                        .map_or(Response::Null, Response::BulkString),
+                    Ok(outcome) if outcome.stored => Response::SimpleString("OK".to_string()),
+                    Ok(_) => Response::Null,
+                }
+            }
+
+            Self::MSet(parser) => {
+                parser.execute(db);
                 Response::SimpleString("OK".to_string())
             }
 
-            Self::Get(key) => {
-                let mut map = db.lock().unwrap();
+            Self::SetNx(parser) => parser.execute(db).map_or_else(
+                |e| Response::error(&e),
+                |outcome| Response::Integer(outcome.stored as i64),
+            ),
 
-                match map.get(&key) {
-                    None => Response::Null,
-                    Some(o) if o.is_expired() => {
-                        map.swap_remove(&key);
-                        Response::Null
-                    }
-                    Some(o) => Response::BulkString(o.value.to_string()),
+            Self::GetDel(key) => getdel(db, key).map_or_else(
+                |e| Response::error(&e),
+                |v| v.map_or(Response::Null, Response::BulkString),
+            ),
+
+            Self::GetEx(parser) => parser.execute(db).map_or_else(
+                |e| Response::error(&e),
+                |v| v.map_or(Response::Null, Response::BulkString),
+            ),
+
+            Self::DelEx(parser) => parser.execute(db).map_or_else(
+                |e| Response::error(&e),
+                |deleted| Response::Integer(deleted as i64),
+            ),
+
+            Self::Digest(key) => digest_of(db, key).map_or_else(
+                |e| Response::error(&e),
+                |d| d.map_or(Response::Null, |d| Response::BulkString(Bytes::from(d))),
+            ),
+
+            Self::Get(key) => {
+                let mut map = db.lock(&key);
+
+                match map.lookup(&key, Kind::String) {
+                    Err(e) => Response::error(&ClientError::from(e)),
+                    Ok(None) => Response::Null,
+                    Ok(Some(o)) => o
+                        .value
+                        .to_bytes()
+                        .map_or(Response::Null, Response::BulkString),
                 }
             }
 
             Self::Exists(keys) => {
-                let mut map = db.lock().unwrap();
-                let mut existing_keys = 0u64;
-
-                for k in keys {
-                    if let Some(o) = map.get(&k) {
-                        if o.is_expired() {
-                            map.swap_remove(&k);
-                        } else {
-                            existing_keys += 1;
-                        }
-                    }
-                }
+                let mut shards = db.lock_keys(&keys);
+                let existing_keys = keys
+                    .iter()
+                    .filter(|k| shards.shard(k).peek(k).is_some())
+                    .count();
 
-                Response::Integer(existing_keys.to_string())
+                Response::Integer(existing_keys as i64)
             }
 
             Self::Del(keys) => {
-                let mut map = db.lock().unwrap();
-                let mut deleted_keys = 0u64;
+                let mut shards = db.lock_keys(&keys);
+                let mut deleted_keys = 0i64;
 
-                for k in keys {
-                    if map.swap_remove(&k).is_some() {
+                for k in keys.iter() {
+                    if shards.shard(k).remove_live(k).is_some() {
                         deleted_keys += 1;
                     }
                 }
 
-                Response::Integer(deleted_keys.to_string())
+                Response::Integer(deleted_keys)
             }
 
             Self::Incr(key) => {
                 Integer::Incr
                     .execute(db, key)
                     .map_or_else(
-                        |e| Response::SimpleError(e.to_string()),
-                        |v| Response::Integer(v.to_string())
+                        |e| Response::error(&e),
+                        Response::Integer
                     )
             },
 
@@ -99,8 +224,8 @@ impl Request {
                 Integer::Decr
                     .execute(db, key)
                     .map_or_else(
-                        |e| Response::SimpleError(e.to_string()),
-                        |v| Response::Integer(v.to_string())
+                        |e| Response::error(&e),
+                        Response::Integer
                     )
             }
 
@@ -108,8 +233,8 @@ impl Request {
                 Integer::IncrBy(parser.value)
                     .execute(db, parser.key)
                     .map_or_else(
-                        |e| Response::SimpleError(e.to_string()),
-                        |v| Response::Integer(v.to_string())
+                        |e| Response::error(&e),
+                        Response::Integer
                     )
             }
 
@@ -117,154 +242,314 @@ impl Request {
                 Integer::DecrBy(parser.value)
                     .execute(db, parser.key)
                     .map_or_else(
-                        |e| Response::SimpleError(e.to_string()),
-                        |v| Response::Integer(v.to_string())
+                        |e| Response::error(&e),
+                        Response::Integer
                     )
             }
 
             Self::LPush(parser) => List::LPush
                 .execute(db, parser.key, parser.values)
-                .map_or_else(
-                    |e| Response::SimpleError(e.to_string()),
-                    |v| Response::Integer(v.to_string()),
-                ),
+                .map_or_else(|e| Response::error(&e), |v| Response::Integer(v as i64)),
 
             Self::RPush(parser) => List::RPush
                 .execute(db, parser.key, parser.values)
-                .map_or_else(
-                    |e| Response::SimpleError(e.to_string()),
-                    |v| Response::Integer(v.to_string()),
-                ),
-        }
-    }
-}
-
-impl TryFrom<Vec<String>> for Request {
-    type Error = ClientError;
-
-    fn try_from(params: Vec<String>) -> Result<Self, Self::Error> {
-        if params.is_empty() {
-            return Err(ClientError::UnknownCommand("".to_string()));
-        }
+                .map_or_else(|e| Response::error(&e), |v| Response::Integer(v as i64)),
 
-        match params[0].to_lowercase().as_str() {
-            PING => {
-                if params.len() > 2 {
-                    Err(ClientError::WrongNumberOfArguments(PING.to_string()))
-                } else {
-                    Ok(Request::Ping(params.get(1).cloned()))
+            Self::Hello(parser) => {
+                if let Some(protocol) = parser.protocol {
+                    client.protocol = protocol;
                 }
-            }
-
-            ECHO => {
-                if params.len() != 2 {
-                    Err(ClientError::WrongNumberOfArguments(ECHO.to_string()))
-                } else {
-                    Ok(Request::Echo(params[1].to_owned()))
+                if parser.name.is_some() {
+                    client.name = parser.name;
                 }
-            }
 
-            SET => {
-                if params.len() == 1 {
-                    Err(ClientError::WrongNumberOfArguments(SET.to_string()))
-                } else {
-                    Ok(SetParser::parse(&params[1..]).map(Request::Set))?
-                }
+                let field = |name: &'static str| Response::BulkString(Bytes::from(name));
+                Response::Map(vec![
+                    (field("server"), field("redis")),
+                    (field("version"), field(env!("CARGO_PKG_VERSION"))),
+                    (field("proto"), Response::Integer(client.protocol.version())),
+                    (field("id"), Response::Integer(client.id as i64)),
+                    (field("mode"), field("standalone")),
+                    (field("role"), field("master")),
+                    (field("modules"), Response::Array(vec![])),
+                ])
             }
 
-            GET => {
-                if params.len() != 2 {
-                    Err(ClientError::WrongNumberOfArguments(GET.to_string()))
-                } else {
-                    Ok(Request::Get(params[1].to_owned()))
-                }
-            }
+            Self::Command(parser) => parser.execute().unwrap_or_else(|e| Response::error(&e)),
 
-            EXISTS => {
-                if params.len() < 2 {
-                    Err(ClientError::WrongNumberOfArguments(EXISTS.to_string()))
-                } else {
-                    Ok(Request::Exists(params[1..].to_vec()))
-                }
-            }
+            Self::Expire(parser) => expire(SetExpiration::Expire, db, parser),
+            Self::PExpire(parser) => expire(SetExpiration::PExpire, db, parser),
+            Self::ExpireAt(parser) => expire(SetExpiration::ExpireAt, db, parser),
+            Self::PExpireAt(parser) => expire(SetExpiration::PExpireAt, db, parser),
 
-            DEL => {
-                if params.len() < 2 {
-                    Err(ClientError::WrongNumberOfArguments(DEL.to_string()))
-                } else {
-                    Ok(Request::Del(params[1..].to_vec()))
-                }
+            Self::Ttl(key) => Response::Integer(GetExpiration::Ttl.execute(db, key)),
+            Self::PTtl(key) => Response::Integer(GetExpiration::PTtl.execute(db, key)),
+            Self::ExpireTime(key) => Response::Integer(GetExpiration::ExpireTime.execute(db, key)),
+            Self::PExpireTime(key) => {
+                Response::Integer(GetExpiration::PExpireTime.execute(db, key))
             }
 
-            INCR => {
-                if params.len() != 2 {
-                    Err(ClientError::WrongNumberOfArguments(INCR.to_string()))
-                } else {
-                    Ok(Request::Incr(params[1].to_owned()))
-                }
-            }
+            Self::Persist(key) => Response::Integer(persist(db, key) as i64),
 
-            DECR => {
-                if params.len() != 2 {
-                    Err(ClientError::WrongNumberOfArguments(DECR.to_string()))
-                } else {
-                    Ok(Request::Decr(params[1].to_owned()))
-                }
+            Self::Info(sections) => {
+                Response::VerbatimString("txt".to_string(), info(&sections, dbs, stats, evictor))
             }
 
-            INCRBY => {
-                if params.len() == 1 {
-                    Err(ClientError::WrongNumberOfArguments(INCRBY.to_string()))
-                } else {
-                    Ok(IntegerParser::parse(&params[1..]).map(Request::IncrBy))?
+            Self::Select(index) => match dbs.checked_index(index) {
+                None => Response::error(&ClientError::DbIndexOutOfRange),
+                Some(index) => {
+                    client.db = index;
+                    Response::SimpleString("OK".to_string())
                 }
+            },
+
+            Self::Move(parser) => parser.execute(dbs, client.db).map_or_else(
+                |e| Response::error(&e),
+                |moved| Response::Integer(moved as i64),
+            ),
+
+            Self::SwapDb(parser) => parser.execute(dbs).map_or_else(
+                |e| Response::error(&e),
+                |_| Response::SimpleString("OK".to_string()),
+            ),
+
+            Self::FlushDb(mode) => {
+                mode.free(db.flush());
+                Response::SimpleString("OK".to_string())
             }
 
-            DECRBY => {
-                if params.len() == 1 {
-                    Err(ClientError::WrongNumberOfArguments(DECRBY.to_string()))
-                } else {
-                    Ok(IntegerParser::parse(&params[1..]).map(Request::DecrBy))?
-                }
+            Self::FlushAll(mode) => {
+                mode.free(dbs.flush());
+                Response::SimpleString("OK".to_string())
             }
 
-            LPUSH => {
-                if params.len() == 1 {
-                    Err(ClientError::WrongNumberOfArguments(LPUSH.to_string()))
-                } else {
-                    Ok(ListParser::parse(&params[1..]).map(Request::LPush)?)
-                }
+            Self::Keys(pattern) => Response::Array(
+                keys(db, &pattern)
+                    .into_iter()
+                    .map(Response::BulkString)
+                    .collect(),
+            ),
+
+            Self::Scan(parser) => {
+                let (cursor, keys) = parser.execute(db);
+                Response::Array(vec![
+                    Response::BulkString(Bytes::from(cursor.to_string())),
+                    Response::Array(keys.into_iter().map(Response::BulkString).collect()),
+                ])
             }
 
-            RPUSH => {
-                if params.len() == 1 {
-                    Err(ClientError::WrongNumberOfArguments(RPUSH.to_string()))
+            Self::Type(key) => Response::SimpleString(type_of(db, &key).to_string()),
+
+            Self::Rename(key, new_key) => rename(db, key, new_key, false).map_or_else(
+                |e| Response::error(&e),
+                |_| Response::SimpleString("OK".to_string()),
+            ),
+
+            Self::RenameNx(key, new_key) => rename(db, key, new_key, true).map_or_else(
+                |e| Response::error(&e),
+                |renamed| Response::Integer(renamed as i64),
+            ),
+
+            Self::Copy(parser) => parser.execute(dbs, client.db).map_or_else(
+                |e| Response::error(&e),
+                |copied| Response::Integer(copied as i64),
+            ),
+
+            Self::Touch(keys) => Response::Integer(touch(db, &keys) as i64),
+
+            Self::RandomKey => db.random_key().map_or(Response::Null, Response::BulkString),
+
+            Self::DbSize => Response::Integer(db.len() as i64),
+
+            Self::Unlink(keys) => Response::Integer(unlink(db, &keys) as i64),
+
+            Self::Object(parser) => parser.execute(db),
+
+            Self::Memory(parser) => parser.execute(dbs, client.db),
+        }
+    }
+}
+
+impl TryFrom<Vec<Bytes>> for Request {
+    type Error = ClientError;
+
+    fn try_from(mut params: Vec<Bytes>) -> Result<Self, Self::Error> {
+        if params.is_empty() {
+            return Err(ClientError::UnknownCommand(String::new(), String::new()));
+        }
+
+        let name = String::from_utf8_lossy(&params[0]).to_lowercase();
+        let Some(spec) = registry::lookup(&name) else {
+            return Err(ClientError::UnknownCommand(
+                truncate(&String::from_utf8_lossy(&params[0]), ECHO_MAX_LEN),
+                quote_args(&params[1..]),
+            ));
+        };
+        spec.check_arity(params.len())?;
+
+        // arguments are moved out of `params` rather than cloned
+        match spec.name {
+            PING => {
+                if params.len() > 2 {
+                    Err(ClientError::WrongNumberOfArguments(PING.to_string()))
                 } else {
-                    Ok(ListParser::parse(&params[1..]).map(Request::RPush)?)
+                    Ok(Request::Ping(params.into_iter().nth(1)))
                 }
             }
+            ECHO => Ok(Request::Echo(params.swap_remove(1))),
+            SET => Ok(SetParser::parse(&params[1..]).map(Request::Set)?),
+            MSET => Ok(MSetParser::parse(&params[1..]).map(Request::MSet)?),
+            GET => Ok(Request::Get(params.swap_remove(1))),
+            EXISTS => Ok(Request::Exists(args(params))),
+            DEL => Ok(Request::Del(args(params))),
+            INCR => Ok(Request::Incr(params.swap_remove(1))),
+            DECR => Ok(Request::Decr(params.swap_remove(1))),
+            INCRBY => Ok(IntegerParser::parse(&params[1..]).map(Request::IncrBy)?),
+            DECRBY => Ok(IntegerParser::parse(&params[1..]).map(Request::DecrBy)?),
+            LPUSH => Ok(ListParser::parse(&params[1..]).map(Request::LPush)?),
+            RPUSH => Ok(ListParser::parse(&params[1..]).map(Request::RPush)?),
+            HELLO => Ok(HelloParser::parse(&params[1..]).map(Request::Hello)?),
+            COMMAND => Ok(CommandParser::parse(&params[1..]).map(Request::Command)?),
+            EXPIRE => Ok(ExpireParser::parse(&params[1..]).map(Request::Expire)?),
+            PEXPIRE => Ok(ExpireParser::parse(&params[1..]).map(Request::PExpire)?),
+            EXPIREAT => Ok(ExpireParser::parse(&params[1..]).map(Request::ExpireAt)?),
+            PEXPIREAT => Ok(ExpireParser::parse(&params[1..]).map(Request::PExpireAt)?),
+            TTL => Ok(Request::Ttl(params.swap_remove(1))),
+            PTTL => Ok(Request::PTtl(params.swap_remove(1))),
+            EXPIRETIME => Ok(Request::ExpireTime(params.swap_remove(1))),
+            PEXPIRETIME => Ok(Request::PExpireTime(params.swap_remove(1))),
+            PERSIST => Ok(Request::Persist(params.swap_remove(1))),
+            GETSET => Ok(Request::Set(SetParser::parse_with(
+                &params[1..],
+                SetOptions {
+                    get: true,
+                    ..Default::default()
+                },
+            ))),
+            SETNX => Ok(Request::SetNx(SetParser::parse_with(
+                &params[1..],
+                SetOptions {
+                    condition: Some(SetCondition::Nx),
+                    ..Default::default()
+                },
+            ))),
+            SETEX => Ok(SetParser::parse_setex(&params[1..], b"ex", SETEX).map(Request::Set)?),
+            PSETEX => Ok(SetParser::parse_setex(&params[1..], b"px", PSETEX).map(Request::Set)?),
+            GETDEL => Ok(Request::GetDel(params.swap_remove(1))),
+            GETEX => Ok(GetExParser::parse(&params[1..]).map(Request::GetEx)?),
+            DELEX => Ok(DelExParser::parse(&params[1..]).map(Request::DelEx)?),
+            DIGEST => Ok(Request::Digest(params.swap_remove(1))),
+            INFO => Ok(Request::Info(args(params))),
+            SELECT => parse_number(&params[1])
+                .map(Request::Select)
+                .ok_or(ClientError::IntegerError),
+            MOVE => Ok(MoveParser::parse(&params[1..]).map(Request::Move)?),
+            SWAPDB => Ok(SwapDbParser::parse(&params[1..]).map(Request::SwapDb)?),
+            FLUSHDB => Ok(Flush::parse(&params[1..]).map(Request::FlushDb)?),
+            FLUSHALL => Ok(Flush::parse(&params[1..]).map(Request::FlushAll)?),
+            KEYS => Ok(Request::Keys(params.swap_remove(1))),
+            SCAN => Ok(ScanParser::parse(&params[1..]).map(Request::Scan)?),
+            TYPE => Ok(Request::Type(params.swap_remove(1))),
+            // the new name takes the place of the key once it's removed
+            RENAME => Ok(Request::Rename(
+                params.swap_remove(1),
+                params.swap_remove(1),
+            )),
+            RENAMENX => Ok(Request::RenameNx(
+                params.swap_remove(1),
+                params.swap_remove(1),
+            )),
+            COPY => Ok(CopyKey::parse(&params[1..]).map(Request::Copy)?),
+            TOUCH => Ok(Request::Touch(args(params))),
+            RANDOMKEY => Ok(Request::RandomKey),
+            DBSIZE => Ok(Request::DbSize),
+            UNLINK => Ok(Request::Unlink(args(params))),
+            OBJECT => Ok(ObjectParser::parse(&params[1..]).map(Request::Object)?),
+            MEMORY => Ok(MemoryParser::parse(&params[1..]).map(Request::Memory)?),
+            c => unreachable!("command '{c}' is registered but not handled"),
+        }
+    }
+}
+
+fn expire(cmd: SetExpiration, db: &Database, parser: ExpireParser) -> Response {
+    cmd.execute(db, parser)
+        .map_or_else(|e| Response::error(&e), |v| Response::Integer(v as i64))
+}
+
+/// Returns the keys of a request given as raw parameters, located as the registry describes.
+/// Unknown commands have none.
+pub fn keys_of(params: &[Bytes]) -> Vec<&Bytes> {
+    let Some(name) = params.first() else {
+        return vec![];
+    };
+    registry::lookup(&String::from_utf8_lossy(name).to_lowercase())
+        .map_or_else(Vec::new, |spec| spec.keys_of(params))
+}
+
+/// Maximum length of the command name and arguments echoed back for an unknown command.
+const ECHO_MAX_LEN: usize = 128;
 
-            c => Err(ClientError::UnknownCommand(c.to_string())),
+/// Quotes the first arguments of an unknown command, as Redis does when echoing them back.
+fn quote_args(args: &[Bytes]) -> String {
+    let mut quoted = String::new();
+    for arg in args {
+        if quoted.len() >= ECHO_MAX_LEN {
+            break;
         }
+        let arg = truncate(&String::from_utf8_lossy(arg), ECHO_MAX_LEN - quoted.len());
+        quoted.push_str(&format!("'{}' ", arg));
     }
+    quoted
+}
+
+fn truncate(s: &str, max_len: usize) -> String {
+    s.chars().take(max_len).collect()
+}
+
+/// Drops the command name from `params`, reusing its allocation for the arguments.
+fn args(mut params: Vec<Bytes>) -> Vec<Bytes> {
+    params.remove(0);
+    params
 }
 
 #[cfg(test)]
 mod tests {
-    use std::{
-        sync::Mutex,
-        time::{Duration, SystemTime},
+    use std::{collections::VecDeque, time::Duration};
+
+    use crate::{
+        clock,
+        cmd::parser::to_params,
+        db::{Object, Value},
+        resp::protocol::Protocol,
     };
 
-    use indexmap::IndexMap;
+    use super::*;
 
-    use crate::db::Value;
+    /// Executes `cmd` on `db` for a new client.
+    fn execute(db: &Databases, cmd: Request) -> Response {
+        execute_as(&mut Client::default(), db, cmd)
+    }
 
-    use super::*;
+    /// Executes `cmd` on `db` for `client`.
+    fn execute_as(client: &mut Client, db: &Databases, cmd: Request) -> Response {
+        cmd.execute(db, &Stats::default(), &Evictor::default(), client)
+    }
+
+    /// Parses `params` and executes the request on `db` for a new client.
+    fn run(db: &Databases, params: &[&'static str]) -> Response {
+        run_as(&mut Client::default(), db, params)
+    }
+
+    /// Parses `params` and executes the request on `db` for `client`, replying with the error if
+    /// they don't parse.
+    fn run_as(client: &mut Client, db: &Databases, params: &[&'static str]) -> Response {
+        Request::try_from(to_params(params))
+            .map_or_else(|e| Response::error(&e), |r| execute_as(client, db, r))
+    }
 
     #[test]
     fn decrby_one_arg() {
-        let params = vec![DECRBY.to_string()];
+        let params = vec![Bytes::from(DECRBY)];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -274,12 +559,12 @@ mod tests {
 
     #[test]
     fn decrby_ok() {
-        let params = vec![DECRBY.to_string(), "key".to_string(), 100.to_string()];
+        let params = vec![Bytes::from(DECRBY), Bytes::from("key"), Bytes::from("100")];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap(),
             Request::DecrBy(IntegerParser {
-                key: "key".to_string(),
+                key: Bytes::from("key"),
                 value: 100,
             })
         );
@@ -287,7 +572,7 @@ mod tests {
 
     #[test]
     fn incrby_one_arg() {
-        let params = vec![INCRBY.to_string()];
+        let params = vec![Bytes::from(INCRBY)];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -297,12 +582,12 @@ mod tests {
 
     #[test]
     fn incrby_ok() {
-        let params = vec![INCRBY.to_string(), "key".to_string(), 100.to_string()];
+        let params = vec![Bytes::from(INCRBY), Bytes::from("key"), Bytes::from("100")];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap(),
             Request::IncrBy(IntegerParser {
-                key: "key".to_string(),
+                key: Bytes::from("key"),
                 value: 100,
             })
         );
@@ -310,7 +595,7 @@ mod tests {
 
     #[test]
     fn decr_one_arg() {
-        let params = vec![DECR.to_string()];
+        let params = vec![Bytes::from(DECR)];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -320,7 +605,7 @@ mod tests {
 
     #[test]
     fn decr_multiple_args() {
-        let params = vec![DECR.to_string(), "key".to_string(), "key2".to_string()];
+        let params = vec![Bytes::from(DECR), Bytes::from("key"), Bytes::from("key2")];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -330,14 +615,14 @@ mod tests {
 
     #[test]
     fn decr_ok() {
-        let params = vec![DECR.to_string(), "key".to_string()];
+        let params = vec![Bytes::from(DECR), Bytes::from("key")];
         let cmd = Request::try_from(params);
-        assert_eq!(cmd.unwrap(), Request::Decr("key".to_string()));
+        assert_eq!(cmd.unwrap(), Request::Decr(Bytes::from("key")));
     }
 
     #[test]
     fn incr_one_arg() {
-        let params = vec![INCR.to_string()];
+        let params = vec![Bytes::from(INCR)];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -347,7 +632,7 @@ mod tests {
 
     #[test]
     fn incr_multiple_args() {
-        let params = vec![INCR.to_string(), "key".to_string(), "key2".to_string()];
+        let params = vec![Bytes::from(INCR), Bytes::from("key"), Bytes::from("key2")];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -357,14 +642,14 @@ mod tests {
 
     #[test]
     fn incr_ok() {
-        let params = vec![INCR.to_string(), "key".to_string()];
+        let params = vec![Bytes::from(INCR), Bytes::from("key")];
         let cmd = Request::try_from(params);
-        assert_eq!(cmd.unwrap(), Request::Incr("key".to_string()));
+        assert_eq!(cmd.unwrap(), Request::Incr(Bytes::from("key")));
     }
 
     #[test]
     fn del_one_arg() {
-        let params = vec![DEL.to_string()];
+        let params = vec![Bytes::from(DEL)];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -374,17 +659,17 @@ mod tests {
 
     #[test]
     fn del_ok() {
-        let params = vec![DEL.to_string(), "key".to_string(), "key2".to_string()];
+        let params = vec![Bytes::from(DEL), Bytes::from("key"), Bytes::from("key2")];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap(),
-            Request::Del(vec!["key".to_string(), "key2".to_string()])
+            Request::Del(vec![Bytes::from("key"), Bytes::from("key2")])
         );
     }
 
     #[test]
     fn exists_one_arg() {
-        let params = vec![EXISTS.to_string()];
+        let params = vec![Bytes::from(EXISTS)];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -394,17 +679,17 @@ mod tests {
 
     #[test]
     fn exists_ok() {
-        let params = vec![EXISTS.to_string(), "key".to_string(), "key2".to_string()];
+        let params = vec![Bytes::from(EXISTS), Bytes::from("key"), Bytes::from("key2")];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap(),
-            Request::Exists(vec!["key".to_string(), "key2".to_string()])
+            Request::Exists(vec![Bytes::from("key"), Bytes::from("key2")])
         );
     }
 
     #[test]
     fn set_one_arg() {
-        let params = vec![SET.to_string()];
+        let params = vec![Bytes::from(SET)];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -414,21 +699,22 @@ mod tests {
 
     #[test]
     fn set_ok() {
-        let params = vec![SET.to_string(), "key".to_string(), "".to_string()];
+        let params = vec![Bytes::from(SET), Bytes::from("key"), Bytes::from("")];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap(),
             Request::Set(SetParser {
-                key: "key".to_string(),
-                value: Value::String("".to_string()),
-                expiration: None
+                key: Bytes::from("key"),
+                value: Value::String(Bytes::from("")),
+                expiration: None,
+                options: SetOptions::default(),
             })
         );
     }
 
     #[test]
     fn get_no_args() {
-        let params = vec![GET.to_string()];
+        let params = vec![Bytes::from(GET)];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -438,28 +724,28 @@ mod tests {
 
     #[test]
     fn get_ok() {
-        let params = vec![GET.to_string(), "key".to_string()];
+        let params = vec![Bytes::from(GET), Bytes::from("key")];
         let cmd = Request::try_from(params);
-        assert_eq!(cmd.unwrap(), Request::Get("key".to_string()));
+        assert_eq!(cmd.unwrap(), Request::Get(Bytes::from("key")));
     }
 
     #[test]
     fn ping_no_args() {
-        let params = vec![PING.to_string()];
+        let params = vec![Bytes::from(PING)];
         let cmd = Request::try_from(params);
         assert_eq!(cmd.unwrap(), Request::Ping(None));
     }
 
     #[test]
     fn ping_with_arg() {
-        let params = vec![PING.to_string(), "hello".to_string()];
+        let params = vec![Bytes::from(PING), Bytes::from("hello")];
         let cmd = Request::try_from(params);
-        assert_eq!(cmd.unwrap(), Request::Ping(Some("hello".to_string())));
+        assert_eq!(cmd.unwrap(), Request::Ping(Some(Bytes::from("hello"))));
     }
 
     #[test]
     fn ping_too_many_args() {
-        let params = vec![PING.to_string(), "arg1".to_string(), "arg2".to_string()];
+        let params = vec![Bytes::from(PING), Bytes::from("arg1"), Bytes::from("arg2")];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -469,14 +755,14 @@ mod tests {
 
     #[test]
     fn echo_ok() {
-        let params = vec![ECHO.to_string(), "hello world".to_string()];
+        let params = vec![Bytes::from(ECHO), Bytes::from("hello world")];
         let cmd = Request::try_from(params);
-        assert_eq!(cmd.unwrap(), Request::Echo("hello world".to_string()));
+        assert_eq!(cmd.unwrap(), Request::Echo(Bytes::from("hello world")));
     }
 
     #[test]
     fn echo_no_args() {
-        let params = vec![ECHO.to_string()];
+        let params = vec![Bytes::from(ECHO)];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -486,7 +772,7 @@ mod tests {
 
     #[test]
     fn echo_too_many_args() {
-        let params = vec![ECHO.to_string(), "arg1".to_string(), "arg2".to_string()];
+        let params = vec![Bytes::from(ECHO), Bytes::from("arg1"), Bytes::from("arg2")];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -496,19 +782,44 @@ mod tests {
 
     #[test]
     fn unknown_command() {
-        let params = vec!["UNKNOWN".to_string()];
+        let params = vec![Bytes::from("UNKNOWN")];
+        let cmd = Request::try_from(params);
+        assert_eq!(
+            cmd.unwrap_err(),
+            ClientError::UnknownCommand("UNKNOWN".to_string(), String::new())
+        );
+    }
+
+    #[test]
+    fn unknown_command_echoes_args() {
+        let params = vec![Bytes::from("foo"), Bytes::from("a"), Bytes::from("b c")];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
-            ClientError::UnknownCommand("unknown".to_string())
+            ClientError::UnknownCommand("foo".to_string(), "'a' 'b c' ".to_string())
         );
     }
 
+    #[test]
+    fn unknown_command_echo_truncated() {
+        let long = "x".repeat(200);
+        let params = vec![
+            Bytes::from(long.clone()),
+            Bytes::from(long.clone()),
+            Bytes::from("never echoed"),
+        ];
+        let Err(ClientError::UnknownCommand(name, args)) = Request::try_from(params) else {
+            panic!("expected an unknown command");
+        };
+        assert_eq!(name.len(), ECHO_MAX_LEN);
+        assert_eq!(args, format!("'{}' ", &long[..ECHO_MAX_LEN]));
+    }
+
     #[test]
     fn case_insensitive_commands() {
-        let ping_lowercase = vec![PING.to_string()];
-        let ping_uppercase = vec!["PING".to_string()];
-        let ping_mixed_case = vec!["PiNg".to_string()];
+        let ping_lowercase = vec![Bytes::from(PING)];
+        let ping_uppercase = vec![Bytes::from("PING")];
+        let ping_mixed_case = vec![Bytes::from("PiNg")];
 
         assert!(Request::try_from(ping_lowercase).is_ok());
         assert!(Request::try_from(ping_uppercase).is_ok());
@@ -518,317 +829,440 @@ mod tests {
     #[test]
     fn execute_ping_no_arg() {
         let cmd = Request::Ping(None);
-        let reply = cmd.execute(&Db::new(Mutex::new(IndexMap::new())));
+        let reply = execute(&Databases::default(), cmd);
         assert_eq!(reply, Response::SimpleString("PONG".to_string()));
     }
 
     #[test]
     fn execute_ping_arg() {
-        let cmd = Request::Ping(Some("ciao".to_string()));
-        let reply = cmd.execute(&Db::new(Mutex::new(IndexMap::new())));
-        assert_eq!(reply, Response::BulkString("ciao".to_string()));
+        let cmd = Request::Ping(Some(Bytes::from("ciao")));
+        let reply = execute(&Databases::default(), cmd);
+        assert_eq!(reply, Response::BulkString(Bytes::from("ciao")));
     }
 
     #[test]
     fn execute_ping_with_arg() {
-        let cmd = Request::Ping(Some("hello".to_string()));
-        let reply = cmd.execute(&Db::new(Mutex::new(IndexMap::new())));
-        assert_eq!(reply, Response::BulkString("hello".to_string()));
+        let cmd = Request::Ping(Some(Bytes::from("hello")));
+        let reply = execute(&Databases::default(), cmd);
+        assert_eq!(reply, Response::BulkString(Bytes::from("hello")));
     }
 
     #[test]
     fn execute_echo() {
-        let cmd = Request::Echo("test message".to_string());
-        let reply = cmd.execute(&Db::new(Mutex::new(IndexMap::new())));
-        assert_eq!(reply, Response::BulkString("test message".to_string()));
+        let cmd = Request::Echo(Bytes::from("test message"));
+        let reply = execute(&Databases::default(), cmd);
+        assert_eq!(reply, Response::BulkString(Bytes::from("test message")));
     }
 
     #[test]
     fn execute_set_ok() {
         let set = SetParser {
-            key: "key".to_string(),
-            value: Value::String("".to_string()),
+            key: Bytes::from("key"),
+            value: Value::String(Bytes::from("")),
             expiration: None,
+            options: SetOptions::default(),
         };
         let cmd = Request::Set(set);
-        let reply = cmd.execute(&Db::new(Mutex::new(IndexMap::new())));
+        let reply = execute(&Databases::default(), cmd);
         assert_eq!(reply, Response::SimpleString("OK".to_string()));
     }
 
     #[test]
     fn execute_get_null() {
-        let cmd = Request::Get("key".to_string());
-        let reply = cmd.execute(&Db::new(Mutex::new(IndexMap::new())));
+        let cmd = Request::Get(Bytes::from("key"));
+        let reply = execute(&Databases::default(), cmd);
         assert_eq!(reply, Response::Null);
     }
 
     #[test]
     fn execute_get_no_expiration() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "key".to_string(),
-            Object::new(Value::String("value".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::String(Bytes::from("value")), None),
+        );
+        let cmd = Request::Get(Bytes::from("key"));
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::BulkString(Bytes::from("value")));
+    }
+
+    #[test]
+    fn execute_get_wrong_type() {
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::List(VecDeque::from([Bytes::from("a")])), None),
+        );
+        let cmd = Request::Get(Bytes::from("key"));
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::error(&ClientError::WrongType));
+    }
+
+    #[test]
+    fn execute_incr_wrong_type() {
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::List(VecDeque::from([Bytes::from("a")])), None),
+        );
+        let cmd = Request::Incr(Bytes::from("key"));
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::error(&ClientError::WrongType));
+    }
+
+    #[test]
+    fn expire_ok() {
+        let params = vec![Bytes::from("EXPIRE"), Bytes::from("key"), Bytes::from("10")];
+        let cmd = Request::try_from(params);
+        assert_eq!(
+            cmd.unwrap(),
+            Request::Expire(ExpireParser {
+                key: Bytes::from("key"),
+                when: 10,
+                condition: Default::default(),
+            })
+        );
+    }
+
+    #[test]
+    fn expire_one_arg() {
+        let params = vec![Bytes::from(EXPIRE), Bytes::from("key")];
+        let cmd = Request::try_from(params);
+        assert_eq!(
+            cmd.unwrap_err(),
+            ClientError::WrongNumberOfArguments(EXPIRE.to_string())
+        );
+    }
+
+    #[test]
+    fn execute_expire_ttl_persist() {
+        let db = Databases::default();
+        assert_eq!(run(&db, &["ttl", "key"]), Response::Integer(-2));
+        run(&db, &["set", "key", "value"]);
+        assert_eq!(run(&db, &["ttl", "key"]), Response::Integer(-1));
+        assert_eq!(
+            run(&db, &["expire", "key", "100", "nx"]),
+            Response::Integer(1)
+        );
+        assert_eq!(
+            run(&db, &["expire", "key", "100", "nx"]),
+            Response::Integer(0)
+        );
+        assert_eq!(run(&db, &["ttl", "key"]), Response::Integer(100));
+        assert_eq!(run(&db, &["persist", "key"]), Response::Integer(1));
+        assert_eq!(run(&db, &["pttl", "key"]), Response::Integer(-1));
+        assert_eq!(run(&db, &["pexpire", "key", "-1"]), Response::Integer(1));
+        assert_eq!(run(&db, &["get", "key"]), Response::Null);
+    }
+
+    #[test]
+    fn execute_expire_invalid_time() {
+        let db = Databases::default();
+        let cmd = Request::Expire(ExpireParser {
+            key: Bytes::from("key"),
+            when: i64::MAX,
+            condition: Default::default(),
+        });
+        let reply = execute(&db, cmd);
+        assert_eq!(
+            reply,
+            Response::error(&ClientError::InvalidExpireTime(EXPIRE.to_string()))
         );
-        let cmd = Request::Get("key".to_string());
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::BulkString("value".to_string()));
     }
 
     #[test]
     fn execute_get_expired() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "key".to_string(),
-            Object::new(Value::String("value".to_string()), Some(SystemTime::now())),
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::String(Bytes::from("value")), Some(clock::now())),
         );
-        let cmd = Request::Get("key".to_string());
-        let reply = cmd.execute(&db);
+        let cmd = Request::Get(Bytes::from("key"));
+        let reply = execute(&db, cmd);
         assert_eq!(reply, Response::Null);
     }
 
     #[test]
     fn execute_get_not_expired() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "key".to_string(),
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
             Object::new(
-                Value::String("value".to_string()),
-                Some(
-                    SystemTime::now()
-                        .checked_add(Duration::from_secs(10))
-                        .unwrap(),
-                ),
+                Value::String(Bytes::from("value")),
+                Some(clock::now().checked_add(Duration::from_secs(10)).unwrap()),
             ),
         );
-        let cmd = Request::Get("key".to_string());
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::BulkString("value".to_string()));
+        let cmd = Request::Get(Bytes::from("key"));
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::BulkString(Bytes::from("value")));
+    }
+
+    #[test]
+    fn execute_set_get_numbers_round_trip() {
+        let db = Databases::default();
+        for value in ["007", "+5", "-0", "42"] {
+            let params = vec![Bytes::from(SET), Bytes::from("key"), Bytes::from(value)];
+            execute(&db, Request::try_from(params).unwrap());
+
+            let reply = execute(&db, Request::Get(Bytes::from("key")));
+            assert_eq!(reply, Response::BulkString(Bytes::from(value)));
+        }
+    }
+
+    #[test]
+    fn execute_set_get_binary() {
+        let db = Databases::default();
+        let key = Bytes::from_static(b"\xFFkey\x00");
+        let value = Bytes::from_static(b"\x00\x9F\x92\r\n");
+        let cmd = Request::Set(SetParser {
+            key: key.clone(),
+            value: Value::String(value.clone()),
+            expiration: None,
+            options: SetOptions::default(),
+        });
+        execute(&db, cmd);
+
+        let reply = execute(&db, Request::Get(key));
+        assert_eq!(reply, Response::BulkString(value));
     }
 
     #[test]
     fn execute_exists_zero() {
-        let cmd = Request::Exists(vec!["key".to_string()]);
-        let reply = cmd.execute(&Db::new(Mutex::new(IndexMap::new())));
-        assert_eq!(reply, Response::Integer("0".to_string()));
+        let cmd = Request::Exists(vec![Bytes::from("key")]);
+        let reply = execute(&Databases::default(), cmd);
+        assert_eq!(reply, Response::Integer(0));
     }
 
     #[test]
     fn execute_exists_no_expiration() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "key".to_string(),
-            Object::new(Value::String("value".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::String(Bytes::from("value")), None),
         );
-        let cmd = Request::Exists(vec!["key".to_string()]);
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("1".to_string()));
+        let cmd = Request::Exists(vec![Bytes::from("key")]);
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(1));
     }
 
     #[test]
     fn execute_exists_same_key_twice() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "key".to_string(),
-            Object::new(Value::String("value".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::String(Bytes::from("value")), None),
         );
-        let cmd = Request::Exists(vec!["key".to_string(), "key".to_string()]);
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("2".to_string()));
+        let cmd = Request::Exists(vec![Bytes::from("key"), Bytes::from("key")]);
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(2));
     }
 
     #[test]
     fn execute_exists_not_expired() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "key".to_string(),
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
             Object::new(
-                Value::String("value".to_string()),
-                Some(
-                    SystemTime::now()
-                        .checked_add(Duration::from_secs(100))
-                        .unwrap(),
-                ),
+                Value::String(Bytes::from("value")),
+                Some(clock::now().checked_add(Duration::from_secs(100)).unwrap()),
             ),
         );
-        let cmd = Request::Exists(vec!["key".to_string()]);
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("1".to_string()));
+        let cmd = Request::Exists(vec![Bytes::from("key")]);
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(1));
     }
 
     #[test]
     fn execute_exists_expired() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "key".to_string(),
-            Object::new(Value::String("value".to_string()), Some(SystemTime::now())),
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::String(Bytes::from("value")), Some(clock::now())),
         );
-        let cmd = Request::Exists(vec!["key".to_string()]);
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("0".to_string()));
+        let cmd = Request::Exists(vec![Bytes::from("key")]);
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(0));
     }
 
     #[test]
     fn execute_exists_multiple_keys() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "key".to_string(),
-            Object::new(Value::String("value".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::String(Bytes::from("value")), None),
         );
-        db.lock().unwrap().insert(
-            "key2".to_string(),
-            Object::new(Value::String("".to_string()), None),
+        db[0].lock(b"key2").insert(
+            Bytes::from("key2"),
+            Object::new(Value::String(Bytes::from("")), None),
         );
-        let cmd = Request::Exists(vec!["key".to_string(), "key2".to_string()]);
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("2".to_string()));
+        let cmd = Request::Exists(vec![Bytes::from("key"), Bytes::from("key2")]);
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(2));
     }
 
     #[test]
     fn execute_exists_multiple_keys_one_expired() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "key".to_string(),
-            Object::new(Value::String("value".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::String(Bytes::from("value")), None),
         );
-        db.lock().unwrap().insert(
-            "key2".to_string(),
-            Object::new(Value::String("".to_string()), Some(SystemTime::now())),
+        db[0].lock(b"key2").insert(
+            Bytes::from("key2"),
+            Object::new(Value::String(Bytes::from("")), Some(clock::now())),
         );
-        let cmd = Request::Exists(vec!["key".to_string(), "key2".to_string()]);
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("1".to_string()));
+        let cmd = Request::Exists(vec![Bytes::from("key"), Bytes::from("key2")]);
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(1));
     }
 
     #[test]
     fn execute_del_zero() {
-        let cmd = Request::Del(vec!["key".to_string()]);
-        let reply = cmd.execute(&Db::new(Mutex::new(IndexMap::new())));
-        assert_eq!(reply, Response::Integer("0".to_string()));
+        let cmd = Request::Del(vec![Bytes::from("key")]);
+        let reply = execute(&Databases::default(), cmd);
+        assert_eq!(reply, Response::Integer(0));
     }
 
     #[test]
     fn execute_del_one() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "key".to_string(),
-            Object::new(Value::String("value".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::String(Bytes::from("value")), None),
         );
-        let cmd = Request::Del(vec!["key".to_string()]);
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("1".to_string()));
+        let cmd = Request::Del(vec![Bytes::from("key")]);
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(1));
     }
 
     #[test]
     fn execute_del_one_multiple_times() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "key".to_string(),
-            Object::new(Value::String("value".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::String(Bytes::from("value")), None),
         );
-        let cmd = Request::Del(vec!["key".to_string(), "key".to_string()]);
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("1".to_string()));
+        let cmd = Request::Del(vec![Bytes::from("key"), Bytes::from("key")]);
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(1));
     }
 
     #[test]
     fn execute_del_multiple() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "key".to_string(),
-            Object::new(Value::String("value".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"key").insert(
+            Bytes::from("key"),
+            Object::new(Value::String(Bytes::from("value")), None),
         );
-        db.lock().unwrap().insert(
-            "key2".to_string(),
-            Object::new(Value::String("".to_string()), Some(SystemTime::now())),
+        db[0].lock(b"key2").insert(
+            Bytes::from("key2"),
+            Object::new(
+                Value::String(Bytes::from("")),
+                Some(clock::now() - Duration::from_secs(1)),
+            ),
         );
-        let cmd = Request::Del(vec!["key".to_string(), "key2".to_string()]);
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("2".to_string()));
+        let cmd = Request::Del(vec![Bytes::from("key"), Bytes::from("key2")]);
+        let reply = execute(&db, cmd);
+        // the expired key doesn't count as deleted
+        assert_eq!(reply, Response::Integer(1));
+        assert!(db[0].is_empty());
     }
 
     #[test]
     fn execute_incr_ok() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        let cmd = Request::Incr("counter".to_string());
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("1".to_string()));
+        let db = Databases::default();
+        let cmd = Request::Incr(Bytes::from("counter"));
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(1));
     }
 
     #[test]
     fn execute_incr_err() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "counter".to_string(),
-            Object::new(Value::String("foo".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"counter").insert(
+            Bytes::from("counter"),
+            Object::new(Value::String(Bytes::from("foo")), None),
         );
-        let cmd = Request::Incr("counter".to_string());
-        let reply = cmd.execute(&db);
+        let cmd = Request::Incr(Bytes::from("counter"));
+        let reply = execute(&db, cmd);
         assert!(matches!(reply, Response::SimpleError(_)));
     }
 
     #[test]
     fn execute_decr_ok() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        let cmd = Request::Decr("counter".to_string());
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("-1".to_string()));
+        let db = Databases::default();
+        let cmd = Request::Decr(Bytes::from("counter"));
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(-1));
     }
 
     #[test]
     fn execute_decr_err() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "counter".to_string(),
-            Object::new(Value::String("foo".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"counter").insert(
+            Bytes::from("counter"),
+            Object::new(Value::String(Bytes::from("foo")), None),
         );
-        let cmd = Request::Decr("counter".to_string());
-        let reply = cmd.execute(&db);
+        let cmd = Request::Decr(Bytes::from("counter"));
+        let reply = execute(&db, cmd);
         assert!(matches!(reply, Response::SimpleError(_)));
     }
 
     #[test]
     fn execute_incrby_ok() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        let cmd = Request::IncrBy(IntegerParser { key: "counter".to_string(), value: 100 });
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("100".to_string()));
+        let db = Databases::default();
+        let cmd = Request::IncrBy(IntegerParser {
+            key: Bytes::from("counter"),
+            value: 100,
+        });
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(100));
     }
 
     #[test]
     fn execute_incrby_err() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "counter".to_string(),
-            Object::new(Value::String("foo".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"counter").insert(
+            Bytes::from("counter"),
+            Object::new(Value::String(Bytes::from("foo")), None),
         );
-        let cmd = Request::IncrBy(IntegerParser { key: "counter".to_string(), value: 100 });
-        let reply = cmd.execute(&db);
+        let cmd = Request::IncrBy(IntegerParser {
+            key: Bytes::from("counter"),
+            value: 100,
+        });
+        let reply = execute(&db, cmd);
         assert!(matches!(reply, Response::SimpleError(_)));
     }
 
     #[test]
     fn execute_decrby_ok() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        let cmd = Request::DecrBy(IntegerParser { key: "counter".to_string(), value: 100 });
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("-100".to_string()));
+        let db = Databases::default();
+        let cmd = Request::DecrBy(IntegerParser {
+            key: Bytes::from("counter"),
+            value: 100,
+        });
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(-100));
     }
 
     #[test]
     fn execute_decrby_err() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "counter".to_string(),
-            Object::new(Value::String("foo".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"counter").insert(
+            Bytes::from("counter"),
+            Object::new(Value::String(Bytes::from("foo")), None),
         );
-        let cmd = Request::DecrBy(IntegerParser { key: "counter".to_string(), value: 100 });
-        let reply = cmd.execute(&db);
+        let cmd = Request::DecrBy(IntegerParser {
+            key: Bytes::from("counter"),
+            value: 100,
+        });
+        let reply = execute(&db, cmd);
         assert!(matches!(reply, Response::SimpleError(_)));
     }
 
     #[test]
     fn lpush_no_args() {
-        let params = vec![LPUSH.to_string()];
+        let params = vec![Bytes::from(LPUSH)];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -838,7 +1272,7 @@ mod tests {
 
     #[test]
     fn lpush_only_key() {
-        let params = vec![LPUSH.to_string(), "k".to_string()];
+        let params = vec![Bytes::from(LPUSH), Bytes::from("k")];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -849,50 +1283,50 @@ mod tests {
     #[test]
     fn lpush_ok() {
         let params = vec![
-            LPUSH.to_string(),
-            "k".to_string(),
-            "a".to_string(),
-            "b".to_string(),
+            Bytes::from(LPUSH),
+            Bytes::from("k"),
+            Bytes::from("a"),
+            Bytes::from("b"),
         ];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap(),
             Request::LPush(ListParser {
-                key: "k".to_string(),
-                values: vec!["a".to_string(), "b".to_string()],
+                key: Bytes::from("k"),
+                values: vec![Bytes::from("a"), Bytes::from("b")],
             })
         );
     }
 
     #[test]
     fn execute_lpush_ok() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Databases::default();
         let cmd = Request::LPush(ListParser {
-            key: "k".to_string(),
-            values: vec!["a".to_string(), "b".to_string()],
+            key: Bytes::from("k"),
+            values: vec![Bytes::from("a"), Bytes::from("b")],
         });
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("2".to_string()));
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(2));
     }
 
     #[test]
     fn execute_lpush_err() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "k".to_string(),
-            Object::new(Value::String("foo".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"k").insert(
+            Bytes::from("k"),
+            Object::new(Value::String(Bytes::from("foo")), None),
         );
         let cmd = Request::LPush(ListParser {
-            key: "k".to_string(),
-            values: vec!["v".to_string()],
+            key: Bytes::from("k"),
+            values: vec![Bytes::from("v")],
         });
-        let reply = cmd.execute(&db);
+        let reply = execute(&db, cmd);
         assert!(matches!(reply, Response::SimpleError(_)));
     }
 
     #[test]
     fn rpush_no_args() {
-        let params = vec![RPUSH.to_string()];
+        let params = vec![Bytes::from(RPUSH)];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap_err(),
@@ -903,44 +1337,155 @@ mod tests {
     #[test]
     fn rpush_ok() {
         let params = vec![
-            RPUSH.to_string(),
-            "k".to_string(),
-            "a".to_string(),
-            "b".to_string(),
+            Bytes::from(RPUSH),
+            Bytes::from("k"),
+            Bytes::from("a"),
+            Bytes::from("b"),
         ];
         let cmd = Request::try_from(params);
         assert_eq!(
             cmd.unwrap(),
             Request::RPush(ListParser {
-                key: "k".to_string(),
-                values: vec!["a".to_string(), "b".to_string()],
+                key: Bytes::from("k"),
+                values: vec![Bytes::from("a"), Bytes::from("b")],
             })
         );
     }
 
     #[test]
     fn execute_rpush_ok() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
+        let db = Databases::default();
         let cmd = Request::RPush(ListParser {
-            key: "k".to_string(),
-            values: vec!["a".to_string(), "b".to_string()],
+            key: Bytes::from("k"),
+            values: vec![Bytes::from("a"), Bytes::from("b")],
         });
-        let reply = cmd.execute(&db);
-        assert_eq!(reply, Response::Integer("2".to_string()));
+        let reply = execute(&db, cmd);
+        assert_eq!(reply, Response::Integer(2));
     }
 
     #[test]
     fn execute_rpush_err() {
-        let db = Db::new(Mutex::new(IndexMap::new()));
-        db.lock().unwrap().insert(
-            "k".to_string(),
-            Object::new(Value::String("foo".to_string()), None),
+        let db = Databases::default();
+        db[0].lock(b"k").insert(
+            Bytes::from("k"),
+            Object::new(Value::String(Bytes::from("foo")), None),
         );
         let cmd = Request::RPush(ListParser {
-            key: "k".to_string(),
-            values: vec!["v".to_string()],
+            key: Bytes::from("k"),
+            values: vec![Bytes::from("v")],
         });
-        let reply = cmd.execute(&db);
+        let reply = execute(&db, cmd);
         assert!(matches!(reply, Response::SimpleError(_)));
     }
+
+    #[test]
+    fn hello_ok() {
+        let params = vec![Bytes::from(HELLO), Bytes::from("3")];
+        let cmd = Request::try_from(params);
+        assert_eq!(
+            cmd.unwrap(),
+            Request::Hello(HelloParser {
+                protocol: Some(Protocol::Resp3),
+                name: None,
+            })
+        );
+    }
+
+    #[test]
+    fn hello_err() {
+        let params = vec![Bytes::from(HELLO), Bytes::from("1")];
+        let cmd = Request::try_from(params);
+        assert_eq!(cmd.unwrap_err(), ClientError::NoProto);
+    }
+
+    #[test]
+    fn execute_hello_switches_protocol() {
+        let db = Databases::default();
+        let mut client = Client::new(7);
+        let cmd = Request::Hello(HelloParser {
+            protocol: Some(Protocol::Resp3),
+            name: Some("conn".to_string()),
+        });
+        let reply = execute_as(&mut client, &db, cmd);
+
+        assert_eq!(client.protocol, Protocol::Resp3);
+        assert_eq!(client.name, Some("conn".to_string()));
+        let Response::Map(fields) = reply else {
+            panic!("expected map");
+        };
+        assert!(fields.contains(&(
+            Response::BulkString(Bytes::from("proto")),
+            Response::Integer(3)
+        )));
+        assert!(fields.contains(&(
+            Response::BulkString(Bytes::from("id")),
+            Response::Integer(7)
+        )));
+    }
+
+    #[test]
+    fn execute_hello_no_args_keeps_protocol() {
+        let db = Databases::default();
+        let mut client = Client::default();
+        let reply = execute_as(&mut client, &db, Request::Hello(HelloParser::default()));
+
+        assert_eq!(client.protocol, Protocol::Resp2);
+        let Response::Map(fields) = reply else {
+            panic!("expected map");
+        };
+        assert!(fields.contains(&(
+            Response::BulkString(Bytes::from("proto")),
+            Response::Integer(2)
+        )));
+    }
+
+    #[test]
+    fn execute_select_move_flush() {
+        let db = Databases::default();
+        let mut client = Client::default();
+        let ok = Response::SimpleString("OK".to_string());
+
+        run_as(&mut client, &db, &["set", "key", "0"]);
+        assert_eq!(run_as(&mut client, &db, &["select", "1"]), ok);
+        assert_eq!(run_as(&mut client, &db, &["get", "key"]), Response::Null);
+        run_as(&mut client, &db, &["set", "key", "1"]);
+        assert_eq!(
+            run_as(&mut client, &db, &["move", "key", "0"]),
+            Response::Integer(0)
+        );
+        assert_eq!(
+            run_as(&mut client, &db, &["move", "key", "2"]),
+            Response::Integer(1)
+        );
+        assert_eq!(run_as(&mut client, &db, &["flushdb", "async"]), ok);
+        assert_eq!(run_as(&mut client, &db, &["select", "2"]), ok);
+        assert_eq!(
+            run_as(&mut client, &db, &["get", "key"]),
+            Response::BulkString(Bytes::from("1"))
+        );
+
+        assert_eq!(run_as(&mut client, &db, &["swapdb", "0", "2"]), ok);
+        assert_eq!(
+            run_as(&mut client, &db, &["get", "key"]),
+            Response::BulkString(Bytes::from("0"))
+        );
+        assert_eq!(run_as(&mut client, &db, &["flushall"]), ok);
+        assert_eq!(run_as(&mut client, &db, &["get", "key"]), Response::Null);
+        assert_eq!(run_as(&mut client, &db, &["select", "0"]), ok);
+        assert_eq!(run_as(&mut client, &db, &["get", "key"]), Response::Null);
+    }
+
+    #[test]
+    fn execute_select_invalid_index() {
+        let db = Databases::default();
+        let mut client = Client::default();
+        let reply = execute_as(&mut client, &db, Request::Select(16));
+        assert_eq!(reply, Response::error(&ClientError::DbIndexOutOfRange));
+        assert_eq!(client.db, 0);
+
+        assert_eq!(
+            Request::try_from(vec![Bytes::from("select"), Bytes::from("one")]),
+            Err(ClientError::IntegerError)
+        );
+    }
 }
@@ -1,110 +1,417 @@
-use crate::resp::types::{BULK_STRING, CR, ERROR, INTEGER, LF, NULL, SIMPLE_STRING};
+use bytes::Bytes;
 
+use crate::resp::{
+    error::ErrorReply,
+    protocol::Protocol,
+    types::{
+        ARRAY, BIG_NUMBER, BOOLEAN, BULK_STRING, CR, DOUBLE, ERROR, INTEGER, LF, MAP, NULL, PUSH,
+        SET, SIMPLE_STRING, VERBATIM_STRING,
+    },
+};
+
+// the variants allowed to be dead code are RESP3 types no command returns yet
 #[derive(Debug, PartialEq)]
 pub enum Response {
     Null,
     SimpleString(String),
-    BulkString(String),
-    Integer(String),
+    BulkString(Bytes),
+    Integer(i64),
     SimpleError(String),
+    Array(Vec<Response>),
+    #[allow(dead_code)]
+    NullArray,
+    Map(Vec<(Response, Response)>),
+    Set(Vec<Response>),
+    Double(f64),
+    #[allow(dead_code)]
+    Boolean(bool),
+    #[allow(dead_code)]
+    BigNumber(String),
+    /// Format (e.g. `txt` or `mkd`) and content of the string.
+    VerbatimString(String, String),
+    #[allow(dead_code)]
+    Push(Vec<Response>),
 }
 
 impl Response {
-    pub fn serialize(&self) -> Vec<u8> {
+    /// Builds the error reply of `e`, prefixed with its error code.
+    pub fn error(e: &impl ErrorReply) -> Self {
+        Response::SimpleError(e.reply())
+    }
+
+    /// Serializes the response with the encoding of `protocol`.
+    ///
+    /// RESP3 types are downgraded when serializing for RESP2 clients, the same way Redis does:
+    /// maps become flat arrays of key-value pairs, sets and pushes become arrays, booleans become
+    /// integers and the remaining types become bulk strings.
+    pub fn serialize(&self, protocol: Protocol) -> Vec<u8> {
         let mut bytes = vec![];
-        match self {
-            Response::Null => {
+        self.serialize_into(protocol, &mut bytes);
+        bytes
+    }
+
+    fn serialize_into(&self, protocol: Protocol, bytes: &mut Vec<u8>) {
+        match (self, protocol) {
+            (Response::Null, Protocol::Resp2) => {
+                bytes.push(BULK_STRING);
+                bytes.extend_from_slice(b"-1");
+                push_crlf(bytes);
+            }
+            (Response::Null, Protocol::Resp3) => {
                 bytes.push(NULL);
-                bytes.push(CR);
-                bytes.push(LF);
+                push_crlf(bytes);
             }
-            Response::SimpleString(s) => {
+            (Response::SimpleString(s), _) => {
                 bytes.push(SIMPLE_STRING);
                 bytes.extend_from_slice(s.as_bytes());
-                bytes.push(CR);
-                bytes.push(LF);
+                push_crlf(bytes);
             }
-            Response::Integer(s) => {
+            (Response::Integer(i), _) => {
                 bytes.push(INTEGER);
-                bytes.extend_from_slice(s.as_bytes());
-                bytes.push(CR);
-                bytes.push(LF);
+                bytes.extend_from_slice(i.to_string().as_bytes());
+                push_crlf(bytes);
             }
-            Response::SimpleError(s) => {
+            (Response::SimpleError(s), _) => {
                 bytes.push(ERROR);
                 bytes.extend_from_slice(s.as_bytes());
-                bytes.push(CR);
-                bytes.push(LF);
+                push_crlf(bytes);
             }
-            Response::BulkString(s) => {
-                bytes.push(BULK_STRING);
-                bytes.extend_from_slice(s.len().to_string().as_bytes());
-                bytes.push(CR);
-                bytes.push(LF);
+            (Response::BulkString(s), _) => push_bulk_string(bytes, s),
+            (Response::Array(elements), _) => {
+                push_header(bytes, ARRAY, elements.len());
+                for e in elements {
+                    e.serialize_into(protocol, bytes);
+                }
+            }
+            (Response::NullArray, Protocol::Resp2) => {
+                bytes.push(ARRAY);
+                bytes.extend_from_slice(b"-1");
+                push_crlf(bytes);
+            }
+            (Response::NullArray, Protocol::Resp3) => {
+                bytes.push(NULL);
+                push_crlf(bytes);
+            }
+            (Response::Map(entries), Protocol::Resp2) => {
+                push_header(bytes, ARRAY, entries.len() * 2);
+                for (k, v) in entries {
+                    k.serialize_into(protocol, bytes);
+                    v.serialize_into(protocol, bytes);
+                }
+            }
+            (Response::Map(entries), Protocol::Resp3) => {
+                push_header(bytes, MAP, entries.len());
+                for (k, v) in entries {
+                    k.serialize_into(protocol, bytes);
+                    v.serialize_into(protocol, bytes);
+                }
+            }
+            (Response::Set(elements), Protocol::Resp2)
+            | (Response::Push(elements), Protocol::Resp2) => {
+                push_header(bytes, ARRAY, elements.len());
+                for e in elements {
+                    e.serialize_into(protocol, bytes);
+                }
+            }
+            (Response::Set(elements), Protocol::Resp3) => {
+                push_header(bytes, SET, elements.len());
+                for e in elements {
+                    e.serialize_into(protocol, bytes);
+                }
+            }
+            (Response::Push(elements), Protocol::Resp3) => {
+                push_header(bytes, PUSH, elements.len());
+                for e in elements {
+                    e.serialize_into(protocol, bytes);
+                }
+            }
+            (Response::Double(d), Protocol::Resp2) => {
+                push_bulk_string(bytes, format_double(*d).as_bytes())
+            }
+            (Response::Double(d), Protocol::Resp3) => {
+                bytes.push(DOUBLE);
+                bytes.extend_from_slice(format_double(*d).as_bytes());
+                push_crlf(bytes);
+            }
+            (Response::Boolean(b), Protocol::Resp2) => {
+                bytes.push(INTEGER);
+                bytes.push(if *b { b'1' } else { b'0' });
+                push_crlf(bytes);
+            }
+            (Response::Boolean(b), Protocol::Resp3) => {
+                bytes.push(BOOLEAN);
+                bytes.push(if *b { b't' } else { b'f' });
+                push_crlf(bytes);
+            }
+            (Response::BigNumber(n), Protocol::Resp2) => push_bulk_string(bytes, n.as_bytes()),
+            (Response::BigNumber(n), Protocol::Resp3) => {
+                bytes.push(BIG_NUMBER);
+                bytes.extend_from_slice(n.as_bytes());
+                push_crlf(bytes);
+            }
+            (Response::VerbatimString(_, s), Protocol::Resp2) => {
+                push_bulk_string(bytes, s.as_bytes())
+            }
+            (Response::VerbatimString(format, s), Protocol::Resp3) => {
+                // the format takes exactly three bytes, followed by a colon
+                bytes.push(VERBATIM_STRING);
+                bytes.extend_from_slice((s.len() + 4).to_string().as_bytes());
+                push_crlf(bytes);
+                bytes.extend_from_slice(format.as_bytes());
+                bytes.push(b':');
                 bytes.extend_from_slice(s.as_bytes());
-                bytes.push(CR);
-                bytes.push(LF);
+                push_crlf(bytes);
             }
         }
-        bytes
+    }
+}
+
+fn push_crlf(bytes: &mut Vec<u8>) {
+    bytes.push(CR);
+    bytes.push(LF);
+}
+
+fn push_header(bytes: &mut Vec<u8>, kind: u8, len: usize) {
+    bytes.push(kind);
+    bytes.extend_from_slice(len.to_string().as_bytes());
+    push_crlf(bytes);
+}
+
+fn push_bulk_string(bytes: &mut Vec<u8>, s: &[u8]) {
+    push_header(bytes, BULK_STRING, s.len());
+    bytes.extend_from_slice(s);
+    push_crlf(bytes);
+}
+
+fn format_double(d: f64) -> String {
+    if d.is_nan() {
+        "nan".to_string()
+    } else if d.is_infinite() {
+        if d > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else {
+        d.to_string()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Response;
+    use super::*;
 
     #[test]
     fn serialize_null() {
         let reply = Response::Null;
-        assert_eq!(reply.serialize(), b"_\r\n");
+        assert_eq!(reply.serialize(Protocol::Resp3), b"_\r\n");
+        assert_eq!(reply.serialize(Protocol::Resp2), b"$-1\r\n");
     }
 
     #[test]
     fn serialize_simple_string() {
         let reply = Response::SimpleString("".to_string());
-        assert_eq!(reply.serialize(), b"+\r\n");
+        assert_eq!(reply.serialize(Protocol::Resp3), b"+\r\n");
 
         let reply = Response::SimpleString("UPPERCASE".to_string());
-        assert_eq!(reply.serialize(), b"+UPPERCASE\r\n");
+        assert_eq!(reply.serialize(Protocol::Resp3), b"+UPPERCASE\r\n");
 
         let reply = Response::SimpleString("Hello World".to_string());
-        assert_eq!(reply.serialize(), b"+Hello World\r\n");
+        assert_eq!(reply.serialize(Protocol::Resp3), b"+Hello World\r\n");
 
         let reply = Response::SimpleString("こんにちは".to_string());
-        assert_eq!(reply.serialize(), "+こんにちは\r\n".as_bytes());
+        assert_eq!(
+            reply.serialize(Protocol::Resp3),
+            "+こんにちは\r\n".as_bytes()
+        );
     }
 
     #[test]
     fn serialize_integer() {
-        let reply = Response::Integer("0".to_string());
-        assert_eq!(reply.serialize(), b":0\r\n");
+        let reply = Response::Integer(0);
+        assert_eq!(reply.serialize(Protocol::Resp3), b":0\r\n");
 
-        let reply = Response::Integer("42".to_string());
-        assert_eq!(reply.serialize(), b":42\r\n");
+        let reply = Response::Integer(42);
+        assert_eq!(reply.serialize(Protocol::Resp3), b":42\r\n");
 
-        let reply = Response::Integer("-1".to_string());
-        assert_eq!(reply.serialize(), b":-1\r\n");
+        let reply = Response::Integer(-1);
+        assert_eq!(reply.serialize(Protocol::Resp3), b":-1\r\n");
     }
 
     #[test]
     fn serialize_simple_error() {
         let reply = Response::SimpleError("Error".to_string());
-        assert_eq!(reply.serialize(), b"-Error\r\n");
+        assert_eq!(reply.serialize(Protocol::Resp3), b"-Error\r\n");
 
         let reply = Response::SimpleError("ERR unknown command".to_string());
-        assert_eq!(reply.serialize(), b"-ERR unknown command\r\n");
+        assert_eq!(
+            reply.serialize(Protocol::Resp3),
+            b"-ERR unknown command\r\n"
+        );
     }
 
     #[test]
     fn serialize_bulk_string() {
-        let reply = Response::BulkString("".to_string());
-        assert_eq!(reply.serialize(), b"$0\r\n\r\n");
+        let reply = Response::BulkString(Bytes::from(""));
+        assert_eq!(reply.serialize(Protocol::Resp3), b"$0\r\n\r\n");
+
+        let reply = Response::BulkString(Bytes::from("hello world"));
+        assert_eq!(reply.serialize(Protocol::Resp3), b"$11\r\nhello world\r\n");
+
+        let reply = Response::BulkString(Bytes::from("💸"));
+        assert_eq!(
+            reply.serialize(Protocol::Resp3),
+            b"$4\r\n\xF0\x9F\x92\xB8\r\n"
+        );
+
+        let reply = Response::BulkString(Bytes::from_static(b"\x00\xFF\r\n"));
+        assert_eq!(reply.serialize(Protocol::Resp3), b"$4\r\n\x00\xFF\r\n\r\n");
+    }
+
+    #[test]
+    fn serialize_map() {
+        let reply = Response::Map(vec![
+            (
+                Response::BulkString(Bytes::from("proto")),
+                Response::Integer(3),
+            ),
+            (
+                Response::BulkString(Bytes::from("modules")),
+                Response::Set(vec![]),
+            ),
+        ]);
+        assert_eq!(
+            reply.serialize(Protocol::Resp3),
+            b"%2\r\n$5\r\nproto\r\n:3\r\n$7\r\nmodules\r\n~0\r\n"
+        );
+        assert_eq!(
+            reply.serialize(Protocol::Resp2),
+            b"*4\r\n$5\r\nproto\r\n:3\r\n$7\r\nmodules\r\n*0\r\n"
+        );
+    }
+
+    #[test]
+    fn serialize_array() {
+        let reply = Response::Array(vec![]);
+        assert_eq!(reply.serialize(Protocol::Resp2), b"*0\r\n");
+
+        let reply = Response::Array(vec![
+            Response::BulkString(Bytes::from("hello")),
+            Response::Integer(1),
+            Response::Null,
+        ]);
+        assert_eq!(
+            reply.serialize(Protocol::Resp2),
+            b"*3\r\n$5\r\nhello\r\n:1\r\n$-1\r\n"
+        );
+        assert_eq!(
+            reply.serialize(Protocol::Resp3),
+            b"*3\r\n$5\r\nhello\r\n:1\r\n_\r\n"
+        );
+    }
 
-        let reply = Response::BulkString("hello world".to_string());
-        assert_eq!(reply.serialize(), b"$11\r\nhello world\r\n");
+    #[test]
+    fn serialize_null_array() {
+        let reply = Response::NullArray;
+        assert_eq!(reply.serialize(Protocol::Resp2), b"*-1\r\n");
+        assert_eq!(reply.serialize(Protocol::Resp3), b"_\r\n");
+    }
 
-        let reply = Response::BulkString("💸".to_string());
-        assert_eq!(reply.serialize(), b"$4\r\n\xF0\x9F\x92\xB8\r\n");
+    #[test]
+    fn serialize_nested_aggregates() {
+        let reply = Response::Array(vec![
+            Response::Array(vec![Response::Integer(1), Response::Integer(2)]),
+            Response::Map(vec![(
+                Response::SimpleString("k".to_string()),
+                Response::Set(vec![Response::Boolean(true)]),
+            )]),
+            Response::NullArray,
+        ]);
+        assert_eq!(
+            reply.serialize(Protocol::Resp3),
+            b"*3\r\n*2\r\n:1\r\n:2\r\n%1\r\n+k\r\n~1\r\n#t\r\n_\r\n"
+        );
+        assert_eq!(
+            reply.serialize(Protocol::Resp2),
+            b"*3\r\n*2\r\n:1\r\n:2\r\n*2\r\n+k\r\n*1\r\n:1\r\n*-1\r\n"
+        );
+    }
+
+    #[test]
+    fn serialize_set() {
+        let reply = Response::Set(vec![Response::BulkString(Bytes::from("a")), Response::Null]);
+        assert_eq!(reply.serialize(Protocol::Resp3), b"~2\r\n$1\r\na\r\n_\r\n");
+        assert_eq!(
+            reply.serialize(Protocol::Resp2),
+            b"*2\r\n$1\r\na\r\n$-1\r\n"
+        );
+    }
+
+    #[test]
+    fn serialize_double() {
+        let reply = Response::Double(1.5);
+        assert_eq!(reply.serialize(Protocol::Resp3), b",1.5\r\n");
+        assert_eq!(reply.serialize(Protocol::Resp2), b"$3\r\n1.5\r\n");
+
+        let reply = Response::Double(f64::NEG_INFINITY);
+        assert_eq!(reply.serialize(Protocol::Resp3), b",-inf\r\n");
+
+        let reply = Response::Double(f64::NAN);
+        assert_eq!(reply.serialize(Protocol::Resp3), b",nan\r\n");
+    }
+
+    #[test]
+    fn serialize_boolean() {
+        assert_eq!(
+            Response::Boolean(true).serialize(Protocol::Resp3),
+            b"#t\r\n"
+        );
+        assert_eq!(
+            Response::Boolean(false).serialize(Protocol::Resp3),
+            b"#f\r\n"
+        );
+        assert_eq!(
+            Response::Boolean(true).serialize(Protocol::Resp2),
+            b":1\r\n"
+        );
+        assert_eq!(
+            Response::Boolean(false).serialize(Protocol::Resp2),
+            b":0\r\n"
+        );
+    }
+
+    #[test]
+    fn serialize_big_number() {
+        let reply = Response::BigNumber("3492890328409238509324850943850943825024385".to_string());
+        assert_eq!(
+            reply.serialize(Protocol::Resp3),
+            b"(3492890328409238509324850943850943825024385\r\n"
+        );
+        assert_eq!(
+            reply.serialize(Protocol::Resp2),
+            b"$43\r\n3492890328409238509324850943850943825024385\r\n"
+        );
+    }
+
+    #[test]
+    fn serialize_verbatim_string() {
+        let reply = Response::VerbatimString("txt".to_string(), "Some string".to_string());
+        assert_eq!(
+            reply.serialize(Protocol::Resp3),
+            b"=15\r\ntxt:Some string\r\n"
+        );
+        assert_eq!(reply.serialize(Protocol::Resp2), b"$11\r\nSome string\r\n");
+    }
+
+    #[test]
+    fn serialize_push() {
+        let reply = Response::Push(vec![
+            Response::BulkString(Bytes::from("message")),
+            Response::Integer(1),
+        ]);
+        assert_eq!(
+            reply.serialize(Protocol::Resp3),
+            b">2\r\n$7\r\nmessage\r\n:1\r\n"
+        );
+        assert_eq!(
+            reply.serialize(Protocol::Resp2),
+            b"*2\r\n$7\r\nmessage\r\n:1\r\n"
+        );
     }
 }
@@ -10,3 +10,50 @@ pub const INCRBY: &str = "incrby";
 pub const DECRBY: &str = "decrby";
 pub const LPUSH: &str = "lpush";
 pub const RPUSH: &str = "rpush";
+pub const HELLO: &str = "hello";
+pub const COMMAND: &str = "command";
+pub const COUNT: &str = "count";
+pub const INFO: &str = "info";
+pub const DOCS: &str = "docs";
+pub const GETKEYS: &str = "getkeys";
+pub const EXPIRE: &str = "expire";
+pub const PEXPIRE: &str = "pexpire";
+pub const EXPIREAT: &str = "expireat";
+pub const PEXPIREAT: &str = "pexpireat";
+pub const TTL: &str = "ttl";
+pub const PTTL: &str = "pttl";
+pub const EXPIRETIME: &str = "expiretime";
+pub const PEXPIRETIME: &str = "pexpiretime";
+pub const PERSIST: &str = "persist";
+pub const GETSET: &str = "getset";
+pub const GETDEL: &str = "getdel";
+pub const GETEX: &str = "getex";
+pub const SETNX: &str = "setnx";
+pub const SETEX: &str = "setex";
+pub const PSETEX: &str = "psetex";
+pub const DELEX: &str = "delex";
+pub const DIGEST: &str = "digest";
+pub const MSET: &str = "mset";
+pub const SELECT: &str = "select";
+pub const MOVE: &str = "move";
+pub const SWAPDB: &str = "swapdb";
+pub const FLUSHDB: &str = "flushdb";
+pub const FLUSHALL: &str = "flushall";
+pub const KEYS: &str = "keys";
+pub const SCAN: &str = "scan";
+pub const TYPE: &str = "type";
+pub const RENAME: &str = "rename";
+pub const RENAMENX: &str = "renamenx";
+pub const COPY: &str = "copy";
+pub const TOUCH: &str = "touch";
+pub const RANDOMKEY: &str = "randomkey";
+pub const DBSIZE: &str = "dbsize";
+pub const UNLINK: &str = "unlink";
+pub const OBJECT: &str = "object";
+pub const ENCODING: &str = "encoding";
+pub const FREQ: &str = "freq";
+pub const IDLETIME: &str = "idletime";
+pub const REFCOUNT: &str = "refcount";
+pub const MEMORY: &str = "memory";
+pub const USAGE: &str = "usage";
+pub const STATS: &str = "stats";
@@ -0,0 +1,371 @@
+use std::ops::RangeInclusive;
+
+use thiserror::Error;
+
+use crate::{
+    db::{DEFAULT_DATABASES, DEFAULT_SHARDS, Settings},
+    evict::Policy,
+};
+
+/// Server settings, given on the command line as `--<name> <value>` pairs, e.g.
+/// `cc-redis --proto-max-bulk-len 64mb`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Port to listen on, on the loopback interface.
+    pub port: u16,
+    /// Maximum size of a single bulk string in a request.
+    pub proto_max_bulk_len: usize,
+    /// Maximum number of elements of a request array.
+    pub proto_max_multibulk_len: usize,
+    /// Maximum size of the data buffered for a client while waiting for a complete request.
+    pub client_query_buffer_limit: usize,
+    /// Whether deadlines are indexed, so that expired keys are removed exactly rather than by
+    /// sampling.
+    pub expiry_index: bool,
+    /// Frequency of the active expire cycle, in runs per second.
+    pub hz: u32,
+    /// Effort of the active expirer, from 1 to 10: higher values sample more keys and give the
+    /// cycle more time, reclaiming memory faster at the expense of latency.
+    pub active_expire_effort: u32,
+    /// Number of databases clients can select.
+    pub databases: usize,
+    /// Number of independently locked shards each database is split into.
+    pub shards: usize,
+    /// Whether each core runs its own single-threaded runtime owning a shard of the keyspace,
+    /// instead of all the threads sharing every shard.
+    ///
+    /// The mode is experimental: it has only been measured on a single core, where it's slower
+    /// than the shared mode, and has yet to show a gain on several.
+    pub thread_per_core: bool,
+    /// Whether the values of expired keys are freed in the background.
+    pub lazyfree_lazy_expire: bool,
+    /// Whether the values of evicted keys are freed in the background.
+    pub lazyfree_lazy_eviction: bool,
+    /// Memory from which keys are evicted, or commands refused, `0` for no limit.
+    pub maxmemory: usize,
+    /// Keys evicted once memory is over `maxmemory`.
+    pub maxmemory_policy: Policy,
+    /// Number of keys sampled at a time to pick the ones to evict: more samples approximate
+    /// LRU, LFU and TTL orders better, at the expense of CPU.
+    pub maxmemory_samples: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            port: 6379,
+            proto_max_bulk_len: 512 * 1024 * 1024,
+            proto_max_multibulk_len: 1024 * 1024,
+            client_query_buffer_limit: 1024 * 1024 * 1024,
+            expiry_index: false,
+            hz: 10,
+            active_expire_effort: 1,
+            databases: DEFAULT_DATABASES,
+            shards: DEFAULT_SHARDS,
+            thread_per_core: false,
+            lazyfree_lazy_expire: false,
+            lazyfree_lazy_eviction: false,
+            maxmemory: 0,
+            maxmemory_policy: Policy::NoEviction,
+            maxmemory_samples: 5,
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum ConfigError {
+    #[error("unknown option '{0}'")]
+    UnknownOption(String),
+    #[error("missing value for option '{0}'")]
+    MissingValue(String),
+    #[error("invalid value '{1}' for option '{0}'")]
+    InvalidValue(String, String),
+    #[error("option '{0}' can't be combined with '{1}'")]
+    Conflicting(String, String),
+}
+
+impl Config {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, ConfigError> {
+        let mut config = Config::default();
+        let mut args = args.into_iter();
+        let mut shards = false;
+
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                return Err(ConfigError::UnknownOption(arg));
+            };
+            let name = name.to_lowercase();
+            let value = args
+                .next()
+                .ok_or_else(|| ConfigError::MissingValue(name.clone()))?;
+            let invalid = || ConfigError::InvalidValue(name.clone(), value.clone());
+
+            match name.as_str() {
+                "port" => {
+                    config.port = value.parse().ok().filter(|p| *p > 0).ok_or_else(invalid)?
+                }
+                "proto-max-bulk-len" => {
+                    config.proto_max_bulk_len = parse_memory(&value).ok_or_else(invalid)?
+                }
+                "proto-max-multibulk-len" => {
+                    config.proto_max_multibulk_len =
+                        value.parse().ok().filter(|v| *v > 0).ok_or_else(invalid)?
+                }
+                "client-query-buffer-limit" => {
+                    config.client_query_buffer_limit = parse_memory(&value).ok_or_else(invalid)?
+                }
+                "expiry-index" => config.expiry_index = parse_bool(&value).ok_or_else(invalid)?,
+                "hz" => config.hz = parse_in_range(&value, 1..=500).ok_or_else(invalid)?,
+                "active-expire-effort" => {
+                    config.active_expire_effort =
+                        parse_in_range(&value, 1..=10).ok_or_else(invalid)?
+                }
+                "databases" => {
+                    config.databases =
+                        parse_in_range(&value, 1..=1024).ok_or_else(invalid)? as usize
+                }
+                "shards" => {
+                    config.shards = parse_in_range(&value, 1..=1024).ok_or_else(invalid)? as usize;
+                    shards = true;
+                }
+                "thread-per-core" => {
+                    config.thread_per_core = parse_bool(&value).ok_or_else(invalid)?
+                }
+                "lazyfree-lazy-expire" => {
+                    config.lazyfree_lazy_expire = parse_bool(&value).ok_or_else(invalid)?
+                }
+                "lazyfree-lazy-eviction" => {
+                    config.lazyfree_lazy_eviction = parse_bool(&value).ok_or_else(invalid)?
+                }
+                "maxmemory" => {
+                    config.maxmemory = match value.as_str() {
+                        "0" => 0,
+                        v => parse_memory(v).ok_or_else(invalid)?,
+                    }
+                }
+                "maxmemory-policy" => {
+                    config.maxmemory_policy = Policy::parse(&value).ok_or_else(invalid)?
+                }
+                "maxmemory-samples" => {
+                    config.maxmemory_samples =
+                        parse_in_range(&value, 1..=64).ok_or_else(invalid)? as usize
+                }
+                _ => return Err(ConfigError::UnknownOption(name)),
+            }
+        }
+
+        // thread-per-core runs one shard per core, so a shard count given with it would be ignored
+        if shards && config.thread_per_core {
+            return Err(ConfigError::Conflicting(
+                "shards".to_string(),
+                "thread-per-core".to_string(),
+            ));
+        }
+
+        Ok(config)
+    }
+
+    /// Settings of the keyspaces of the databases.
+    pub fn keyspace_settings(&self) -> Settings {
+        Settings {
+            expiry_index: self.expiry_index,
+            lazy_expire: self.lazyfree_lazy_expire,
+        }
+    }
+}
+
+/// Parses a positive amount of memory, with an optional unit as in the Redis configuration
+/// file: `k`, `m` and `g` are powers of 1000, `kb`, `mb` and `gb` are powers of 1024.
+fn parse_memory(value: &str) -> Option<usize> {
+    let value = value.to_lowercase();
+    let digits_end = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(digits_end);
+
+    let multiplier: usize = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    amount
+        .parse::<usize>()
+        .ok()
+        .and_then(|a| a.checked_mul(multiplier))
+        .filter(|v| *v > 0)
+}
+
+/// Parses an integer, provided it's within `range`.
+fn parse_in_range(value: &str, range: RangeInclusive<u32>) -> Option<u32> {
+    value.parse().ok().filter(|v| range.contains(v))
+}
+
+/// Parses a boolean written `yes` or `no`, as in the Redis configuration file.
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn from_args_empty() {
+        assert_eq!(Config::from_args(vec![]).unwrap(), Config::default());
+    }
+
+    #[test]
+    fn from_args_ok() {
+        let args = to_args(&[
+            "--proto-max-bulk-len",
+            "1mb",
+            "--PROTO-MAX-MULTIBULK-LEN",
+            "10",
+            "--client-query-buffer-limit",
+            "2048",
+            "--expiry-index",
+            "yes",
+            "--hz",
+            "100",
+            "--active-expire-effort",
+            "10",
+            "--shards",
+            "1",
+            "--port",
+            "7000",
+            "--databases",
+            "2",
+            "--lazyfree-lazy-expire",
+            "yes",
+            "--lazyfree-lazy-eviction",
+            "yes",
+            "--maxmemory",
+            "100mb",
+            "--maxmemory-policy",
+            "allkeys-lfu",
+            "--maxmemory-samples",
+            "10",
+        ]);
+        assert_eq!(
+            Config::from_args(args).unwrap(),
+            Config {
+                proto_max_bulk_len: 1024 * 1024,
+                proto_max_multibulk_len: 10,
+                client_query_buffer_limit: 2048,
+                expiry_index: true,
+                hz: 100,
+                active_expire_effort: 10,
+                shards: 1,
+                port: 7000,
+                thread_per_core: false,
+                databases: 2,
+                lazyfree_lazy_expire: true,
+                lazyfree_lazy_eviction: true,
+                maxmemory: 100 * 1024 * 1024,
+                maxmemory_policy: Policy::AllKeysLfu,
+                maxmemory_samples: 10,
+            }
+        );
+    }
+
+    #[test]
+    fn from_args_unknown_option() {
+        let args = to_args(&["--foo", "1"]);
+        assert_eq!(
+            Config::from_args(args).unwrap_err(),
+            ConfigError::UnknownOption("foo".to_string())
+        );
+
+        let args = to_args(&["proto-max-bulk-len", "1"]);
+        assert_eq!(
+            Config::from_args(args).unwrap_err(),
+            ConfigError::UnknownOption("proto-max-bulk-len".to_string())
+        );
+    }
+
+    #[test]
+    fn from_args_missing_value() {
+        let args = to_args(&["--proto-max-bulk-len"]);
+        assert_eq!(
+            Config::from_args(args).unwrap_err(),
+            ConfigError::MissingValue("proto-max-bulk-len".to_string())
+        );
+    }
+
+    #[test]
+    fn from_args_invalid_value() {
+        let args = to_args(&["--proto-max-multibulk-len", "0"]);
+        assert_eq!(
+            Config::from_args(args).unwrap_err(),
+            ConfigError::InvalidValue("proto-max-multibulk-len".to_string(), "0".to_string())
+        );
+
+        for (name, value) in [
+            ("--hz", "0"),
+            ("--hz", "501"),
+            ("--active-expire-effort", "11"),
+            ("--shards", "0"),
+            ("--databases", "0"),
+            ("--port", "0"),
+            ("--port", "65536"),
+            ("--maxmemory", "-1"),
+            ("--maxmemory-policy", "lru"),
+            ("--maxmemory-samples", "0"),
+        ] {
+            assert_eq!(
+                Config::from_args(to_args(&[name, value])).unwrap_err(),
+                ConfigError::InvalidValue(name[2..].to_string(), value.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn from_args_conflicting() {
+        let args = to_args(&["--thread-per-core", "yes", "--shards", "4"]);
+        assert_eq!(
+            Config::from_args(args).unwrap_err(),
+            ConfigError::Conflicting("shards".to_string(), "thread-per-core".to_string())
+        );
+
+        let args = to_args(&["--thread-per-core", "yes"]);
+        assert!(Config::from_args(args).unwrap().thread_per_core);
+
+        let args = to_args(&["--thread-per-core", "no", "--shards", "4"]);
+        assert_eq!(Config::from_args(args).unwrap().shards, 4);
+    }
+
+    #[test]
+    fn parse_memory_units() {
+        assert_eq!(parse_memory("100"), Some(100));
+        assert_eq!(parse_memory("1k"), Some(1000));
+        assert_eq!(parse_memory("1KB"), Some(1024));
+        assert_eq!(parse_memory("2m"), Some(2_000_000));
+        assert_eq!(parse_memory("2mb"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_memory("1g"), Some(1_000_000_000));
+        assert_eq!(parse_memory("1gb"), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_memory("0"), None);
+        assert_eq!(parse_memory("mb"), None);
+        assert_eq!(parse_memory("1tb"), None);
+        assert_eq!(parse_memory("-1"), None);
+    }
+
+    #[test]
+    fn parse_bool_values() {
+        assert_eq!(parse_bool("yes"), Some(true));
+        assert_eq!(parse_bool("NO"), Some(false));
+        assert_eq!(parse_bool("1"), None);
+    }
+}
@@ -1,40 +1,220 @@
 use std::{
-    collections::VecDeque, fmt::Display, sync::{Arc, Mutex}, time::SystemTime
+    collections::{BTreeSet, VecDeque},
+    mem,
+    ops::Index,
+    str,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, SystemTime},
 };
 
+use bytes::Bytes;
 use indexmap::IndexMap;
 use log::trace;
-use rand::{rng, seq::index::sample};
+use rand::{Rng, rng, seq::index::sample};
+use xxhash_rust::xxh3::xxh3_64;
 
-#[derive(Debug, PartialEq)]
+use crate::{clock, lazyfree, scan::scan};
+
+/// Size from which bytes read from a connection are stored without being copied.
+///
+/// Arguments are slices of the connection's read buffer and keep all of it alive. That's fine
+/// for big values, which take most of the buffer anyway, but a small value would retain memory
+/// many times its size for as long as it's stored. Redis makes the same trade-off for arguments
+/// of 32KB or more.
+const SHARED_MIN_LEN: usize = 32 * 1024;
+
+/// Prepares bytes read from a connection to be stored, copying them out of the read buffer
+/// unless they're big enough to be worth sharing it.
+pub fn detach(bytes: Bytes) -> Bytes {
+    if bytes.len() < SHARED_MIN_LEN {
+        Bytes::copy_from_slice(&bytes)
+    } else {
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Integer(i64),
-    String(String),
-    List(VecDeque<String>),
+    String(Bytes),
+    List(VecDeque<Bytes>),
+}
+
+/// Type of a value as seen by clients, which commands declare to look keys up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// Strings, integers included.
+    String,
+    List,
+}
+
+impl Kind {
+    /// Name of the type, as clients know it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::String => "string",
+            Kind::List => "list",
+        }
+    }
 }
 
-impl Display for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// Error of a lookup finding a value of another kind than the one the command accepts.
+#[derive(Debug, PartialEq)]
+pub struct WrongType;
+
+impl Value {
+    /// Builds a string value, encoded as an integer when the bytes are the canonical
+    /// representation of one. Encoding is invisible to clients: `007` or `+5` are kept as they
+    /// are, since they wouldn't read back the same from an integer.
+    pub fn from_bytes(bytes: Bytes) -> Self {
+        match str::from_utf8(&bytes)
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+        {
+            Some(i) if i.to_string().as_bytes() == bytes => Value::Integer(i),
+            _ => Value::String(bytes),
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        match self {
+            Value::Integer(_) | Value::String(_) => Kind::String,
+            Value::List(_) => Kind::List,
+        }
+    }
+
+    /// Returns the bytes of a string value, `None` for other kinds.
+    pub fn to_bytes(&self) -> Option<Bytes> {
         match self {
-            Value::Integer(i) => write!(f, "{}", i),
-            Value::String(s) => write!(f, "{}", s),
-            Value::List(l) => write!(f, "{:?}", l),
+            Value::Integer(i) => Some(Bytes::from(i.to_string())),
+            Value::String(s) => Some(s.clone()),
+            Value::List(_) => None,
         }
     }
+
+    /// Number of allocations freed when dropping the value, which is what makes dropping it
+    /// slow: its elements for a list, one for a string.
+    pub fn free_effort(&self) -> usize {
+        match self {
+            Value::Integer(_) | Value::String(_) => 1,
+            Value::List(l) => l.len(),
+        }
+    }
+
+    /// Memory allocated for the value besides the `Value` itself: the bytes of a string, the
+    /// buffer of a list and the bytes of its elements.
+    pub fn memory_usage(&self) -> usize {
+        match self {
+            Value::Integer(_) => 0,
+            Value::String(s) => s.len(),
+            Value::List(l) => {
+                l.capacity() * size_of::<Bytes>() + l.iter().map(Bytes::len).sum::<usize>()
+            }
+        }
+    }
+
+    /// Detaches the bytes held by the value (see [`detach`]).
+    pub fn detach(self) -> Self {
+        match self {
+            Value::String(s) => Value::String(detach(s)),
+            Value::List(l) => Value::List(l.into_iter().map(detach).collect()),
+            i => i,
+        }
+    }
+}
+
+/// Digest of a string value as returned by `DIGEST`: its XXH3 hash in hexadecimal, which lets
+/// clients compare big values without sending them.
+pub fn digest(bytes: &[u8]) -> String {
+    format!("{:016x}", xxh3_64(bytes))
 }
 
+/// Access counter of new objects, high enough that they aren't evicted before they get a chance
+/// to be accessed again, as Redis' `LFU_INIT_VAL`.
+const LFU_INIT_VAL: u8 = 5;
+/// How much harder it gets to increment the access counter as it grows, as Redis'
+/// `lfu-log-factor`: with 10, it takes about a million accesses to saturate it.
+const LFU_LOG_FACTOR: f64 = 10.0;
+/// Minutes without access it takes for the access counter to decrease by one, as Redis'
+/// `lfu-decay-time`.
+const LFU_DECAY_MINUTES: u32 = 1;
+
+/// Current time in seconds, which is what access times are measured in. It follows the clock of
+/// the expirations, so that tests can move it.
+fn lru_clock() -> u32 {
+    (clock::to_unix_ms(clock::now()) / 1000) as u32
+}
+
+#[derive(Clone)]
 pub struct Object {
     pub value: Value,
-    pub expiration: Option<SystemTime>,
+    expiration: Option<SystemTime>,
+    /// Time of the last access, see [`lru_clock`].
+    accessed: u32,
+    /// Logarithmic counter of the accesses, decreasing with the time since the last one.
+    frequency: u8,
+    /// Memory allocated for the value, see [`Value::memory_usage`]. It's measured once, then
+    /// kept up to date by [`Keyspace::grow`], so that removing a big list doesn't walk it.
+    size: usize,
 }
 
 impl Object {
     pub fn new(value: Value, expiration: Option<SystemTime>) -> Self {
-        Self { value, expiration }
+        Self {
+            size: value.memory_usage(),
+            value,
+            expiration,
+            accessed: lru_clock(),
+            frequency: LFU_INIT_VAL,
+        }
+    }
+
+    /// Memory used by the object, its value included.
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Object>() + self.size
+    }
+
+    /// Records an access, which the LRU and LFU eviction policies go by.
+    ///
+    /// The access counter is incremented with a probability decreasing as it grows, as Redis'
+    /// `LFULogIncr`, so that 8 bits can tell apart keys accessed hundreds and millions of times.
+    pub fn touch(&mut self) {
+        let now = lru_clock();
+        let mut frequency = self.frequency_at(now);
+        if frequency < u8::MAX {
+            let base = frequency.saturating_sub(LFU_INIT_VAL) as f64;
+            if base == 0.0 || rng().random::<f64>() < 1.0 / (base * LFU_LOG_FACTOR + 1.0) {
+                frequency += 1;
+            }
+        }
+        self.frequency = frequency;
+        self.accessed = now;
+    }
+
+    /// Time since the last access.
+    pub fn idle_time(&self) -> Duration {
+        Duration::from_secs(lru_clock().saturating_sub(self.accessed) as u64)
+    }
+
+    /// Access counter, decreased by the time since the last access.
+    pub fn frequency(&self) -> u8 {
+        self.frequency_at(lru_clock())
+    }
+
+    fn frequency_at(&self, now: u32) -> u8 {
+        let periods = now.saturating_sub(self.accessed) / (60 * LFU_DECAY_MINUTES);
+        self.frequency
+            .saturating_sub(u8::try_from(periods).unwrap_or(u8::MAX))
+    }
+
+    /// Deadline of the object. It can only be changed through [`Keyspace::set_expiration`],
+    /// which keeps the expiry index in sync.
+    pub fn expiration(&self) -> Option<SystemTime> {
+        self.expiration
     }
 
     pub fn is_expired(&self) -> bool {
-        let now = SystemTime::now();
+        let now = clock::now();
 
         match self.expiration {
             None => false,
@@ -43,106 +223,831 @@ impl Object {
     }
 }
 
-pub type Db = Arc<Mutex<IndexMap<String, Object>>>;
+/// Memory used by an entry of a keyspace besides its key and object: the handle of the key, and
+/// the hash and index the `IndexMap` keeps for it.
+const ENTRY_OVERHEAD: usize = size_of::<Bytes>() + 2 * size_of::<usize>();
+/// Memory used by a deadline in the expiry index.
+const EXPIRY_OVERHEAD: usize = size_of::<(SystemTime, Bytes)>();
+
+/// Settings of the keyspaces, the same for every shard of every database.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Settings {
+    /// Whether deadlines are indexed, see [`Keyspace`].
+    pub expiry_index: bool,
+    /// Whether expired keys are freed by the lazyfree thread rather than where they're found.
+    pub lazy_expire: bool,
+}
+
+/// Keys of a database and, optionally, an index of their deadlines.
+///
+/// The index orders `(deadline, key)` pairs so that the active expirer can pop exactly the keys
+/// that are due instead of sampling. Deadlines are still stored in the objects, which gives the
+/// key to deadline association needed to update the index, and keeps TTL lookups O(1).
+#[derive(Default)]
+pub struct Keyspace {
+    entries: IndexMap<Bytes, Object>,
+    expires: Option<BTreeSet<(SystemTime, Bytes)>>,
+    /// Keys removed for having expired, whether on access or by the active expirer.
+    expired: u64,
+    /// Number of keys with a deadline.
+    volatile: usize,
+    /// Memory used by the entries, see [`Keyspace::memory_usage`].
+    memory: usize,
+    lazy_expire: bool,
+}
 
-pub fn remove_expired_entries(db: &Db, sample_size: usize) -> f64 {
-    let mut map = db.lock().unwrap();
-    if map.is_empty() {
-        return 0.0;
+/// Keys looked at by a pass of the active expirer, and how many of them had expired.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub sampled: usize,
+    pub expired: usize,
+    /// Whether the pass proves that no other key has expired, which the expiry index does when
+    /// it finds a deadline still to come.
+    pub complete: bool,
+}
+
+impl Sample {
+    /// Ratio of expired keys in the sample, `0` when it's empty.
+    pub fn ratio(&self) -> f64 {
+        if self.sampled == 0 {
+            return 0.0;
+        }
+        self.expired as f64 / self.sampled as f64
     }
+}
 
-    let mut rng = rng();
-    let sample_size = sample_size.min(map.len());
+impl Keyspace {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            expires: settings.expiry_index.then(BTreeSet::new),
+            lazy_expire: settings.lazy_expire,
+            ..Default::default()
+        }
+    }
 
-    let indexes = sample(&mut rng, map.len(), sample_size);
-    let mut keys: Vec<String> = vec![];
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn expired_keys(&self) -> u64 {
+        self.expired
+    }
+
+    /// Memory used by the keys and their values.
+    pub fn used_memory(&self) -> usize {
+        self.memory
+    }
+
+    /// Returns the memory used by `key`, expired or not: its bytes, its object and value, and
+    /// its share of the structures of the keyspace.
+    pub fn memory_usage(&self, key: &[u8]) -> Option<usize> {
+        let (key, object) = self.entries.get_key_value(key)?;
+        Some(self.usage(key, object))
+    }
+
+    fn usage(&self, key: &[u8], object: &Object) -> usize {
+        let indexed = self.expires.is_some() && object.expiration.is_some();
+        ENTRY_OVERHEAD + key.len() + object.memory_usage() + indexed as usize * EXPIRY_OVERHEAD
+    }
 
-    for i in indexes {
-        if let Some((k, o)) = map.get_index(i) {
-            if o.is_expired() {
-                keys.push(k.clone());
+    /// Accounts for `bytes` more memory used by the value of `key`, which commands growing a
+    /// value in place must call.
+    pub fn grow(&mut self, key: &[u8], bytes: usize) {
+        if let Some(object) = self.entries.get_mut(key) {
+            object.size += bytes;
+            self.memory += bytes;
+        }
+    }
+
+    /// Returns the object of `key`, expired or not: commands should use [`Keyspace::live`].
+    pub fn get(&self, key: &[u8]) -> Option<&Object> {
+        self.entries.get(key)
+    }
+
+    pub fn insert(&mut self, key: Bytes, object: Object) -> Option<Object> {
+        self.memory += self.usage(&key, &object);
+        if let Some(deadline) = object.expiration {
+            self.volatile += 1;
+            if let Some(expires) = &mut self.expires {
+                expires.insert((deadline, key.clone()));
             }
         }
+        let previous = self.entries.insert(key.clone(), object);
+        self.unindex(&key, previous.as_ref());
+        previous
     }
 
-    if !keys.is_empty() {
+    pub fn swap_remove(&mut self, key: &[u8]) -> Option<Object> {
+        let (_, key, removed) = self.entries.swap_remove_full(key)?;
+        self.unindex(&key, Some(&removed));
+        Some(removed)
+    }
+
+    /// Sets or clears the deadline of `key`, returning whether the key exists.
+    pub fn set_expiration(&mut self, key: &[u8], expiration: Option<SystemTime>) -> bool {
+        let Some((_, k, o)) = self.entries.get_full_mut(key) else {
+            return false;
+        };
+        let previous = std::mem::replace(&mut o.expiration, expiration);
+        self.volatile = self.volatile + expiration.is_some() as usize - previous.is_some() as usize;
+        if let Some(expires) = &mut self.expires {
+            self.memory = self.memory + expiration.is_some() as usize * EXPIRY_OVERHEAD
+                - previous.is_some() as usize * EXPIRY_OVERHEAD;
+            if let Some(deadline) = previous {
+                expires.remove(&(deadline, k.clone()));
+            }
+            if let Some(deadline) = expiration {
+                expires.insert((deadline, k.clone()));
+            }
+        }
+        true
+    }
+
+    /// Removes the index entry and the memory of an object that no longer is the one stored for
+    /// `key`.
+    fn unindex(&mut self, key: &Bytes, object: Option<&Object>) {
+        if let Some(o) = object {
+            self.memory -= self.usage(key, o);
+        }
+        if object.is_some_and(|o| o.expiration.is_some()) {
+            self.volatile -= 1;
+        }
+        if let Some(expires) = &mut self.expires
+            && let Some(deadline) = object.and_then(|o| o.expiration)
+        {
+            // The entry of a replacing object with the same deadline must stay.
+            if self.entries.get(key).and_then(|o| o.expiration) != Some(deadline) {
+                expires.remove(&(deadline, key.clone()));
+            }
+        }
+    }
+
+    /// Visits up to `count` keys from `cursor`, expired ones included, and returns the cursor to
+    /// continue from (see [`scan`]).
+    pub fn scan(&self, cursor: usize, count: usize, visit: impl FnMut(&Bytes, &Object)) -> usize {
+        scan(&self.entries, cursor, count, visit)
+    }
+
+    /// Takes all the keys out, leaving the keyspace empty with the same settings and statistics.
+    pub fn take(&mut self) -> Keyspace {
+        Keyspace {
+            entries: mem::take(&mut self.entries),
+            expires: self.expires.as_mut().map(mem::take),
+            volatile: mem::take(&mut self.volatile),
+            memory: mem::take(&mut self.memory),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the object of `key`, removing it first if it has expired, and records the access.
+    pub fn live(&mut self, key: &[u8]) -> Option<&mut Object> {
+        let object = self.peek(key)?;
+        object.touch();
+        Some(object)
+    }
+
+    /// Returns the object of `key` like [`Keyspace::live`], without recording an access: for
+    /// commands that look at keys without using their values, like `EXISTS` or `TTL`.
+    pub fn peek(&mut self, key: &[u8]) -> Option<&mut Object> {
+        if self.get(key).is_some_and(Object::is_expired) {
+            self.remove_expired_key(key);
+            return None;
+        }
+        self.entries.get_mut(key)
+    }
+
+    /// Removes `key` if it's live, returning its object. An expired key is reclaimed as such
+    /// instead, so that commands don't count it as removed.
+    pub fn remove_live(&mut self, key: &[u8]) -> Option<Object> {
+        self.peek(key)?;
+        self.swap_remove(key)
+    }
+
+    /// Returns the object of `key` like [`Keyspace::live`], provided its value is of `kind`.
+    pub fn lookup(&mut self, key: &[u8], kind: Kind) -> Result<Option<&mut Object>, WrongType> {
+        match self.live(key) {
+            Some(o) if o.value.kind() != kind => Err(WrongType),
+            o => Ok(o),
+        }
+    }
+
+    /// Looks at up to `limit` keys and removes those that have expired.
+    ///
+    /// With the expiry index, the keys looked at are the due deadlines, up to `limit`, and the
+    /// first deadline to come if there's one before the limit, which completes the pass. Without,
+    /// they're a random sample of `limit` keys.
+    pub fn remove_expired(&mut self, limit: usize) -> Sample {
+        if self.is_empty() || limit == 0 {
+            return Sample::default();
+        }
+
+        let (keys, sampled, complete) = match &self.expires {
+            Some(expires) => {
+                let now = clock::now();
+                let keys: Vec<_> = expires
+                    .iter()
+                    .take(limit)
+                    .take_while(|(deadline, _)| *deadline < now)
+                    .map(|(_, k)| k.clone())
+                    .collect();
+                let sampled = (keys.len() + 1).min(limit).min(expires.len());
+                let complete = keys.len() < limit;
+                (keys, sampled, complete)
+            }
+            None => (self.sample_expired(limit), limit.min(self.len()), false),
+        };
+
         for k in keys.iter() {
-            map.swap_remove(k);
+            self.remove_expired_key(k);
+        }
+        if !keys.is_empty() {
+            trace!("removed {} expired entries", keys.len());
+        }
+
+        Sample {
+            sampled,
+            expired: keys.len(),
+            complete,
+        }
+    }
+
+    /// Returns a random key, removing the expired keys picked until a live one is.
+    pub fn random_key(&mut self) -> Option<Bytes> {
+        while !self.is_empty() {
+            let (key, object) = self.entries.get_index(rng().random_range(0..self.len()))?;
+            if !object.is_expired() {
+                return Some(key.clone());
+            }
+            let key = key.clone();
+            self.remove_expired_key(&key);
+        }
+        None
+    }
+
+    /// Removes a key that has expired, handing its value to the lazyfree thread with
+    /// `lazyfree-lazy-expire`.
+    fn remove_expired_key(&mut self, key: &[u8]) {
+        let Some(object) = self.swap_remove(key) else {
+            return;
+        };
+        self.expired += 1;
+        if self.lazy_expire {
+            lazyfree::free(object);
+        }
+    }
+
+    /// Visits up to `count` random keys, expired ones included, for eviction to pick from. With
+    /// `volatile`, only keys with a deadline are visited.
+    ///
+    /// Keys with a deadline are looked for from a random index on, as Redis' `dictGetSomeKeys`
+    /// looks at consecutive buckets, which stays cheap when few keys have one.
+    pub fn sample(&self, count: usize, volatile: bool, mut visit: impl FnMut(&Bytes, &Object)) {
+        if self.is_empty() || (volatile && self.volatile == 0) {
+            return;
+        }
+
+        if !volatile {
+            for i in sample(&mut rng(), self.len(), count.min(self.len())) {
+                let (k, o) = self
+                    .entries
+                    .get_index(i)
+                    .expect("the index is within the map");
+                visit(k, o);
+            }
+            return;
+        }
+        let start = rng().random_range(0..self.len());
+        let mut visited = 0;
+        for i in (start..self.len()).chain(0..start) {
+            let (k, o) = self
+                .entries
+                .get_index(i)
+                .expect("the index is within the map");
+            if o.expiration.is_some() {
+                visit(k, o);
+                visited += 1;
+                if visited == count {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn sample_expired(&self, sample_size: usize) -> Vec<Bytes> {
+        let indexes = sample(&mut rng(), self.len(), sample_size.min(self.len()));
+
+        indexes
+            .into_iter()
+            .filter_map(|i| self.entries.get_index(i))
+            .filter(|(_, o)| o.is_expired())
+            .map(|(k, _)| k.clone())
+            .collect()
+    }
+}
+
+/// Number of shards of a database, unless configured otherwise.
+pub const DEFAULT_SHARDS: usize = 16;
+
+/// Keyspace split into shards that are locked independently, so that commands on keys of
+/// different shards don't wait for each other. A key belongs to the shard picked by its hash.
+pub struct Database {
+    shards: Box<[Mutex<Keyspace>]>,
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHARDS, Settings::default())
+    }
+}
+
+impl Database {
+    pub fn new(shards: usize, settings: Settings) -> Self {
+        Self {
+            shards: (0..shards)
+                .map(|_| Mutex::new(Keyspace::new(settings)))
+                .collect(),
+        }
+    }
+
+    pub fn shards(&self) -> &[Mutex<Keyspace>] {
+        &self.shards
+    }
+
+    /// Index of the shard of `key`.
+    pub fn shard_of(&self, key: &[u8]) -> usize {
+        (xxh3_64(key) % self.shards.len() as u64) as usize
+    }
+
+    /// Locks the shard of `key`.
+    pub fn lock(&self, key: &[u8]) -> MutexGuard<'_, Keyspace> {
+        self.shards[self.shard_of(key)].lock().unwrap()
+    }
+
+    /// Locks the shards of all of `keys` at once, for commands that must see or change them
+    /// atomically. Shards are locked in ascending order, so that two such commands can't
+    /// deadlock.
+    pub fn lock_keys(&self, keys: &[Bytes]) -> LockedShards<'_> {
+        let mut indexes: Vec<usize> = keys.iter().map(|k| self.shard_of(k)).collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+
+        LockedShards {
+            db: self,
+            guards: indexes
+                .into_iter()
+                .map(|i| (i, self.shards[i].lock().unwrap()))
+                .collect(),
+        }
+    }
+
+    /// Number of keys, expired ones included. Shards are counted one after the other, so the
+    /// result may mix states of the database while commands run.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().len()).sum()
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn expired_keys(&self) -> u64 {
+        self.shards
+            .iter()
+            .map(|s| s.lock().unwrap().expired_keys())
+            .sum()
+    }
+
+    /// Memory used by the keys of the database and their values.
+    pub fn used_memory(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|s| s.lock().unwrap().used_memory())
+            .sum()
+    }
+
+    /// Returns a random live key. Shards are picked in proportion to their number of keys, so
+    /// that every key has the same chance of being returned.
+    pub fn random_key(&self) -> Option<Bytes> {
+        let mut shards = self.lock_all();
+        loop {
+            let total: usize = shards.iter().map(|s| s.len()).sum();
+            if total == 0 {
+                return None;
+            }
+            let mut i = rng().random_range(0..total);
+            let shard = shards
+                .iter_mut()
+                .find(|s| match i.checked_sub(s.len()) {
+                    Some(rest) => {
+                        i = rest;
+                        false
+                    }
+                    None => true,
+                })
+                .expect("the index is below the number of keys");
+            // only expired keys were picked otherwise
+            if let Some(key) = shard.random_key() {
+                return Some(key);
+            }
+        }
+    }
+
+    /// Locks every shard, in ascending order.
+    pub fn lock_all(&self) -> Vec<MutexGuard<'_, Keyspace>> {
+        self.shards.iter().map(|s| s.lock().unwrap()).collect()
+    }
+
+    /// Visits live keys from `cursor`, shard after shard, until `count` keys have been looked at,
+    /// and returns the cursor to continue from: `0` once every shard has been scanned.
+    ///
+    /// The lowest bits of the cursor are the index of the shard, the others are the cursor
+    /// within the shard. Shards are locked one at a time.
+    pub fn scan(&self, cursor: u64, count: usize, mut visit: impl FnMut(&Bytes, &Object)) -> u64 {
+        let bits = self.shards.len().next_power_of_two().trailing_zeros();
+        let mut shard = (cursor & ((1 << bits) - 1)) as usize;
+        let mut position = (cursor >> bits) as usize;
+
+        let mut looked_at = 0;
+        while shard < self.shards.len() && looked_at < count {
+            let keyspace = self.shards[shard].lock().unwrap();
+            position = keyspace.scan(position, count - looked_at, |k, o| {
+                looked_at += 1;
+                if !o.is_expired() {
+                    visit(k, o);
+                }
+            });
+            if position == 0 {
+                shard += 1;
+            }
+        }
+
+        if shard >= self.shards.len() {
+            return 0;
+        }
+        (position as u64) << bits | shard as u64
+    }
+
+    /// Empties the database at once, returning the keys that were removed.
+    pub fn flush(&self) -> Vec<Keyspace> {
+        self.lock_all().iter_mut().map(|s| s.take()).collect()
+    }
+}
+
+/// Number of databases, unless configured otherwise.
+pub const DEFAULT_DATABASES: usize = 16;
+
+/// Logical databases, selected by index. Each one has its own keys, split into the same number
+/// of shards.
+///
+/// Commands locking several shards across databases lock them in the order of the databases,
+/// then of the shards, so that they can't deadlock.
+pub struct Databases {
+    databases: Box<[Database]>,
+}
+
+pub type Db = Arc<Databases>;
+
+impl Default for Databases {
+    fn default() -> Self {
+        Self::new(DEFAULT_DATABASES, DEFAULT_SHARDS, Settings::default())
+    }
+}
+
+impl Index<usize> for Databases {
+    type Output = Database;
+
+    fn index(&self, index: usize) -> &Database {
+        &self.databases[index]
+    }
+}
+
+impl Databases {
+    pub fn new(databases: usize, shards: usize, settings: Settings) -> Self {
+        Self {
+            databases: (0..databases)
+                .map(|_| Database::new(shards, settings))
+                .collect(),
+        }
+    }
+
+    /// Checks a database index given by a client, who may send negative ones: `None` if it's
+    /// out of range.
+    pub fn checked_index(&self, index: i64) -> Option<usize> {
+        usize::try_from(index)
+            .ok()
+            .filter(|i| *i < self.databases.len())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Database> {
+        self.databases.iter()
+    }
+
+    pub fn expired_keys(&self) -> u64 {
+        self.databases.iter().map(Database::expired_keys).sum()
+    }
+
+    pub fn used_memory(&self) -> usize {
+        self.databases.iter().map(Database::used_memory).sum()
+    }
+
+    /// Locks the shard of `source` in the database at `from` and the shard of `target` in the
+    /// database at `to`, which must be different, in ascending order of the databases.
+    pub fn lock_pair(
+        &self,
+        from: usize,
+        source: &[u8],
+        to: usize,
+        target: &[u8],
+    ) -> (MutexGuard<'_, Keyspace>, MutexGuard<'_, Keyspace>) {
+        assert_ne!(
+            from, to,
+            "shards of the same database are locked with `lock_keys`"
+        );
+        if from < to {
+            let first = self.databases[from].lock(source);
+            (first, self.databases[to].lock(target))
+        } else {
+            let first = self.databases[to].lock(target);
+            (self.databases[from].lock(source), first)
+        }
+    }
+
+    /// Swaps the keys of two databases at once: clients see the keys of the other database
+    /// without having to select it.
+    pub fn swap(&self, a: usize, b: usize) {
+        if a == b {
+            return;
+        }
+        let (first, second) = (a.min(b), a.max(b));
+        let mut first = self.databases[first].lock_all();
+        let mut second = self.databases[second].lock_all();
+        for (x, y) in first.iter_mut().zip(second.iter_mut()) {
+            mem::swap(&mut **x, &mut **y);
         }
-        trace!("removed {} expired entries", keys.len());
     }
 
-    keys.len() as f64 / sample_size as f64
+    /// Empties every database at once, returning the keys that were removed.
+    pub fn flush(&self) -> Vec<Keyspace> {
+        let mut shards: Vec<_> = self.databases.iter().flat_map(Database::lock_all).collect();
+        shards.iter_mut().map(|s| s.take()).collect()
+    }
+}
+
+/// Shards locked by [`Database::lock_keys`], released when dropped.
+pub struct LockedShards<'a> {
+    db: &'a Database,
+    /// Guards of the locked shards, by ascending index.
+    guards: Vec<(usize, MutexGuard<'a, Keyspace>)>,
+}
+
+impl LockedShards<'_> {
+    /// Returns the shard of `key`, which must be one of the keys the shards were locked for.
+    pub fn shard(&mut self, key: &[u8]) -> &mut Keyspace {
+        let i = self.db.shard_of(key);
+        let position = self
+            .guards
+            .binary_search_by_key(&i, |(j, _)| *j)
+            .expect("the shard of the key is locked");
+        &mut self.guards[position].1
+    }
+}
+
+/// Runs a pass of the active expirer on `shard`, see [`Keyspace::remove_expired`].
+pub fn remove_expired_entries(shard: &Mutex<Keyspace>, sample_size: usize) -> Sample {
+    shard.lock().unwrap().remove_expired(sample_size)
 }
 
 #[cfg(test)]
 mod test {
-    use std::time::Duration;
+    use std::time::{Duration, UNIX_EPOCH};
 
     use uuid::Uuid;
 
+    use crate::clock::MockClock;
+
     use super::*;
 
+    const INDEXED: Settings = Settings {
+        expiry_index: true,
+        lazy_expire: false,
+    };
+
     fn create_object(value: &str, expires_in_s: Option<i64>) -> Object {
         let expiration = expires_in_s.map(|s| {
             if s >= 0 {
-                SystemTime::now()
+                clock::now()
                     .checked_add(Duration::from_secs(s as u64))
                     .unwrap()
             } else {
-                SystemTime::now()
+                clock::now()
                     .checked_sub(Duration::from_secs((-s) as u64))
                     .unwrap()
             }
         });
 
-        Object {
-            value: Value::String(value.to_string()),
+        Object::new(
+            Value::String(Bytes::copy_from_slice(value.as_bytes())),
             expiration,
-        }
+        )
     }
 
-    fn create_test_db(entries: Vec<(String, Object)>) -> Db {
-        let mut map = IndexMap::new();
+    fn create_test_db(entries: Vec<(Bytes, Object)>) -> Mutex<Keyspace> {
+        let mut map = Keyspace::default();
         for (key, obj) in entries {
             map.insert(key, obj);
         }
-        Arc::new(Mutex::new(map))
+        Mutex::new(map)
+    }
+
+    #[test]
+    fn detach_small_bytes() {
+        let buf = Bytes::from(vec![b'a'; 64]);
+        let detached = detach(buf.slice(..10));
+        assert_eq!(detached, buf.slice(..10));
+        assert_ne!(detached.as_ptr(), buf.as_ptr());
+    }
+
+    #[test]
+    fn detach_big_bytes() {
+        let buf = Bytes::from(vec![b'a'; SHARED_MIN_LEN + 1]);
+        let detached = detach(buf.slice(1..));
+        assert_eq!(detached.as_ptr(), buf[1..].as_ptr());
     }
 
     #[test]
     fn object_no_expiration() {
-        let entries = vec![("key".to_string(), create_object("value", None))];
+        let entries = vec![(Bytes::from("key"), create_object("value", None))];
         let db = create_test_db(entries);
         let map = db.lock().unwrap();
 
-        assert!(!map.get("key").unwrap().is_expired());
+        assert!(!map.get(&b"key"[..]).unwrap().is_expired());
     }
 
     #[test]
     fn object_expired() {
-        let entries = vec![("key".to_string(), create_object("value", Some(-1)))];
+        let entries = vec![(Bytes::from("key"), create_object("value", Some(-1)))];
         let db = create_test_db(entries);
         let map = db.lock().unwrap();
 
-        assert!(map.get("key").unwrap().is_expired());
+        assert!(map.get(&b"key"[..]).unwrap().is_expired());
     }
 
     #[test]
     fn object_not_expired() {
         let obj = create_object("value", Some(3600));
-        let entries = vec![("key".to_string(), obj)];
+        let entries = vec![(Bytes::from("key"), obj)];
 
         let db = create_test_db(entries);
         let map = db.lock().unwrap();
 
-        assert!(!map.get("key").unwrap().is_expired());
+        assert!(!map.get(&b"key"[..]).unwrap().is_expired());
+    }
+
+    #[test]
+    fn from_bytes_integer() {
+        assert_eq!(Value::from_bytes(Bytes::from("123")), Value::Integer(123));
+        assert_eq!(Value::from_bytes(Bytes::from("-5")), Value::Integer(-5));
+        assert_eq!(Value::from_bytes(Bytes::from("0")), Value::Integer(0));
+    }
+
+    #[test]
+    fn from_bytes_not_canonical() {
+        for s in [
+            "007",
+            "+5",
+            "-0",
+            " 1",
+            "1 ",
+            "",
+            "9223372036854775808",
+            "1e3",
+        ] {
+            assert_eq!(
+                Value::from_bytes(Bytes::from(s)),
+                Value::String(Bytes::from(s)),
+                "{s}"
+            );
+        }
+    }
+
+    #[test]
+    fn digest_ok() {
+        assert_eq!(digest(b""), "2d06800538d394c2");
+        assert_eq!(digest(b"hello").len(), 16);
+        assert_ne!(digest(b"hello"), digest(b"hellO"));
+    }
+
+    #[test]
+    fn live_missing() {
+        let mut map = Keyspace::default();
+        assert!(map.live(b"key").is_none());
+    }
+
+    #[test]
+    fn remove_live_skips_expired() {
+        let mut map = Keyspace::default();
+        map.insert(Bytes::from("live"), create_object("value", None));
+        map.insert(Bytes::from("expired"), create_object("value", Some(-1)));
+
+        assert!(map.remove_live(b"expired").is_none());
+        assert_eq!(map.expired_keys(), 1);
+        assert!(map.remove_live(b"live").is_some());
+        assert!(map.remove_live(b"missing").is_none());
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn live_removes_expired() {
+        let mut map = Keyspace::default();
+        map.insert(Bytes::from("key"), create_object("value", Some(-1)));
+
+        assert!(map.live(b"key").is_none());
+        assert!(map.is_empty());
+        assert_eq!(map.expired_keys(), 1);
+    }
+
+    #[test]
+    fn live_removes_expired_lazily() {
+        let mut map = Keyspace::new(Settings {
+            lazy_expire: true,
+            ..Default::default()
+        });
+        let list = (0..1000).map(|i| Bytes::from(i.to_string())).collect();
+        let expiration = clock::now() - Duration::from_secs(1);
+        map.insert(
+            Bytes::from("list"),
+            Object::new(Value::List(list), Some(expiration)),
+        );
+        map.insert(Bytes::from("key"), create_object("value", Some(-1)));
+
+        assert!(map.live(b"list").is_none());
+        assert!(map.live(b"key").is_none());
+        assert!(map.is_empty());
+        assert_eq!(map.expired_keys(), 2);
+    }
+
+    #[test]
+    fn live_ok() {
+        let mut map = Keyspace::default();
+        map.insert(Bytes::from("key"), create_object("value", Some(3600)));
+
+        let o = map.live(b"key").unwrap();
+        assert_eq!(o.value, Value::String(Bytes::from("value")));
+    }
+
+    #[test]
+    fn lookup_kind_ok() {
+        let mut map = Keyspace::default();
+        map.insert(Bytes::from("s"), create_object("value", None));
+        map.insert(Bytes::from("i"), Object::new(Value::Integer(1), None));
+
+        assert!(map.lookup(b"s", Kind::String).unwrap().is_some());
+        assert!(map.lookup(b"i", Kind::String).unwrap().is_some());
+        assert!(map.lookup(b"missing", Kind::List).unwrap().is_none());
+    }
+
+    #[test]
+    fn lookup_wrong_kind() {
+        let mut map = Keyspace::default();
+        map.insert(Bytes::from("s"), create_object("value", None));
+        map.insert(
+            Bytes::from("l"),
+            Object::new(Value::List(VecDeque::from([Bytes::from("a")])), None),
+        );
+
+        assert_eq!(map.lookup(b"s", Kind::List).err(), Some(WrongType));
+        assert_eq!(map.lookup(b"l", Kind::String).err(), Some(WrongType));
+    }
+
+    #[test]
+    fn lookup_expired_wrong_kind() {
+        let mut map = Keyspace::default();
+        map.insert(Bytes::from("s"), create_object("value", Some(-1)));
+
+        assert!(map.lookup(b"s", Kind::List).unwrap().is_none());
+    }
+
+    #[test]
+    fn object_expires_with_clock() {
+        let clock = MockClock::at(UNIX_EPOCH + Duration::from_secs(1_000));
+        let obj = create_object("value", Some(10));
+
+        clock.advance(Duration::from_secs(10));
+        assert!(!obj.is_expired());
+
+        clock.advance(Duration::from_millis(1));
+        assert!(obj.is_expired());
     }
 
     #[test]
     fn empty_map() {
         let db = create_test_db(vec![]);
-        let result = remove_expired_entries(&db, 10);
+        let result = remove_expired_entries(&db, 10).ratio();
         assert_eq!(result, 0.0);
         assert_eq!(db.lock().unwrap().len(), 0);
     }
@@ -150,53 +1055,71 @@ mod test {
     #[test]
     fn not_yet_expired_entries() {
         let entries = vec![
-            (Uuid::new_v4().to_string(), create_object("val1", Some(100))),
-            (Uuid::new_v4().to_string(), create_object("val2", Some(100))),
-            (Uuid::new_v4().to_string(), create_object("val3", Some(100))),
+            (
+                Bytes::from(Uuid::new_v4().to_string()),
+                create_object("val1", Some(100)),
+            ),
+            (
+                Bytes::from(Uuid::new_v4().to_string()),
+                create_object("val2", Some(100)),
+            ),
+            (
+                Bytes::from(Uuid::new_v4().to_string()),
+                create_object("val3", Some(100)),
+            ),
         ];
         let original_len = entries.len();
         let db = create_test_db(entries);
 
-        let result = remove_expired_entries(&db, original_len);
+        let result = remove_expired_entries(&db, original_len).ratio();
         assert_eq!(result, 0.0);
         assert_eq!(db.lock().unwrap().len(), original_len);
     }
 
     #[test]
     fn no_expiration_entries() {
-        let key1 = Uuid::new_v4().to_string();
+        let key1 = Bytes::from(Uuid::new_v4().to_string());
 
         let entries = vec![(key1.clone(), create_object("val1", None))];
         let db = create_test_db(entries);
 
-        let result = remove_expired_entries(&db, 1);
+        let result = remove_expired_entries(&db, 1).ratio();
         assert_eq!(result, 0.0);
 
         let locked_db = db.lock().unwrap();
         assert_eq!(locked_db.len(), 1);
-        assert!(locked_db.contains_key(&key1));
+        assert!(locked_db.get(&key1).is_some());
     }
 
     #[test]
     fn all_entries_expired() {
         let entries = vec![
-            (Uuid::new_v4().to_string(), create_object("val1", Some(-1))),
-            (Uuid::new_v4().to_string(), create_object("val2", Some(-2))),
-            (Uuid::new_v4().to_string(), create_object("val3", Some(-3))),
+            (
+                Bytes::from(Uuid::new_v4().to_string()),
+                create_object("val1", Some(-1)),
+            ),
+            (
+                Bytes::from(Uuid::new_v4().to_string()),
+                create_object("val2", Some(-2)),
+            ),
+            (
+                Bytes::from(Uuid::new_v4().to_string()),
+                create_object("val3", Some(-3)),
+            ),
         ];
         let original_len = entries.len();
         let db = create_test_db(entries);
 
-        let result = remove_expired_entries(&db, original_len);
+        let result = remove_expired_entries(&db, original_len).ratio();
         assert_eq!(result, 1.0);
         assert_eq!(db.lock().unwrap().len(), 0);
     }
 
     #[test]
     fn some_entries_expired() {
-        let expired_key1 = Uuid::new_v4().to_string();
-        let expired_key2 = Uuid::new_v4().to_string();
-        let valid_key = Uuid::new_v4().to_string();
+        let expired_key1 = Bytes::from(Uuid::new_v4().to_string());
+        let expired_key2 = Bytes::from(Uuid::new_v4().to_string());
+        let valid_key = Bytes::from(Uuid::new_v4().to_string());
 
         let entries = vec![
             (expired_key1.clone(), create_object("expired1", Some(-1))),
@@ -206,12 +1129,12 @@ mod test {
         let db = create_test_db(entries);
 
         // 2 out of 3 expired
-        let result = remove_expired_entries(&db, 3);
+        let result = remove_expired_entries(&db, 3).ratio();
         assert!(result > 0.65 && result < 0.67);
 
         let locked_db = db.lock().unwrap();
         assert_eq!(locked_db.len(), 1);
-        assert!(locked_db.contains_key(&valid_key));
+        assert!(locked_db.get(&valid_key).is_some());
     }
 
     #[test]
@@ -220,7 +1143,7 @@ mod test {
         let mut expired_keys = vec![];
 
         for i in 0..5 {
-            let key = Uuid::new_v4().to_string();
+            let key = Bytes::from(Uuid::new_v4().to_string());
             entries.push((
                 key.clone(),
                 create_object(&format!("expired{}", i), Some(-1)),
@@ -230,14 +1153,14 @@ mod test {
 
         for i in 0..5 {
             entries.push((
-                Uuid::new_v4().to_string(),
+                Bytes::from(Uuid::new_v4().to_string()),
                 create_object(&format!("valid{}", i), Some(100)),
             ));
         }
 
         let db = create_test_db(entries);
 
-        let ratio = remove_expired_entries(&db, 3);
+        let ratio = remove_expired_entries(&db, 3).ratio();
         assert!((0.0..=1.0).contains(&ratio));
 
         // at most 3 entries should have been removed
@@ -247,7 +1170,8 @@ mod test {
 
     #[test]
     fn just_expired_entries() {
-        let just_expired_key = Uuid::new_v4().to_string();
+        let clock = MockClock::at(UNIX_EPOCH + Duration::from_secs(1_000));
+        let just_expired_key = Bytes::from(Uuid::new_v4().to_string());
 
         let entries = vec![(
             just_expired_key.clone(),
@@ -255,12 +1179,317 @@ mod test {
         )];
         let db = create_test_db(entries);
 
-        std::thread::sleep(Duration::from_millis(1));
+        let result = remove_expired_entries(&db, 2).ratio();
+        assert_eq!(result, 0.0);
+
+        clock.advance(Duration::from_millis(1));
 
-        let result = remove_expired_entries(&db, 2);
+        let result = remove_expired_entries(&db, 2).ratio();
         assert_eq!(result, 1.0);
 
         let locked_db = db.lock().unwrap();
         assert_eq!(locked_db.len(), 0);
     }
+
+    #[test]
+    fn value_memory_usage() {
+        assert_eq!(Value::Integer(1).memory_usage(), 0);
+        assert_eq!(Value::String(Bytes::from("abc")).memory_usage(), 3);
+
+        let mut list = VecDeque::with_capacity(4);
+        list.push_back(Bytes::from("ab"));
+        list.push_back(Bytes::from("c"));
+        let capacity = list.capacity();
+        assert_eq!(
+            Value::List(list).memory_usage(),
+            capacity * size_of::<Bytes>() + 3
+        );
+    }
+
+    #[test]
+    fn keyspace_used_memory() {
+        let mut map = Keyspace::new(INDEXED);
+        let object = create_object("value", None);
+        let usage = ENTRY_OVERHEAD + 3 + size_of::<Object>() + 5;
+        assert_eq!(object.memory_usage(), size_of::<Object>() + 5);
+
+        map.insert(Bytes::from("key"), object);
+        assert_eq!(map.memory_usage(b"key"), Some(usage));
+        map.insert(Bytes::from("key"), create_object("longer value", None));
+        assert_eq!(map.used_memory(), usage + 7);
+
+        map.set_expiration(b"key", Some(clock::now() + Duration::from_secs(10)));
+        assert_eq!(map.used_memory(), usage + 7 + EXPIRY_OVERHEAD);
+        map.grow(b"key", 100);
+        assert_eq!(
+            map.memory_usage(b"key"),
+            Some(usage + 107 + EXPIRY_OVERHEAD)
+        );
+
+        map.insert(Bytes::from("other"), create_object("value", None));
+        map.swap_remove(b"key");
+        assert_eq!(map.used_memory(), usage + 2);
+        assert_eq!(map.take().used_memory(), usage + 2);
+        assert_eq!(map.used_memory(), 0);
+    }
+
+    fn indexed(map: &Keyspace) -> Vec<(SystemTime, Bytes)> {
+        map.expires.as_ref().unwrap().iter().cloned().collect()
+    }
+
+    #[test]
+    fn expiry_index_insert_and_remove() {
+        let mut map = Keyspace::new(INDEXED);
+        let deadline = clock::now() + Duration::from_secs(10);
+        map.insert(
+            Bytes::from("a"),
+            Object::new(Value::Integer(1), Some(deadline)),
+        );
+        map.insert(Bytes::from("b"), Object::new(Value::Integer(2), None));
+        assert_eq!(indexed(&map), vec![(deadline, Bytes::from("a"))]);
+
+        // overwriting with the same deadline keeps the entry, with another one replaces it
+        map.insert(
+            Bytes::from("a"),
+            Object::new(Value::Integer(3), Some(deadline)),
+        );
+        assert_eq!(indexed(&map), vec![(deadline, Bytes::from("a"))]);
+        let later = deadline + Duration::from_secs(1);
+        map.insert(
+            Bytes::from("a"),
+            Object::new(Value::Integer(4), Some(later)),
+        );
+        assert_eq!(indexed(&map), vec![(later, Bytes::from("a"))]);
+
+        map.insert(Bytes::from("a"), Object::new(Value::Integer(5), None));
+        assert!(indexed(&map).is_empty());
+
+        map.set_expiration(b"b", Some(deadline));
+        assert_eq!(indexed(&map), vec![(deadline, Bytes::from("b"))]);
+        map.swap_remove(b"b");
+        assert!(indexed(&map).is_empty());
+    }
+
+    #[test]
+    fn expiry_index_set_expiration() {
+        let mut map = Keyspace::new(INDEXED);
+        let deadline = clock::now() + Duration::from_secs(10);
+        map.insert(
+            Bytes::from("a"),
+            Object::new(Value::Integer(1), Some(deadline)),
+        );
+
+        let later = deadline + Duration::from_secs(1);
+        assert!(map.set_expiration(b"a", Some(later)));
+        assert_eq!(indexed(&map), vec![(later, Bytes::from("a"))]);
+        assert_eq!(map.get(b"a").unwrap().expiration(), Some(later));
+
+        assert!(map.set_expiration(b"a", None));
+        assert!(indexed(&map).is_empty());
+        assert_eq!(map.get(b"a").unwrap().expiration(), None);
+
+        assert!(!map.set_expiration(b"missing", Some(later)));
+        assert!(indexed(&map).is_empty());
+    }
+
+    #[test]
+    fn expiry_index_sample() {
+        let mut map = Keyspace::new(INDEXED);
+        for i in 0..20 {
+            let expires_in_s = if i < 3 { -1 } else { 100 };
+            map.insert(
+                Bytes::from(format!("key{i}")),
+                create_object("value", Some(expires_in_s)),
+            );
+        }
+
+        // the keys looked at are the due ones and the first one that isn't
+        assert_eq!(
+            map.remove_expired(20),
+            Sample {
+                sampled: 4,
+                expired: 3,
+                complete: true
+            }
+        );
+        assert_eq!(map.len(), 17);
+    }
+
+    #[test]
+    fn expiry_index_removes_due_keys() {
+        let clock = MockClock::at(UNIX_EPOCH + Duration::from_secs(1_000));
+        let mut map = Keyspace::new(INDEXED);
+        for i in 0..10 {
+            map.insert(
+                Bytes::from(format!("key{i}")),
+                Object::new(
+                    Value::Integer(i),
+                    Some(clock::now() + Duration::from_secs(i as u64)),
+                ),
+            );
+        }
+        map.insert(
+            Bytes::from("persistent"),
+            Object::new(Value::Integer(0), None),
+        );
+
+        clock.advance(Duration::from_millis(3_500));
+        // key0 to key3 are due, more than the limit
+        assert_eq!(
+            map.remove_expired(2),
+            Sample {
+                sampled: 2,
+                expired: 2,
+                complete: false
+            }
+        );
+        assert_eq!(map.len(), 9);
+        // key2 and key3 are due, key4 is looked at and isn't
+        assert_eq!(
+            map.remove_expired(5),
+            Sample {
+                sampled: 3,
+                expired: 2,
+                complete: true
+            }
+        );
+        assert_eq!(map.len(), 7);
+        assert_eq!(
+            map.remove_expired(5),
+            Sample {
+                sampled: 1,
+                expired: 0,
+                complete: true
+            }
+        );
+        assert!(map.get(b"key3").is_none());
+        assert!(map.get(b"key4").is_some());
+        assert_eq!(indexed(&map).len(), 6);
+        assert_eq!(map.expired_keys(), 4);
+    }
+
+    #[test]
+    fn database_shards_keys() {
+        let db = Database::new(4, Settings::default());
+        for i in 0..100 {
+            let key = Bytes::from(format!("key{i}"));
+            db.lock(&key)
+                .insert(key.clone(), Object::new(Value::Integer(i), None));
+        }
+
+        assert_eq!(db.len(), 100);
+        assert!(db.shards().iter().all(|s| !s.lock().unwrap().is_empty()));
+        for i in 0..100 {
+            let key = format!("key{i}");
+            assert!(db.lock(key.as_bytes()).live(key.as_bytes()).is_some());
+        }
+    }
+
+    #[test]
+    fn database_lock_keys() {
+        let db = Database::new(4, INDEXED);
+        let keys: Vec<Bytes> = (0..20).map(|i| Bytes::from(format!("key{i}"))).collect();
+
+        let mut shards = db.lock_keys(&[keys.clone(), keys.clone()].concat());
+        assert!(shards.guards.is_sorted_by_key(|(i, _)| *i));
+        assert!(shards.guards.len() <= 4);
+        for (i, key) in keys.iter().enumerate() {
+            shards
+                .shard(key)
+                .insert(key.clone(), Object::new(Value::Integer(i as i64), None));
+        }
+        drop(shards);
+
+        assert_eq!(db.len(), 20);
+        assert_eq!(
+            db.lock(b"key3").get(b"key3").unwrap().value,
+            Value::Integer(3)
+        );
+    }
+
+    #[test]
+    fn databases_swap() {
+        let dbs = Databases::new(3, 4, INDEXED);
+        dbs[0]
+            .lock(b"a")
+            .insert(Bytes::from("a"), create_object("0", Some(10)));
+        dbs[2]
+            .lock(b"b")
+            .insert(Bytes::from("b"), create_object("2", None));
+
+        dbs.swap(2, 0);
+        assert!(dbs[0].lock(b"a").get(b"a").is_none());
+        assert!(dbs[0].lock(b"b").get(b"b").is_some());
+        assert!(dbs[2].lock(b"a").get(b"a").is_some());
+        assert_eq!(
+            dbs[2].lock(b"a").remove_expired(10),
+            Sample {
+                sampled: 1,
+                expired: 0,
+                complete: true
+            }
+        );
+    }
+
+    #[test]
+    fn databases_flush() {
+        let dbs = Databases::new(2, 4, INDEXED);
+        for db in dbs.iter() {
+            db.lock(b"key")
+                .insert(Bytes::from("key"), create_object("value", Some(-10)));
+        }
+        dbs[1].lock(b"key").live(b"key");
+
+        let removed = dbs[0].flush();
+        assert_eq!(removed.iter().map(Keyspace::len).sum::<usize>(), 1);
+        assert!(dbs[0].is_empty());
+        assert_eq!(dbs[1].len(), 0);
+
+        dbs[0]
+            .lock(b"key")
+            .insert(Bytes::from("key"), create_object("value", Some(-10)));
+        dbs.flush();
+        assert!(dbs.iter().all(Database::is_empty));
+        assert_eq!(dbs.expired_keys(), 1);
+        // the index was emptied along with the keys
+        let mut shard = dbs[0].lock(b"key");
+        shard.insert(Bytes::from("key"), create_object("value", None));
+        assert_eq!(
+            shard.remove_expired(10),
+            Sample {
+                sampled: 0,
+                expired: 0,
+                complete: true
+            }
+        );
+    }
+
+    #[test]
+    fn databases_checked_index() {
+        let dbs = Databases::new(2, 1, Settings::default());
+        assert_eq!(dbs.checked_index(1), Some(1));
+        assert_eq!(dbs.checked_index(2), None);
+        assert_eq!(dbs.checked_index(-1), None);
+    }
+
+    #[test]
+    fn database_random_key() {
+        let db = Database::new(4, Settings::default());
+        assert_eq!(db.random_key(), None);
+
+        for i in 0..10 {
+            let key = Bytes::from(format!("expired{i}"));
+            db.lock(&key)
+                .insert(key.clone(), create_object("value", Some(-1)));
+        }
+        assert_eq!(db.random_key(), None);
+        assert!(db.is_empty());
+        assert_eq!(db.expired_keys(), 10);
+
+        db.lock(b"live")
+            .insert(Bytes::from("live"), create_object("value", None));
+        db.lock(b"expired")
+            .insert(Bytes::from("expired"), create_object("value", Some(-1)));
+        assert_eq!(db.random_key(), Some(Bytes::from("live")));
+    }
 }
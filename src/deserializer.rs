@@ -1,113 +1,243 @@
-use std::{num::ParseIntError, str};
+use std::{mem, num::ParseIntError, ops::Range, str};
+
+use bytes::{Bytes, BytesMut};
 use thiserror::Error;
 
-use crate::resp::types::{ARRAY, BULK_STRING, CR, LF};
+use crate::{
+    config::Config,
+    resp::{
+        error::ErrorReply,
+        types::{ARRAY, BULK_STRING, CR, LF},
+    },
+};
 
-#[derive(Debug, Error)]
-#[error("\r\n not found")]
-struct CrLfNotFound;
+mod inline;
+
+/// Maximum size of an inline request, or of the header of an array or bulk string, as in Redis.
+const INLINE_MAX_LEN: usize = 64 * 1024;
 
-#[derive(Default)]
+/// Parser of the frames of a connection. An array whose end hasn't been read yet is parsed as far
+/// as it goes, and parsing resumes from there once more data has been read, as Redis does with
+/// `multibulklen` and `bulklen`: a big array sent over many reads is parsed once, not once per
+/// read.
 pub struct Deserializer {
+    /// Number of elements of the array being parsed, `None` until its header has been parsed.
+    array_len: Option<usize>,
+    /// Elements of the array parsed so far, as ranges of the frame.
+    params: Vec<Range<usize>>,
+    /// Position in the frame where parsing resumes: the next element, then the end of the frame.
     cursor: usize,
     cr_pos: usize,
     lf_pos: usize,
+    max_bulk_len: usize,
+    max_multibulk_len: usize,
+    query_buffer_limit: usize,
+}
+
+impl Default for Deserializer {
+    fn default() -> Self {
+        Self::new(&Config::default())
+    }
 }
 
 #[derive(Debug, Error)]
 pub enum DeserializeError {
-    #[error("message must be an array")]
-    InvalidStartOfMsg,
-    #[error("invalid array")]
+    #[error("Protocol error: invalid array")]
     MalformedArray,
-    #[error("bulk string expected")]
+    #[error("Protocol error: bulk string expected")]
     BulkStringExpected,
-    #[error("malformed bulk string")]
+    #[error("Protocol error: malformed bulk string")]
     MalformedBulkString,
+    #[error("Protocol error: unbalanced quotes in request")]
+    UnbalancedQuotes,
+    #[error("Protocol error: invalid multibulk length")]
+    InvalidMultibulkLength,
+    #[error("Protocol error: invalid bulk length")]
+    InvalidBulkLength,
+    #[error("Protocol error: too big inline request")]
+    InlineTooBig,
+    #[error("Protocol error: too big mbulk count string")]
+    MultibulkCountTooBig,
+    #[error("Protocol error: too big bulk count string")]
+    BulkCountTooBig,
+    #[error("query buffer limit exceeded")]
+    QueryBufferLimit,
+}
+
+impl ErrorReply for DeserializeError {}
+
+/// Internal outcome of a parsing step: either the buffer ends before the frame does, or the
+/// frame is invalid and the connection can't be resynchronized.
+enum ParseError {
+    Incomplete,
+    Invalid(DeserializeError),
+}
+
+impl From<DeserializeError> for ParseError {
+    fn from(e: DeserializeError) -> Self {
+        ParseError::Invalid(e)
+    }
 }
 
 impl Deserializer {
-    pub fn deserialize_msg(&mut self, msg: &[u8]) -> Result<Vec<String>, DeserializeError> {
-        if msg.get(self.cursor).is_none_or(|c| *c != ARRAY) {
-            return Err(DeserializeError::InvalidStartOfMsg);
+    pub fn new(config: &Config) -> Self {
+        Self {
+            array_len: None,
+            params: vec![],
+            cursor: 0,
+            cr_pos: 0,
+            lf_pos: 0,
+            max_bulk_len: config.proto_max_bulk_len,
+            max_multibulk_len: config.proto_max_multibulk_len,
+            query_buffer_limit: config.client_query_buffer_limit,
         }
+    }
 
-        // advance to the first CRLF to find out how many elements the array has
-        self.cursor += 1;
-        self.update_cr_lf(msg)
-            .map_err(|_| DeserializeError::MalformedArray)?;
-        let array_size = get_u32_from_string(&msg[self.cursor..self.cr_pos])
-            .map_err(|_| DeserializeError::MalformedArray)?;
+    /// Deserializes the first frame of `buf` and splits it off the buffer, so that the next call
+    /// parses the following frame. `buf` must hold the same data as in the previous call, more
+    /// may have been appended, unless that call returned a frame.
+    ///
+    /// Frames are arrays of bulk strings, unless they start with anything other than `*`: those
+    /// are inline commands, i.e. a line of space-separated arguments as typed in a telnet session.
+    ///
+    /// The parameters of an array are slices of the split frame: no data is copied. `None` is
+    /// returned when `buf` doesn't hold a complete frame yet, i.e. more data has to be read from
+    /// the socket, unless `buf` is already over `client-query-buffer-limit`.
+    pub fn deserialize_msg(
+        &mut self,
+        buf: &mut BytesMut,
+    ) -> Result<Option<Vec<Bytes>>, DeserializeError> {
+        let frame = match buf.first() {
+            None => None,
+            Some(&ARRAY) => self.deserialize_array(buf)?,
+            Some(_) => deserialize_inline(buf)?,
+        };
+        if frame.is_none() && buf.len() > self.query_buffer_limit {
+            self.reset();
+            return Err(DeserializeError::QueryBufferLimit);
+        }
+        Ok(frame)
+    }
 
-        // extract the bulk strings
-        let mut params = vec![];
-        for _ in 0..array_size {
-            self.check_bulk_string_type(msg)?;
+    fn deserialize_array(
+        &mut self,
+        buf: &mut BytesMut,
+    ) -> Result<Option<Vec<Bytes>>, DeserializeError> {
+        let ranges = match self.parse_array(buf) {
+            Ok(ranges) => ranges,
+            Err(ParseError::Incomplete) => return Ok(None),
+            Err(ParseError::Invalid(e)) => {
+                self.reset();
+                return Err(e);
+            }
+        };
 
-            let (bulk_string, bulk_string_size) = self.extract_bulk_string(msg)?;
-            params.push(bulk_string);
+        let frame = buf.split_to(self.cursor).freeze();
+        self.reset();
+        Ok(Some(ranges.into_iter().map(|r| frame.slice(r)).collect()))
+    }
 
-            self.jump_to_lf(msg, bulk_string_size as usize)?;
-        }
+    /// Forgets the frame being parsed, so that the next one is parsed from its start.
+    fn reset(&mut self) {
+        self.array_len = None;
+        self.params.clear();
+        self.cursor = 0;
+    }
 
-        // make sure there's nothing else after the last CRLF
-        self.cursor += 1;
-        if msg.get(self.cursor).is_some() {
-            return Err(DeserializeError::MalformedArray);
+    fn parse_array(&mut self, msg: &[u8]) -> Result<Vec<Range<usize>>, ParseError> {
+        let array_len = match self.array_len {
+            Some(len) => len,
+            None => {
+                // skip the array type and advance to the first CRLF to find out how many
+                // elements the array has
+                self.cursor = 1;
+                self.update_cr_lf(msg, DeserializeError::MultibulkCountTooBig)?;
+                let len = get_usize_from_string(&msg[self.cursor..self.cr_pos])
+                    .map_err(|_| DeserializeError::MalformedArray)?;
+                if len > self.max_multibulk_len {
+                    return Err(DeserializeError::InvalidMultibulkLength.into());
+                }
+                self.cursor = self.lf_pos + 1;
+                self.array_len = Some(len);
+                len
+            }
+        };
+
+        // extract the bulk strings, from the first one that wasn't complete in the last call
+        while self.params.len() < array_len {
+            let start = self.cursor;
+            match self.parse_bulk_string(msg) {
+                Ok(bulk_string) => self.params.push(bulk_string),
+                Err(e) => {
+                    self.cursor = start;
+                    return Err(e);
+                }
+            }
         }
 
-        Ok(params)
+        // the frame ends right after the last LF, where the cursor is
+        Ok(mem::take(&mut self.params))
     }
 
-    fn check_bulk_string_type(&mut self, msg: &[u8]) -> Result<(), DeserializeError> {
+    /// Parses the bulk string at the cursor, moving the cursor past it.
+    fn parse_bulk_string(&mut self, msg: &[u8]) -> Result<Range<usize>, ParseError> {
+        self.check_bulk_string_type(msg)?;
+        let bulk_string = self.extract_bulk_string(msg)?;
+        self.jump_to_lf(msg, bulk_string.len())?;
         self.cursor = self.lf_pos + 1;
-        if msg.get(self.cursor).is_none() {
-            return Err(DeserializeError::MalformedBulkString);
-        }
-        if msg[self.cursor] != BULK_STRING {
-            return Err(DeserializeError::BulkStringExpected);
+        Ok(bulk_string)
+    }
+
+    fn check_bulk_string_type(&mut self, msg: &[u8]) -> Result<(), ParseError> {
+        match msg.get(self.cursor) {
+            None => Err(ParseError::Incomplete),
+            Some(c) if *c != BULK_STRING => Err(DeserializeError::BulkStringExpected.into()),
+            _ => Ok(()),
         }
-        Ok(())
     }
 
-    fn jump_to_lf(&mut self, msg: &[u8], bulk_string_size: usize) -> Result<(), DeserializeError> {
+    fn jump_to_lf(&mut self, msg: &[u8], bulk_string_size: usize) -> Result<(), ParseError> {
         self.cursor += bulk_string_size;
-        if msg.get(self.cursor).is_none_or(|c| *c != CR) {
-            return Err(DeserializeError::MalformedBulkString);
+        match msg.get(self.cursor) {
+            None => return Err(ParseError::Incomplete),
+            Some(c) if *c != CR => return Err(DeserializeError::MalformedBulkString.into()),
+            _ => {}
         }
         self.cursor += 1;
-        if msg.get(self.cursor).is_none_or(|c| *c != LF) {
-            return Err(DeserializeError::MalformedBulkString);
+        match msg.get(self.cursor) {
+            None => return Err(ParseError::Incomplete),
+            Some(c) if *c != LF => return Err(DeserializeError::MalformedBulkString.into()),
+            _ => {}
         }
         self.lf_pos = self.cursor;
         Ok(())
     }
 
-    fn extract_bulk_string(&mut self, msg: &[u8]) -> Result<(String, u32), DeserializeError> {
+    fn extract_bulk_string(&mut self, msg: &[u8]) -> Result<Range<usize>, ParseError> {
         // get the size
         self.cursor += 1;
-        self.update_cr_lf(msg)
-            .map_err(|_| DeserializeError::MalformedBulkString)?;
+        self.update_cr_lf(msg, DeserializeError::BulkCountTooBig)?;
 
-        let bulk_string_size = get_u32_from_string(&msg[self.cursor..self.cr_pos])
+        let bulk_string_size = get_usize_from_string(&msg[self.cursor..self.cr_pos])
             .map_err(|_| DeserializeError::MalformedBulkString)?;
+        if bulk_string_size > self.max_bulk_len {
+            return Err(DeserializeError::InvalidBulkLength.into());
+        }
 
-        // get the data (make sure it's consistent with the size)
+        // get the data (make sure it's all there before going any further)
         self.cursor = self.lf_pos + 1;
-        if msg.get(self.cursor).is_none() || msg[self.cursor..].len() < bulk_string_size as usize {
-            return Err(DeserializeError::MalformedBulkString);
+        if msg[self.cursor..].len() < bulk_string_size {
+            return Err(ParseError::Incomplete);
         }
-        let bulk_string_bytes = &msg[self.cursor..self.cursor + bulk_string_size as usize];
-        let bulk_string = str::from_utf8(bulk_string_bytes)
-            .map(|s| s.to_owned())
-            .map_err(|_| DeserializeError::MalformedBulkString)?;
 
-        Ok((bulk_string, bulk_string_size))
+        Ok(self.cursor..self.cursor + bulk_string_size)
     }
 
-    fn update_cr_lf(&mut self, msg: &[u8]) -> Result<(), CrLfNotFound> {
+    /// Finds the CRLF that ends the header at the cursor, failing with `too_big` if the header
+    /// is still going on after `INLINE_MAX_LEN` bytes.
+    fn update_cr_lf(&mut self, msg: &[u8], too_big: DeserializeError) -> Result<(), ParseError> {
         let mut cursor = self.cursor;
-        while cursor < msg.len() - 1 {
+        while cursor + 1 < msg.len() {
             if msg[cursor] == CR && msg[cursor + 1] == LF {
                 self.cr_pos = cursor;
                 self.lf_pos = cursor + 1;
@@ -115,89 +245,198 @@ impl Deserializer {
             }
             cursor += 1;
         }
-        Err(CrLfNotFound)
+        if msg.len() - self.cursor > INLINE_MAX_LEN {
+            return Err(too_big.into());
+        }
+        Err(ParseError::Incomplete)
     }
 }
 
-fn get_u32_from_string(s: &[u8]) -> Result<u32, ParseIntError> {
-    str::from_utf8(s).unwrap_or_default().parse::<u32>()
+/// Inline commands end with LF, optionally preceded by CR.
+fn deserialize_inline(buf: &mut BytesMut) -> Result<Option<Vec<Bytes>>, DeserializeError> {
+    let Some(lf_pos) = buf.iter().position(|c| *c == LF) else {
+        if buf.len() > INLINE_MAX_LEN {
+            return Err(DeserializeError::InlineTooBig);
+        }
+        return Ok(None);
+    };
+
+    let line = buf.split_to(lf_pos + 1);
+    let line = &line[..lf_pos];
+    let line = line.strip_suffix(&[CR]).unwrap_or(line);
+    inline::split_args(line).map(Some)
+}
+
+fn get_usize_from_string(s: &[u8]) -> Result<usize, ParseIntError> {
+    str::from_utf8(s).unwrap_or_default().parse::<usize>()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn deserialize(msg: &[u8]) -> Result<Option<Vec<Bytes>>, DeserializeError> {
+        Deserializer::default().deserialize_msg(&mut BytesMut::from(msg))
+    }
+
+    /// Returns the parameters of the first frame and how many bytes it takes.
+    fn deserialize_complete(msg: &[u8]) -> (Vec<Bytes>, usize) {
+        let mut buf = BytesMut::from(msg);
+        let params = Deserializer::default()
+            .deserialize_msg(&mut buf)
+            .unwrap()
+            .unwrap();
+        (params, msg.len() - buf.len())
+    }
+
+    fn deserialize_incomplete(msg: &[u8]) -> bool {
+        let mut buf = BytesMut::from(msg);
+        let incomplete = Deserializer::default()
+            .deserialize_msg(&mut buf)
+            .unwrap()
+            .is_none();
+        incomplete && buf.len() == msg.len()
+    }
+
     #[test]
     fn deserialize_ok() {
         let msg = b"*3\r\n$3\r\nits\r\n$3\r\nkey\r\n$5\r\nvalue\r\n";
         let expected_params = vec!["its", "key", "value"];
-        let mut deserializer = Deserializer::default();
-        assert_eq!(expected_params, deserializer.deserialize_msg(msg).unwrap());
+        let (params, consumed) = deserialize_complete(msg);
+        assert_eq!(expected_params, params);
+        assert_eq!(msg.len(), consumed);
 
         let msg = b"*1\r\n$0\r\n\r\n";
         let expected_params = vec![""];
-        let mut deserializer = Deserializer::default();
-        assert_eq!(expected_params, deserializer.deserialize_msg(msg).unwrap());
+        let (params, consumed) = deserialize_complete(msg);
+        assert_eq!(expected_params, params);
+        assert_eq!(msg.len(), consumed);
 
         let msg = b"*1\r\n$4\r\n\xF0\x9F\x92\xB8\r\n";
         let expected_params = vec!["💸"];
-        let mut deserializer = Deserializer::default();
-        assert_eq!(expected_params, deserializer.deserialize_msg(msg).unwrap());
+        let (params, consumed) = deserialize_complete(msg);
+        assert_eq!(expected_params, params);
+        assert_eq!(msg.len(), consumed);
+    }
+
+    #[test]
+    fn deserialize_binary() {
+        let msg = b"*2\r\n$3\r\n\xFF\x00\xFE\r\n$4\r\n\r\n\r\n\r\n";
+        let (params, consumed) = deserialize_complete(msg);
+        assert_eq!(vec![&b"\xFF\x00\xFE"[..], &b"\r\n\r\n"[..]], params);
+        assert_eq!(msg.len(), consumed);
     }
 
     #[test]
-    fn deserialize_invalid_start() {
+    fn deserialize_empty_array() {
+        let msg = b"*0\r\n";
+        assert_eq!((vec![], msg.len()), deserialize_complete(msg));
+    }
+
+    #[test]
+    fn deserialize_inline_ok() {
         let msg = b"$3\r\nGET\r\n";
-        let mut deserializer = Deserializer::default();
-        assert!(matches!(
-            deserializer.deserialize_msg(msg).unwrap_err(),
-            DeserializeError::InvalidStartOfMsg
-        ));
+        assert_eq!((vec![Bytes::from("$3")], 4), deserialize_complete(msg));
 
-        let msg = b"";
-        let mut deserializer = Deserializer::default();
+        let msg = b"SET foo \"bar baz\"\n";
+        let (params, consumed) = deserialize_complete(msg);
+        assert_eq!(vec!["SET", "foo", "bar baz"], params);
+        assert_eq!(msg.len(), consumed);
+
+        let msg = b"\r\n";
+        assert_eq!((vec![], 2), deserialize_complete(msg));
+    }
+
+    #[test]
+    fn deserialize_inline_incomplete() {
+        assert!(deserialize_incomplete(b"PING"));
+        assert!(deserialize_incomplete(b"SET foo bar\r"));
+    }
+
+    #[test]
+    fn deserialize_inline_pipelined() {
+        let msg = b"PING\r\n*1\r\n$4\r\nPING\r\nECHO hi\r\n";
+        let (params, consumed) = deserialize_complete(msg);
+        assert_eq!((vec![Bytes::from("PING")], 6), (params, consumed));
+
+        let (params, consumed) = deserialize_complete(&msg[6..]);
+        assert_eq!((vec![Bytes::from("PING")], 14), (params, consumed));
+
+        let (params, consumed) = deserialize_complete(&msg[20..]);
+        assert_eq!(
+            (vec![Bytes::from("ECHO"), Bytes::from("hi")], 9),
+            (params, consumed)
+        );
+    }
+
+    #[test]
+    fn deserialize_inline_unbalanced_quotes() {
+        let msg = b"SET foo \"bar\r\n";
         assert!(matches!(
-            deserializer.deserialize_msg(msg).unwrap_err(),
-            DeserializeError::InvalidStartOfMsg
+            deserialize(msg).unwrap_err(),
+            DeserializeError::UnbalancedQuotes
         ));
     }
 
+    #[test]
+    fn deserialize_empty_msg() {
+        assert!(deserialize_incomplete(b""));
+    }
+
     #[test]
     fn deserialize_invalid_array_size() {
         let msg = b"*x\r\n$4\r\nPING\r\n";
-        let mut deserializer = Deserializer::default();
         assert!(matches!(
-            deserializer.deserialize_msg(msg).unwrap_err(),
+            deserialize(msg).unwrap_err(),
             DeserializeError::MalformedArray
         ));
     }
 
     #[test]
     fn deserialize_array_size_bigger() {
-        let msg = b"*2\r\n$4\r\nPING\r\n";
+        assert!(deserialize_incomplete(b"*2\r\n$4\r\nPING\r\n"));
+    }
+
+    #[test]
+    fn deserialize_in_chunks() {
+        let frame = b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n";
+        let msg = [&frame[..], b"*1\r\n$4\r\nPING\r\n"].concat();
         let mut deserializer = Deserializer::default();
-        assert!(matches!(
-            deserializer.deserialize_msg(msg).unwrap_err(),
-            DeserializeError::MalformedBulkString
-        ));
+        let mut buf = BytesMut::new();
+        let mut frames = vec![];
+
+        for chunk in msg.chunks(3) {
+            buf.extend_from_slice(chunk);
+            while let Some(params) = deserializer.deserialize_msg(&mut buf).unwrap() {
+                frames.push(params);
+            }
+            // with `SET` and part of `key` read, `SET` is kept and won't be parsed again
+            if buf.len() == 21 {
+                assert_eq!(deserializer.params, vec![8..11]);
+            }
+        }
+
+        assert_eq!(frames, vec![vec!["SET", "key", "value"], vec!["PING"]]);
+        assert!(buf.is_empty());
     }
 
     #[test]
     fn deserialize_array_size_smaller() {
         let msg = b"*1\r\n$4\r\nECHO\r\n$5\r\nworld\r\n";
-        let mut deserializer = Deserializer::default();
-        assert!(matches!(
-            deserializer.deserialize_msg(msg).unwrap_err(),
-            DeserializeError::MalformedArray
-        ));
+        assert_eq!((vec![Bytes::from("ECHO")], 14), deserialize_complete(msg));
+
+        // what's left is read as an inline command
+        assert_eq!(
+            (vec![Bytes::from("$5")], 4),
+            deserialize_complete(&msg[14..])
+        );
     }
 
     #[test]
     fn deserialize_array_size_missing_terminator() {
         let msg = b"*1$4\r\nPING\r\n";
-        let mut deserializer = Deserializer::default();
         assert!(matches!(
-            deserializer.deserialize_msg(msg).unwrap_err(),
+            deserialize(msg).unwrap_err(),
             DeserializeError::MalformedArray
         ));
     }
@@ -205,9 +444,8 @@ mod tests {
     #[test]
     fn deserialize_bulk_string_expected() {
         let msg = b"*1\r\n[123\r\n";
-        let mut deserializer = Deserializer::default();
         assert!(matches!(
-            deserializer.deserialize_msg(msg).unwrap_err(),
+            deserialize(msg).unwrap_err(),
             DeserializeError::BulkStringExpected
         ));
     }
@@ -215,49 +453,37 @@ mod tests {
     #[test]
     fn deserialize_invalid_bulk_string_size() {
         let msg = b"*1\r\n$x\r\nPING\r\n";
-        let mut deserializer = Deserializer::default();
         assert!(matches!(
-            deserializer.deserialize_msg(msg).unwrap_err(),
+            deserialize(msg).unwrap_err(),
             DeserializeError::MalformedBulkString
         ));
     }
 
     #[test]
     fn deserialize_bulk_string_size_bigger() {
-        let msg = b"*1\r\n$10\r\nPING\r\n";
-        let mut deserializer = Deserializer::default();
-        assert!(matches!(
-            deserializer.deserialize_msg(msg).unwrap_err(),
-            DeserializeError::MalformedBulkString
-        ));
+        assert!(deserialize_incomplete(b"*1\r\n$10\r\nPING\r\n"));
     }
 
     #[test]
     fn deserialize_bulk_string_size_smaller() {
         let msg = b"*1\r\n$1\r\nPING\r\n";
-        let mut deserializer = Deserializer::default();
         assert!(matches!(
-            deserializer.deserialize_msg(msg).unwrap_err(),
+            deserialize(msg).unwrap_err(),
             DeserializeError::MalformedBulkString
         ));
     }
 
     #[test]
     fn deserialize_bulk_string_missing_terminator() {
-        let msg = b"*1\r\n$4\r\nPING";
-        let mut deserializer = Deserializer::default();
-        assert!(matches!(
-            deserializer.deserialize_msg(msg).unwrap_err(),
-            DeserializeError::MalformedBulkString
-        ));
+        assert!(deserialize_incomplete(b"*1\r\n$4\r\nPING"));
+        assert!(deserialize_incomplete(b"*1\r\n$4\r\nPING\r"));
     }
 
     #[test]
     fn deserialize_bulk_string_size_missing_terminator() {
         let msg = b"*1\r\n$4PING\r\n";
-        let mut deserializer = Deserializer::default();
         assert!(matches!(
-            deserializer.deserialize_msg(msg).unwrap_err(),
+            deserialize(msg).unwrap_err(),
             DeserializeError::MalformedBulkString
         ));
     }
@@ -265,10 +491,140 @@ mod tests {
     #[test]
     fn deserialize_trailing_data() {
         let msg = b"*1\r\n$4\r\nPING\r\nEXTRA";
-        let mut deserializer = Deserializer::default();
+        assert_eq!((vec![Bytes::from("PING")], 14), deserialize_complete(msg));
+    }
+
+    #[test]
+    fn deserialize_zero_copy() {
+        let mut buf = BytesMut::from(&b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n*1\r\n$4\r\nPI"[..]);
+        let start = buf.as_ptr() as usize;
+        let params = Deserializer::default()
+            .deserialize_msg(&mut buf)
+            .unwrap()
+            .unwrap();
+
+        // the parameters point into the read buffer
+        assert_eq!(params[0].as_ptr() as usize, start + 8);
+        assert_eq!(params[1].as_ptr() as usize, start + 17);
+        assert_eq!(&buf[..], b"*1\r\n$4\r\nPI");
+    }
+
+    fn limited_deserializer() -> Deserializer {
+        Deserializer::new(&Config {
+            proto_max_bulk_len: 4,
+            proto_max_multibulk_len: 2,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn deserialize_query_buffer_limit() {
+        let mut deserializer = Deserializer::new(&Config {
+            client_query_buffer_limit: 16,
+            ..Default::default()
+        });
+
+        // a complete frame is taken out whatever its size
+        let mut buf = BytesMut::from(&b"*1\r\n$10\r\n0123456789\r\n"[..]);
+        assert!(deserializer.deserialize_msg(&mut buf).unwrap().is_some());
+
+        let mut buf = BytesMut::from(&b"*1\r\n$10\r\n01234567"[..]);
         assert!(matches!(
-            deserializer.deserialize_msg(msg).unwrap_err(),
-            DeserializeError::MalformedArray
+            deserializer.deserialize_msg(&mut buf).unwrap_err(),
+            DeserializeError::QueryBufferLimit
         ));
     }
+
+    #[test]
+    fn deserialize_within_limits() {
+        let mut buf = BytesMut::from(&b"*2\r\n$4\r\nECHO\r\n$4\r\nabcd\r\n"[..]);
+        let params = limited_deserializer().deserialize_msg(&mut buf).unwrap();
+        assert_eq!(params.unwrap(), vec!["ECHO", "abcd"]);
+    }
+
+    #[test]
+    fn deserialize_multibulk_len_exceeded() {
+        let mut buf = BytesMut::from(&b"*3\r\n"[..]);
+        assert!(matches!(
+            limited_deserializer()
+                .deserialize_msg(&mut buf)
+                .unwrap_err(),
+            DeserializeError::InvalidMultibulkLength
+        ));
+
+        let mut buf = BytesMut::from(&b"*4294967296\r\n"[..]);
+        assert!(matches!(
+            Deserializer::default()
+                .deserialize_msg(&mut buf)
+                .unwrap_err(),
+            DeserializeError::InvalidMultibulkLength
+        ));
+    }
+
+    #[test]
+    fn deserialize_bulk_len_exceeded() {
+        let mut buf = BytesMut::from(&b"*2\r\n$4\r\nECHO\r\n$5\r\n"[..]);
+        assert!(matches!(
+            limited_deserializer()
+                .deserialize_msg(&mut buf)
+                .unwrap_err(),
+            DeserializeError::InvalidBulkLength
+        ));
+    }
+
+    #[test]
+    fn deserialize_header_too_big() {
+        let mut msg = b"*".to_vec();
+        msg.extend(vec![b'1'; INLINE_MAX_LEN]);
+        assert!(deserialize_incomplete(&msg));
+        msg.push(b'1');
+        assert!(matches!(
+            deserialize(&msg).unwrap_err(),
+            DeserializeError::MultibulkCountTooBig
+        ));
+
+        let mut msg = b"*1\r\n$".to_vec();
+        msg.extend(vec![b'1'; INLINE_MAX_LEN + 1]);
+        assert!(matches!(
+            deserialize(&msg).unwrap_err(),
+            DeserializeError::BulkCountTooBig
+        ));
+    }
+
+    #[test]
+    fn deserialize_inline_too_big() {
+        let msg = vec![b'a'; INLINE_MAX_LEN];
+        assert!(deserialize_incomplete(&msg));
+        let msg = vec![b'a'; INLINE_MAX_LEN + 1];
+        assert!(matches!(
+            deserialize(&msg).unwrap_err(),
+            DeserializeError::InlineTooBig
+        ));
+    }
+
+    #[test]
+    fn deserialize_pipelined() {
+        let msg = b"*1\r\n$4\r\nPING\r\n*2\r\n$4\r\nECHO\r\n$2\r\nhi\r\n";
+        let (params, consumed) = deserialize_complete(msg);
+        assert_eq!((vec![Bytes::from("PING")], 14), (params, consumed));
+
+        let (params, consumed) = deserialize_complete(&msg[consumed..]);
+        assert_eq!(vec!["ECHO", "hi"], params);
+        assert_eq!(msg.len(), 14 + consumed);
+    }
+
+    #[test]
+    fn deserialize_fragmented() {
+        let msg = b"*2\r\n$4\r\nECHO\r\n$11\r\nhello world\r\n";
+        for i in 0..msg.len() {
+            assert!(deserialize_incomplete(&msg[..i]));
+        }
+        assert_eq!(
+            (
+                vec![Bytes::from("ECHO"), Bytes::from("hello world")],
+                msg.len()
+            ),
+            deserialize_complete(msg)
+        );
+    }
 }
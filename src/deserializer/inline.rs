@@ -0,0 +1,146 @@
+use bytes::Bytes;
+
+use crate::deserializer::DeserializeError;
+
+/// Splits an inline command into its arguments, following the quoting rules of `redis-cli`.
+///
+/// Arguments are separated by whitespace. Double quotes allow escape sequences such as `\n`,
+/// `\"` or `\x41`, single quotes only allow `\'`. A closing quote must be followed by
+/// whitespace or by the end of the line.
+pub fn split_args(line: &[u8]) -> Result<Vec<Bytes>, DeserializeError> {
+    let mut args = vec![];
+    let mut i = 0;
+
+    loop {
+        while line.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+        if i == line.len() {
+            return Ok(args);
+        }
+
+        let mut arg = vec![];
+        let mut in_double_quotes = false;
+        let mut in_single_quotes = false;
+        loop {
+            let c = line.get(i).copied();
+            if in_double_quotes {
+                match c {
+                    None => return Err(DeserializeError::UnbalancedQuotes),
+                    Some(b'\\') if i + 1 < line.len() => {
+                        i += 1;
+                        // like any unknown escape, `\x` not followed by two hex digits is `x`
+                        let hex = line.get(i + 1..i + 3).and_then(hex_byte);
+                        arg.push(match (line[i], hex) {
+                            (b'x', Some(b)) => {
+                                i += 2;
+                                b
+                            }
+                            (b'n', _) => b'\n',
+                            (b'r', _) => b'\r',
+                            (b't', _) => b'\t',
+                            (b'b', _) => 0x08,
+                            (b'a', _) => 0x07,
+                            (c, _) => c,
+                        });
+                    }
+                    Some(b'"') => {
+                        close_quotes(line, i)?;
+                        i += 1;
+                        break;
+                    }
+                    Some(c) => arg.push(c),
+                }
+            } else if in_single_quotes {
+                match c {
+                    None => return Err(DeserializeError::UnbalancedQuotes),
+                    Some(b'\\') if line.get(i + 1) == Some(&b'\'') => {
+                        arg.push(b'\'');
+                        i += 1;
+                    }
+                    Some(b'\'') => {
+                        close_quotes(line, i)?;
+                        i += 1;
+                        break;
+                    }
+                    Some(c) => arg.push(c),
+                }
+            } else {
+                match c {
+                    None => break,
+                    Some(c) if c.is_ascii_whitespace() => break,
+                    Some(b'"') => in_double_quotes = true,
+                    Some(b'\'') => in_single_quotes = true,
+                    Some(c) => arg.push(c),
+                }
+            }
+            i += 1;
+        }
+
+        args.push(Bytes::from(arg));
+    }
+}
+
+fn close_quotes(line: &[u8], quote_pos: usize) -> Result<(), DeserializeError> {
+    match line.get(quote_pos + 1) {
+        Some(c) if !c.is_ascii_whitespace() => Err(DeserializeError::UnbalancedQuotes),
+        _ => Ok(()),
+    }
+}
+
+fn hex_byte(digits: &[u8]) -> Option<u8> {
+    let hex = |d: u8| (d as char).to_digit(16);
+    Some((hex(digits[0])? * 16 + hex(digits[1])?) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_plain() {
+        assert_eq!(
+            split_args(b"SET foo bar").unwrap(),
+            vec!["SET", "foo", "bar"]
+        );
+        assert_eq!(split_args(b"  PING \t ").unwrap(), vec!["PING"]);
+        assert!(split_args(b"").unwrap().is_empty());
+        assert!(split_args(b"   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn split_double_quotes() {
+        assert_eq!(
+            split_args(b"SET \"hello world\" \"\"").unwrap(),
+            vec!["SET", "hello world", ""]
+        );
+        assert_eq!(
+            split_args(br#"ECHO "a\"b\n\x41\x4g""#).unwrap(),
+            vec![&b"ECHO"[..], &b"a\"b\nAx4g"[..]]
+        );
+    }
+
+    #[test]
+    fn split_single_quotes() {
+        assert_eq!(
+            split_args(br"SET 'it\'s' '\n'").unwrap(),
+            vec![&b"SET"[..], &b"it's"[..], &b"\\n"[..]]
+        );
+    }
+
+    #[test]
+    fn split_unbalanced_quotes() {
+        assert!(matches!(
+            split_args(b"SET \"foo bar").unwrap_err(),
+            DeserializeError::UnbalancedQuotes
+        ));
+        assert!(matches!(
+            split_args(b"SET 'foo").unwrap_err(),
+            DeserializeError::UnbalancedQuotes
+        ));
+        assert!(matches!(
+            split_args(b"SET \"foo\"bar").unwrap_err(),
+            DeserializeError::UnbalancedQuotes
+        ));
+    }
+}
@@ -0,0 +1,429 @@
+use std::{
+    sync::{Mutex, atomic::Ordering},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+
+use crate::{
+    clock,
+    config::Config,
+    db::{Databases, Keyspace, Object},
+    lazyfree, memory,
+    stats::Stats,
+};
+
+/// Number of candidates kept between eviction rounds, as Redis' `EVPOOL_SIZE`.
+const POOL_SIZE: usize = 16;
+/// Time a command may spend evicting keys before it runs, as Redis with the default
+/// `maxmemory-eviction-tenacity`. What's left to evict is evicted before the next commands.
+const BUDGET: Duration = Duration::from_micros(500);
+
+/// Keys to evict once memory is over `maxmemory`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    /// Nothing is evicted, commands that may use more memory are refused instead.
+    NoEviction,
+    AllKeysLru,
+    AllKeysLfu,
+    AllKeysRandom,
+    VolatileLru,
+    VolatileLfu,
+    VolatileRandom,
+    /// Keys with the nearest deadlines first.
+    VolatileTtl,
+}
+
+const POLICIES: &[(Policy, &str)] = &[
+    (Policy::NoEviction, "noeviction"),
+    (Policy::AllKeysLru, "allkeys-lru"),
+    (Policy::AllKeysLfu, "allkeys-lfu"),
+    (Policy::AllKeysRandom, "allkeys-random"),
+    (Policy::VolatileLru, "volatile-lru"),
+    (Policy::VolatileLfu, "volatile-lfu"),
+    (Policy::VolatileRandom, "volatile-random"),
+    (Policy::VolatileTtl, "volatile-ttl"),
+];
+
+impl Policy {
+    pub fn name(self) -> &'static str {
+        POLICIES
+            .iter()
+            .find(|(p, _)| *p == self)
+            .map(|(_, n)| *n)
+            .expect("every policy has a name")
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        POLICIES
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(name))
+            .map(|(p, _)| *p)
+    }
+
+    /// Whether only keys with a deadline may be evicted.
+    fn volatile(self) -> bool {
+        matches!(
+            self,
+            Policy::VolatileLru
+                | Policy::VolatileLfu
+                | Policy::VolatileRandom
+                | Policy::VolatileTtl
+        )
+    }
+
+    fn random(self) -> bool {
+        matches!(self, Policy::AllKeysRandom | Policy::VolatileRandom)
+    }
+
+    /// How good a candidate `object` is, the highest being evicted first.
+    fn score(self, object: &Object) -> u64 {
+        match self {
+            Policy::AllKeysLru | Policy::VolatileLru => object.idle_time().as_secs(),
+            Policy::AllKeysLfu | Policy::VolatileLfu => (u8::MAX - object.frequency()) as u64,
+            Policy::VolatileTtl => object.expiration().map_or(0, |deadline| {
+                u64::MAX - clock::to_unix_ms(deadline).max(0) as u64
+            }),
+            Policy::NoEviction | Policy::AllKeysRandom | Policy::VolatileRandom => 0,
+        }
+    }
+}
+
+struct Candidate {
+    score: u64,
+    db: usize,
+    key: Bytes,
+}
+
+#[derive(Default)]
+struct State {
+    /// Best candidates sampled so far, by ascending score.
+    pool: Vec<Candidate>,
+    /// Shard the next round samples, counting the shards of every database one after the other.
+    next_shard: usize,
+}
+
+impl State {
+    /// Adds a candidate to the pool, unless it's already there or the pool is full of better
+    /// ones, in which case the worst one makes room.
+    fn offer(&mut self, candidate: Candidate) {
+        if self
+            .pool
+            .iter()
+            .any(|c| c.db == candidate.db && c.key == candidate.key)
+        {
+            return;
+        }
+        let mut position = self.pool.partition_point(|c| c.score <= candidate.score);
+        if self.pool.len() == POOL_SIZE {
+            if position == 0 {
+                return;
+            }
+            self.pool.remove(0);
+            position -= 1;
+        }
+        self.pool.insert(position, candidate);
+    }
+}
+
+/// Eviction of keys to keep memory under `maxmemory`, which works as Redis' `performEvictions`.
+///
+/// Before a command runs, keys are evicted until memory is back under the limit. Each round
+/// samples a few keys of a shard, going through the shards of every database in turn, into a
+/// pool of the best candidates so far, and evicts the best one. The pool is kept from a round to
+/// the next, so that candidates compete with more keys than a single sample. Random policies
+/// evict a random key of the shard instead. Commands that may use more memory are refused when
+/// there's nothing left to evict.
+pub struct Evictor {
+    maxmemory: usize,
+    policy: Policy,
+    samples: usize,
+    lazy: bool,
+    budget: Duration,
+    /// Evictions hold the lock, so that one runs at a time.
+    state: Mutex<State>,
+}
+
+impl Default for Evictor {
+    /// Evictor of the default configuration, which has no memory limit.
+    fn default() -> Self {
+        Self::new(&Config::default())
+    }
+}
+
+impl Evictor {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            maxmemory: config.maxmemory,
+            policy: config.maxmemory_policy,
+            samples: config.maxmemory_samples,
+            lazy: config.lazyfree_lazy_eviction,
+            budget: BUDGET,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    pub fn maxmemory(&self) -> usize {
+        self.maxmemory
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    /// Evicts keys until memory is under `maxmemory`, before a command runs. Returns `false`
+    /// if it's still over, in which case commands that may use more memory must be refused.
+    ///
+    /// Memory the lazyfree thread is about to free isn't counted, or evicting big values lazily
+    /// would evict many more keys while they wait to be dropped.
+    pub fn make_room(&self, dbs: &Databases, stats: &Stats) -> bool {
+        self.maxmemory == 0
+            || self.evict(dbs, stats, || {
+                memory::used_memory().saturating_sub(lazyfree::pending_memory())
+            })
+    }
+
+    /// Evicts keys until `used_memory` is under the limit, returning whether it is. It also
+    /// returns `true` when the time budget is spent, since the next commands will go on.
+    fn evict(&self, dbs: &Databases, stats: &Stats, used_memory: impl Fn() -> usize) -> bool {
+        if used_memory() <= self.maxmemory {
+            return true;
+        }
+        if self.policy == Policy::NoEviction {
+            return false;
+        }
+
+        let start = Instant::now();
+        let shards: Vec<_> = dbs
+            .iter()
+            .enumerate()
+            .flat_map(|(i, db)| db.shards().iter().map(move |s| (i, s)))
+            .collect();
+        let mut state = self.state.lock().unwrap();
+        while used_memory() > self.maxmemory {
+            if start.elapsed() >= self.budget {
+                return true;
+            }
+            let Some(object) = self.evict_one(dbs, &shards, &mut state) else {
+                // the values being freed in the background may be enough, but waiting for them
+                // would block the runtime: the next command checks again, as Redis does with
+                // `EVICT_RUNNING`
+                return used_memory() <= self.maxmemory;
+            };
+
+            stats.evicted_keys.fetch_add(1, Ordering::Relaxed);
+            if self.lazy {
+                lazyfree::free(object);
+            }
+        }
+        true
+    }
+
+    /// Evicts a key, sampling shards until one has a key the policy allows to evict. Returns
+    /// its object, `None` if no shard has one.
+    fn evict_one(
+        &self,
+        dbs: &Databases,
+        shards: &[(usize, &Mutex<Keyspace>)],
+        state: &mut State,
+    ) -> Option<Object> {
+        let volatile = self.policy.volatile();
+        for _ in 0..shards.len() {
+            let (db, shard) = shards[state.next_shard % shards.len()];
+            state.next_shard = state.next_shard.wrapping_add(1);
+
+            let mut keyspace = shard.lock().unwrap();
+            if self.policy.random() {
+                let mut key = None;
+                keyspace.sample(1, volatile, |k, _| key = Some(k.clone()));
+                match key {
+                    Some(key) => return keyspace.swap_remove(&key),
+                    None => continue,
+                }
+            }
+            keyspace.sample(self.samples, volatile, |k, o| {
+                state.offer(Candidate {
+                    score: self.policy.score(o),
+                    db,
+                    key: k.clone(),
+                })
+            });
+            drop(keyspace);
+
+            while let Some(candidate) = state.pool.pop() {
+                let mut keyspace = dbs[candidate.db].lock(&candidate.key);
+                // the key may have been removed or persisted since it was sampled
+                if keyspace
+                    .get(&candidate.key)
+                    .is_some_and(|o| !volatile || o.expiration().is_some())
+                {
+                    return keyspace.swap_remove(&candidate.key);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use crate::{
+        clock::MockClock,
+        db::{Database, Settings, Value},
+    };
+
+    use super::*;
+
+    fn evictor(policy: Policy, maxmemory: usize) -> Evictor {
+        let mut evictor = Evictor::new(&Config {
+            maxmemory,
+            maxmemory_policy: policy,
+            maxmemory_samples: 64,
+            ..Default::default()
+        });
+        // so that slow test runs don't stop evictions halfway
+        evictor.budget = Duration::from_secs(10);
+        evictor
+    }
+
+    fn insert(dbs: &Databases, keys: &[&str], ttl: Option<u64>) {
+        for key in keys {
+            let expiration = ttl.map(|s| clock::now() + Duration::from_secs(s));
+            dbs[0].lock(key.as_bytes()).insert(
+                Bytes::from(key.to_string()),
+                Object::new(Value::Integer(0), expiration),
+            );
+        }
+    }
+
+    /// Memory used by the keys, counting 100 bytes per key.
+    fn used(dbs: &Databases) -> impl Fn() -> usize {
+        || dbs.iter().map(Database::len).sum::<usize>() * 100
+    }
+
+    fn remaining(dbs: &Databases) -> Vec<String> {
+        let mut keys = vec![];
+        dbs[0].scan(0, usize::MAX, |k, _| {
+            keys.push(String::from_utf8_lossy(k).to_string())
+        });
+        keys.sort();
+        keys
+    }
+
+    /// Two databases of a shard each, so that sampling sees every key and eviction is exact.
+    fn dbs() -> Databases {
+        Databases::new(2, 1, Settings::default())
+    }
+
+    #[test]
+    fn policy_names() {
+        for (policy, name) in POLICIES {
+            assert_eq!(Policy::parse(name), Some(*policy));
+            assert_eq!(policy.name(), *name);
+        }
+        assert_eq!(Policy::parse("ALLKEYS-LRU"), Some(Policy::AllKeysLru));
+        assert_eq!(Policy::parse("lru"), None);
+    }
+
+    #[test]
+    fn offer_keeps_best_candidates() {
+        let mut state = State::default();
+        for score in (0..POOL_SIZE as u64 + 4).rev() {
+            let key = Bytes::from(score.to_string());
+            state.offer(Candidate { score, db: 0, key });
+        }
+        state.offer(Candidate {
+            score: 10,
+            db: 0,
+            key: Bytes::from("10"),
+        });
+
+        let scores: Vec<u64> = state.pool.iter().map(|c| c.score).collect();
+        assert_eq!(scores, (4..POOL_SIZE as u64 + 4).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn evict_lru() {
+        let clock = MockClock::at(UNIX_EPOCH + Duration::from_secs(1_000_000));
+        let dbs = dbs();
+        insert(&dbs, &["old1", "old2", "old3", "used"], None);
+        clock.advance(Duration::from_secs(100));
+        insert(&dbs, &["new1", "new2"], None);
+        dbs[0].lock(b"used").live(b"used");
+
+        let stats = Stats::default();
+        assert!(evictor(Policy::AllKeysLru, 300).evict(&dbs, &stats, used(&dbs)));
+        assert_eq!(remaining(&dbs), ["new1", "new2", "used"]);
+        assert_eq!(stats.evicted_keys.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn evict_lfu() {
+        let dbs = dbs();
+        insert(&dbs, &["a", "b", "c", "d"], None);
+        for key in ["b", "d"] {
+            dbs[0].lock(key.as_bytes()).live(key.as_bytes());
+        }
+
+        let allkeys_lfu = evictor(Policy::AllKeysLfu, 200);
+        assert!(allkeys_lfu.evict(&dbs, &Stats::default(), used(&dbs)));
+        assert_eq!(remaining(&dbs), ["b", "d"]);
+    }
+
+    #[test]
+    fn evict_volatile_ttl() {
+        let dbs = dbs();
+        insert(&dbs, &["persistent"], None);
+        insert(&dbs, &["soon"], Some(10));
+        insert(&dbs, &["later"], Some(1000));
+
+        let volatile_ttl = |maxmemory| evictor(Policy::VolatileTtl, maxmemory);
+        assert!(volatile_ttl(200).evict(&dbs, &Stats::default(), used(&dbs)));
+        assert_eq!(remaining(&dbs), ["later", "persistent"]);
+
+        // keys without a deadline are never evicted
+        assert!(!volatile_ttl(0).evict(&dbs, &Stats::default(), used(&dbs)));
+        assert_eq!(remaining(&dbs), ["persistent"]);
+    }
+
+    #[test]
+    fn evict_random() {
+        let dbs = dbs();
+        insert(&dbs, &["a", "b", "c"], None);
+        insert(&dbs, &["x", "y"], Some(100));
+
+        let volatile_random = evictor(Policy::VolatileRandom, 300);
+        assert!(volatile_random.evict(&dbs, &Stats::default(), used(&dbs)));
+        assert_eq!(remaining(&dbs), ["a", "b", "c"]);
+        assert!(!volatile_random.evict(&dbs, &Stats::default(), || 400));
+
+        let allkeys_random = evictor(Policy::AllKeysRandom, 100);
+        assert!(allkeys_random.evict(&dbs, &Stats::default(), used(&dbs)));
+        assert_eq!(remaining(&dbs).len(), 1);
+    }
+
+    #[test]
+    fn evict_across_databases() {
+        let dbs = dbs();
+        insert(&dbs, &["a"], None);
+        dbs[1]
+            .lock(b"b")
+            .insert(Bytes::from("b"), Object::new(Value::Integer(0), None));
+
+        let allkeys_lru = evictor(Policy::AllKeysLru, 0);
+        assert!(allkeys_lru.evict(&dbs, &Stats::default(), used(&dbs)));
+        assert!(dbs[0].is_empty() && dbs[1].is_empty());
+    }
+
+    #[test]
+    fn make_room() {
+        let dbs = dbs();
+        let stats = Stats::default();
+        // whatever the tests allocate, it's more than a byte
+        assert!(!evictor(Policy::NoEviction, 1).make_room(&dbs, &stats));
+        assert!(evictor(Policy::NoEviction, usize::MAX).make_room(&dbs, &stats));
+        assert!(Evictor::new(&Config::default()).make_room(&dbs, &stats));
+    }
+}
@@ -0,0 +1,210 @@
+use std::{
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::Config,
+    db::{Database, Db, Sample, remove_expired_entries},
+    stats::Stats,
+};
+
+/// Keys looked at by each pass of a cycle, at the lowest effort.
+const KEYS_PER_LOOP: usize = 20;
+/// Time budget of a fast cycle, at the lowest effort.
+const FAST_DURATION: Duration = Duration::from_micros(1000);
+/// Share of the time slow cycles may take, in percent, at the lowest effort.
+const SLOW_TIME_PERC: u32 = 25;
+/// Percentage of expired keys in a pass under which a cycle stops, at the lowest effort.
+const ACCEPTABLE_STALE: u32 = 10;
+
+/// Active expiration, removing the expired keys that nobody accesses anymore. It works as
+/// Redis' `activeExpireCycle`.
+///
+/// A cycle goes through the shards of every database, starting after the shard where the
+/// previous one stopped. On each shard, it runs passes over a few keys, taking the lock for one
+/// pass at a time so that clients aren't stalled, until a pass finds few expired keys. The cycle
+/// stops early once its time budget is spent. Slow cycles run `hz` times per second and may take
+/// a share of that time. Fast cycles run between requests, with a budget of a millisecond, when
+/// the last cycle suggests that many expired keys are left.
+pub struct Expirer {
+    keys_per_loop: usize,
+    acceptable_stale: f64,
+    period: Duration,
+    slow_budget: Duration,
+    fast_budget: Duration,
+    /// Whether the last cycle left enough expired keys for fast cycles to be worth running.
+    behind: AtomicBool,
+    /// Shard the next cycle starts with.
+    next_shard: AtomicUsize,
+    /// Start of the last fast cycle. Running cycles hold the lock, so that one runs at a time.
+    last_fast: Mutex<Option<Instant>>,
+}
+
+impl Expirer {
+    pub fn new(config: &Config) -> Self {
+        let effort = config.active_expire_effort - 1;
+        let period = Duration::from_secs(1) / config.hz;
+
+        Self {
+            keys_per_loop: KEYS_PER_LOOP + KEYS_PER_LOOP / 4 * effort as usize,
+            acceptable_stale: (ACCEPTABLE_STALE - effort) as f64 / 100.0,
+            period,
+            slow_budget: period * (SLOW_TIME_PERC + 2 * effort) / 100,
+            fast_budget: FAST_DURATION + FAST_DURATION / 4 * effort,
+            behind: AtomicBool::new(false),
+            next_shard: AtomicUsize::new(0),
+            last_fast: Mutex::new(None),
+        }
+    }
+
+    /// Time between two slow cycles.
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Runs a slow cycle, once the running fast cycle, if any, has completed.
+    pub fn slow_cycle(&self, db: &Db, stats: &Stats) {
+        let _running = self.last_fast.lock().unwrap();
+        self.cycle(db, stats, self.slow_budget);
+    }
+
+    /// Runs a fast cycle, unless there are few expired keys to remove, another cycle is
+    /// running, or the last fast cycle started less than two budgets ago.
+    pub fn fast_cycle(&self, db: &Db, stats: &Stats) {
+        if !self.behind.load(Ordering::Relaxed) {
+            return;
+        }
+        let Ok(mut last_fast) = self.last_fast.try_lock() else {
+            return;
+        };
+        let start = Instant::now();
+        if last_fast.is_some_and(|last| start - last < self.fast_budget * 2) {
+            return;
+        }
+
+        *last_fast = Some(start);
+        self.cycle(db, stats, self.fast_budget);
+    }
+
+    fn cycle(&self, db: &Db, stats: &Stats, budget: Duration) {
+        let start = Instant::now();
+        let mut total = Sample::default();
+
+        let shards: Vec<_> = db.iter().flat_map(Database::shards).collect();
+        let timed_out = 'cycle: {
+            for _ in 0..shards.len() {
+                let i = self.next_shard.fetch_add(1, Ordering::Relaxed) % shards.len();
+                let shard = &shards[i];
+                loop {
+                    let sample = remove_expired_entries(shard, self.keys_per_loop);
+                    total.sampled += sample.sampled;
+                    total.expired += sample.expired;
+
+                    if sample.complete
+                        || sample.sampled == 0
+                        || sample.ratio() <= self.acceptable_stale
+                    {
+                        break;
+                    }
+                    if start.elapsed() >= budget {
+                        break 'cycle true;
+                    }
+                }
+            }
+            false
+        };
+
+        stats.record_expire_cycle(start.elapsed(), total.sampled, total.expired);
+        let behind = timed_out || stats.expired_stale_ratio() >= self.acceptable_stale;
+        self.behind.store(behind, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{
+        clock,
+        db::{Object, Value},
+    };
+
+    use super::*;
+
+    fn db_with(expired: usize, live: usize) -> Db {
+        let db = Db::default();
+        let past = clock::now() - Duration::from_secs(1);
+        let future = clock::now() + Duration::from_secs(100);
+        let keys = (0..expired)
+            .map(|i| (format!("expired{i}"), past))
+            .chain((0..live).map(|i| (format!("live{i}"), future)));
+        for (key, deadline) in keys {
+            let key = Bytes::from(key);
+            db[0]
+                .lock(&key)
+                .insert(key.clone(), Object::new(Value::Integer(0), Some(deadline)));
+        }
+        db
+    }
+
+    #[test]
+    fn new_scales_with_effort() {
+        let expirer = Expirer::new(&Config::default());
+        assert_eq!(expirer.keys_per_loop, 20);
+        assert_eq!(expirer.period(), Duration::from_millis(100));
+        assert_eq!(expirer.slow_budget, Duration::from_millis(25));
+        assert_eq!(expirer.fast_budget, Duration::from_millis(1));
+
+        let expirer = Expirer::new(&Config {
+            hz: 100,
+            active_expire_effort: 10,
+            ..Default::default()
+        });
+        assert_eq!(expirer.keys_per_loop, 65);
+        assert_eq!(expirer.acceptable_stale, 0.01);
+        assert_eq!(expirer.slow_budget, Duration::from_micros(4300));
+        assert_eq!(expirer.fast_budget, Duration::from_micros(3250));
+    }
+
+    #[test]
+    fn slow_cycle_removes_expired_keys() {
+        let db = db_with(1000, 0);
+        let stats = Stats::default();
+        let expirer = Expirer::new(&Config::default());
+
+        expirer.slow_cycle(&db, &stats);
+        assert!(db[0].is_empty());
+        assert_eq!(db.expired_keys(), 1000);
+        assert!(stats.expired_stale_ratio() > 0.0);
+    }
+
+    #[test]
+    fn slow_cycle_stops_on_few_expired_keys() {
+        let db = db_with(0, 100);
+        let stats = Stats::default();
+        let expirer = Expirer::new(&Config::default());
+
+        expirer.slow_cycle(&db, &stats);
+        assert_eq!(db[0].len(), 100);
+        assert_eq!(stats.expired_stale_ratio(), 0.0);
+        assert!(!expirer.behind.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn fast_cycle_only_when_behind() {
+        let db = db_with(10, 0);
+        let stats = Stats::default();
+        let expirer = Expirer::new(&Config::default());
+
+        expirer.fast_cycle(&db, &stats);
+        assert_eq!(db[0].len(), 10);
+
+        expirer.behind.store(true, Ordering::Relaxed);
+        expirer.fast_cycle(&db, &stats);
+        assert!(db[0].is_empty());
+    }
+}
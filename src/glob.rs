@@ -0,0 +1,131 @@
+/// Whether `string` matches the glob-style `pattern`, with the syntax of Redis' `KEYS`:
+///
+/// - `*` matches any sequence of bytes, `?` any single byte;
+/// - `[abc]` matches one of the listed bytes, `[a-z]` a range and `[^a]` any other byte;
+/// - `\` escapes the byte that follows.
+///
+/// As in Redis, an unterminated `[` takes the rest of the pattern as the set of bytes. Stars are
+/// matched by backtracking to the last one only, so the time taken is bounded by the product of
+/// the lengths rather than exponential.
+pub fn matches(pattern: &[u8], string: &[u8]) -> bool {
+    let (mut p, mut s) = (0, 0);
+    // position after the last star, and of the byte of `string` it's matched up to
+    let mut star = None;
+
+    while s < string.len() {
+        if pattern.get(p) == Some(&b'*') {
+            p += 1;
+            star = Some((p, s));
+            continue;
+        }
+        if let Some(next) = match_byte(pattern, p, string[s]) {
+            p = next;
+            s += 1;
+            continue;
+        }
+        // the last star takes one more byte
+        let Some((after_star, matched)) = star else {
+            return false;
+        };
+        p = after_star;
+        s = matched + 1;
+        star = Some((after_star, s));
+    }
+    pattern[p..].iter().all(|b| *b == b'*')
+}
+
+/// Matches the token of `pattern` at `p`, other than a star, against `byte`, returning the
+/// position of the next token if it matches.
+fn match_byte(pattern: &[u8], p: usize, byte: u8) -> Option<usize> {
+    match *pattern.get(p)? {
+        b'?' => Some(p + 1),
+        b'[' => match_set(pattern, p + 1, byte),
+        b'\\' if p + 1 < pattern.len() => (pattern[p + 1] == byte).then_some(p + 2),
+        b => (b == byte).then_some(p + 1),
+    }
+}
+
+/// Matches the set starting at `p`, after its `[`.
+fn match_set(pattern: &[u8], mut p: usize, byte: u8) -> Option<usize> {
+    let negated = pattern.get(p) == Some(&b'^');
+    if negated {
+        p += 1;
+    }
+
+    let mut matched = false;
+    while p < pattern.len() && pattern[p] != b']' {
+        if pattern[p] == b'\\' && p + 1 < pattern.len() {
+            matched |= pattern[p + 1] == byte;
+            p += 2;
+        } else if p + 2 < pattern.len() && pattern[p + 1] == b'-' {
+            // reversed ranges are accepted, as in Redis
+            let (a, b) = (pattern[p], pattern[p + 2]);
+            matched |= (a.min(b)..=a.max(b)).contains(&byte);
+            p += 3;
+        } else {
+            matched |= pattern[p] == byte;
+            p += 1;
+        }
+    }
+
+    // skips the `]`, if any
+    (matched != negated).then_some((p + 1).min(pattern.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_matches(pattern: &str, matching: &[&str], not_matching: &[&str]) {
+        for s in matching {
+            assert!(matches(pattern.as_bytes(), s.as_bytes()), "{pattern} {s}");
+        }
+        for s in not_matching {
+            assert!(!matches(pattern.as_bytes(), s.as_bytes()), "{pattern} {s}");
+        }
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert_matches("*", &["", "a", "hello"], &[]);
+        assert_matches("h?llo", &["hello", "hallo"], &["hllo", "heello"]);
+        assert_matches("h*llo", &["hllo", "heeeello"], &["hell", "hello!"]);
+        assert_matches("*a*b", &["ab", "xaxb", "aab", "abab"], &["aba", "b"]);
+        assert_matches(
+            "user:*:name",
+            &["user:1:name", "user::name"],
+            &["user:1:age"],
+        );
+    }
+
+    #[test]
+    fn matches_sets() {
+        assert_matches("h[ae]llo", &["hello", "hallo"], &["hillo", "hllo"]);
+        assert_matches("h[^e]llo", &["hallo", "hbllo"], &["hello"]);
+        assert_matches("h[a-b]llo", &["hallo", "hbllo"], &["hcllo"]);
+        assert_matches("h[b-a]llo", &["hallo", "hbllo"], &["hcllo"]);
+        assert_matches("[\\]]", &["]"], &["\\"]);
+        // unterminated
+        assert_matches("a[bc", &["ab", "ac"], &["a", "a["]);
+    }
+
+    #[test]
+    fn matches_escaped() {
+        assert_matches("a\\*", &["a*"], &["ab"]);
+        assert_matches("a\\?b", &["a?b"], &["axb"]);
+        assert_matches("a\\", &["a\\"], &["a"]);
+    }
+
+    #[test]
+    fn matches_binary() {
+        assert!(matches(b"\xff*", b"\xff\x00\x01"));
+        assert!(!matches(b"\xff?", b"\xfe\x00"));
+    }
+
+    #[test]
+    fn matches_many_stars_quickly() {
+        let pattern = "a*".repeat(50) + "b";
+        let string = "a".repeat(1000);
+        assert!(!matches(pattern.as_bytes(), string.as_bytes()));
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    sync::{
+        Arc, LazyLock,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Sender},
+    },
+    thread,
+};
+
+use crate::db::Object;
+
+/// Number of allocations above which freeing a value is left to the background thread, as
+/// Redis' `LAZYFREE_THRESHOLD`. Below it, sending the value costs about as much as dropping it.
+pub const LAZYFREE_THRESHOLD: usize = 64;
+
+/// Values to drop, how many objects they count for in `lazyfree_pending_objects` and the memory
+/// they use.
+struct Job {
+    garbage: Box<dyn Send>,
+    objects: u64,
+    memory: usize,
+}
+
+/// Thread dropping what it's sent, so that freeing big values holds neither a shard lock nor the
+/// connection that removed them. It's Redis' `BIO_LAZY_FREE` job.
+struct LazyFree {
+    sender: Sender<Job>,
+    /// Objects sent to the thread and not dropped yet.
+    pending: Arc<AtomicU64>,
+    /// Memory used by the objects sent to the thread and not dropped yet.
+    pending_memory: Arc<AtomicUsize>,
+    /// Objects dropped by the thread since the server started.
+    freed: Arc<AtomicU64>,
+}
+
+impl LazyFree {
+    fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let pending = Arc::new(AtomicU64::new(0));
+        let pending_memory = Arc::new(AtomicUsize::new(0));
+        let freed = Arc::new(AtomicU64::new(0));
+
+        let (thread_pending, thread_freed) = (Arc::clone(&pending), Arc::clone(&freed));
+        let thread_pending_memory = Arc::clone(&pending_memory);
+        thread::Builder::new()
+            .name("lazyfree".to_string())
+            .spawn(move || {
+                for job in receiver {
+                    drop(job.garbage);
+                    thread_pending.fetch_sub(job.objects, Ordering::Relaxed);
+                    thread_pending_memory.fetch_sub(job.memory, Ordering::Relaxed);
+                    thread_freed.fetch_add(job.objects, Ordering::Relaxed);
+                }
+            })
+            .expect("the lazyfree thread can be spawned");
+
+        Self {
+            sender,
+            pending,
+            pending_memory,
+            freed,
+        }
+    }
+
+    fn free(&self, object: Object) {
+        if object.value.free_effort() > LAZYFREE_THRESHOLD {
+            let memory = object.memory_usage();
+            self.free_later(object, 1, memory);
+        }
+    }
+
+    fn free_later<T: Send + 'static>(&self, garbage: T, objects: u64, memory: usize) {
+        self.pending.fetch_add(objects, Ordering::Relaxed);
+        self.pending_memory.fetch_add(memory, Ordering::Relaxed);
+        let job = Job {
+            garbage: Box::new(garbage),
+            objects,
+            memory,
+        };
+        if let Err(mpsc::SendError(job)) = self.sender.send(job) {
+            // the thread is gone, which only happens if dropping panicked: drop it here
+            self.pending.fetch_sub(job.objects, Ordering::Relaxed);
+            self.pending_memory.fetch_sub(job.memory, Ordering::Relaxed);
+        }
+    }
+}
+
+static LAZYFREE: LazyLock<LazyFree> = LazyLock::new(LazyFree::spawn);
+
+/// Drops `object`, in the background if it holds more than [`LAZYFREE_THRESHOLD`] allocations.
+pub fn free(object: Object) {
+    LAZYFREE.free(object);
+}
+
+/// Drops `garbage` in the background, whatever its size, counting it as `objects` using
+/// `memory` until then.
+pub fn free_later<T: Send + 'static>(garbage: T, objects: u64, memory: usize) {
+    LAZYFREE.free_later(garbage, objects, memory);
+}
+
+/// Number of objects waiting to be dropped in the background.
+pub fn pending_objects() -> u64 {
+    LAZYFREE.pending.load(Ordering::Relaxed)
+}
+
+/// Memory that will be freed once the objects waiting to be dropped are.
+pub fn pending_memory() -> usize {
+    LAZYFREE.pending_memory.load(Ordering::Relaxed)
+}
+
+/// Number of objects dropped in the background since the server started.
+pub fn freed_objects() -> u64 {
+    LAZYFREE.freed.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::mpsc::Receiver,
+        time::{Duration, Instant},
+    };
+
+    use bytes::Bytes;
+
+    use crate::db::Value;
+
+    use super::*;
+
+    /// Blocks the thread dropping it until it's told to go on.
+    struct Blocking(Receiver<()>);
+
+    impl Drop for Blocking {
+        fn drop(&mut self) {
+            let _ = self.0.recv();
+        }
+    }
+
+    fn wait_until(condition: impl Fn() -> bool) {
+        let start = Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn list(len: usize) -> Object {
+        let list = (0..len).map(|i| Bytes::from(i.to_string())).collect();
+        Object::new(Value::List(list), None)
+    }
+
+    #[test]
+    fn free_later_counts_pending_objects() {
+        let lazyfree = LazyFree::spawn();
+        let (go_on, blocked) = mpsc::channel();
+
+        lazyfree.free_later(Blocking(blocked), 3, 300);
+        lazyfree.free_later(VecDeque::<Bytes>::new(), 2, 200);
+        assert_eq!(lazyfree.pending.load(Ordering::Relaxed), 5);
+        assert_eq!(lazyfree.pending_memory.load(Ordering::Relaxed), 500);
+
+        go_on.send(()).unwrap();
+        // the thread updates the counters one after the other, so wait for all of them
+        wait_until(|| {
+            lazyfree.freed.load(Ordering::Relaxed) == 5
+                && lazyfree.pending.load(Ordering::Relaxed) == 0
+                && lazyfree.pending_memory.load(Ordering::Relaxed) == 0
+        });
+    }
+
+    #[test]
+    fn free_small_objects_in_place() {
+        let lazyfree = LazyFree::spawn();
+        lazyfree.free(list(LAZYFREE_THRESHOLD));
+        lazyfree.free(Object::new(Value::Integer(1), None));
+        assert_eq!(lazyfree.pending.load(Ordering::Relaxed), 0);
+
+        lazyfree.free(list(LAZYFREE_THRESHOLD + 1));
+        wait_until(|| lazyfree.freed.load(Ordering::Relaxed) == 1);
+    }
+}
@@ -1,55 +1,98 @@
+mod client;
+mod clock;
 mod cmd;
+mod config;
 mod db;
 mod deserializer;
+mod evict;
+mod expire;
+mod glob;
+mod lazyfree;
+mod memory;
 mod resp;
+mod scan;
+mod stats;
+mod thread_per_core;
 
-use std::{
-    sync::{Arc, Mutex},
-    time::Duration,
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
 };
 
+use client::Client;
 use cmd::{request::Request, response::Response};
-use db::{Db, Object, remove_expired_entries};
-use deserializer::Deserializer;
+use config::Config;
+use db::{Databases, Db};
+use deserializer::{DeserializeError, Deserializer};
+use evict::Evictor;
+use expire::Expirer;
+use stats::Stats;
 
-use indexmap::IndexMap;
 use log::{error, trace, warn};
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use tokio::{
     io::{self, AsyncReadExt, AsyncWriteExt},
     net::TcpListener,
+    runtime,
+    time::{self, MissedTickBehavior},
 };
 
-#[tokio::main]
-async fn main() -> io::Result<()> {
+fn main() -> io::Result<()> {
     env_logger::init();
-    let db = Arc::new(Mutex::new(IndexMap::<String, Object>::new()));
-    let listener = TcpListener::bind("127.0.0.1:6379").await?;
-
-    let expiry_db = Arc::clone(&db);
-    tokio::spawn(async move {
-        let sample_size = 100u64;
-        let stop_threshold = 0.25f64;
-
-        loop {
-            let mut ratio = 1.0f64;
-            while ratio > stop_threshold {
-                ratio = remove_expired_entries(&expiry_db, sample_size as usize);
-            }
+    let config = Config::from_args(std::env::args().skip(1))
+        .map(Arc::new)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
 
-            tokio::time::sleep(Duration::from_secs(1)).await;
-        }
-    });
+    if config.thread_per_core {
+        thread_per_core::run(config)
+    } else {
+        runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?
+            .block_on(serve(config))
+    }
+}
+
+/// Serves clients from tasks spread over the threads of the runtime, all sharing the keyspace.
+async fn serve(config: Arc<Config>) -> io::Result<()> {
+    let stats = Arc::new(Stats::default());
+    let db = Db::new(Databases::new(
+        config.databases,
+        config.shards,
+        config.keyspace_settings(),
+    ));
+    let listener = TcpListener::bind(("127.0.0.1", config.port)).await?;
+
+    let expirer = Arc::new(Expirer::new(&config));
+    tokio::spawn(run_slow_cycles(
+        Arc::clone(&expirer),
+        Arc::clone(&db),
+        Arc::clone(&stats),
+    ));
+    let evictor = Arc::new(Evictor::new(&config));
 
+    let next_client_id = AtomicU64::new(1);
     loop {
         let (mut stream, _) = listener.accept().await?;
+        // the replies to a read are written at once, Nagle's algorithm would only delay them.
+        // Redis disables it too
+        if let Err(e) = stream.set_nodelay(true) {
+            warn!("failed to disable Nagle's algorithm: {}", e);
+        }
         let db = Arc::clone(&db);
+        let config = Arc::clone(&config);
+        let stats = Arc::clone(&stats);
+        let expirer = Arc::clone(&expirer);
+        let evictor = Arc::clone(&evictor);
+        let mut client = Client::new(next_client_id.fetch_add(1, Ordering::Relaxed));
 
         tokio::spawn(async move {
             // TODO evaluate `BufReader` and `BufWriter` over `ReadHalf` and `WriteHalf`
             let (mut reader, mut writer) = stream.split();
             let mut buf = BytesMut::with_capacity(1024);
+            let mut deserializer = Deserializer::new(&config);
+            let mut replies = vec![];
             loop {
                 match reader.read_buf(&mut buf).await {
                     Ok(0) => {
@@ -58,15 +101,29 @@ async fn main() -> io::Result<()> {
                     Ok(_) => {
                         trace!("received: {:?}", String::from_utf8(buf[..].to_vec()));
 
-                        let reply = deserialize_and_execute(&buf[..], &db);
+                        let keep_open = deserialize_and_execute(
+                            &mut buf,
+                            &mut deserializer,
+                            &db,
+                            &stats,
+                            &evictor,
+                            &mut client,
+                            &mut replies,
+                        );
 
-                        if let Err(e) = writer.write_all(&reply.serialize()).await {
+                        if let Err(e) = writer.write_all(&replies).await {
                             error!("failed to write to socket: {}", e)
                         }
                         if let Err(e) = writer.flush().await {
                             error!("failed to flush to socket: {}", e)
                         }
-                        buf.clear();
+                        replies.clear();
+                        // what Redis does before waiting for the next events
+                        expirer.fast_cycle(&db, &stats);
+
+                        if !keep_open {
+                            break;
+                        }
                     }
                     Err(e) => {
                         error!("failed to read from socket: {}", e);
@@ -78,19 +135,73 @@ async fn main() -> io::Result<()> {
     }
 }
 
-fn deserialize_and_execute(msg: &[u8], db: &Db) -> Response {
-    let maybe_des = Deserializer::default()
-        .deserialize_msg(msg)
-        .map_err(|e| Response::SimpleError(e.to_string()));
-    if let Err(e) = maybe_des {
+async fn run_slow_cycles(expirer: Arc<Expirer>, db: Db, stats: Arc<Stats>) {
+    let mut interval = time::interval(expirer.period());
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        expirer.slow_cycle(&db, &stats);
+    }
+}
+
+/// Takes the next complete frame out of `buf` with the `deserializer` of the connection, `None`
+/// if it only holds part of one.
+///
+/// Malformed frames and frames exceeding the protocol limits are errors. The stream can't be
+/// resynchronized after that: `buf` is cleared, and the connection must be closed once the error
+/// has been sent.
+fn next_frame(
+    buf: &mut BytesMut,
+    deserializer: &mut Deserializer,
+    stats: &Stats,
+) -> Result<Option<Vec<Bytes>>, DeserializeError> {
+    let frame = deserializer.deserialize_msg(buf);
+
+    if let Err(e) = &frame {
         warn!("deserialization failed: {:?}", e);
-        return e;
+        stats.record_rejection(e);
+        buf.clear();
     }
+    frame
+}
+
+/// Executes every complete request in `buf`, appending the serialized replies to `replies` in
+/// the same order. Partial requests are left in `buf` until the rest of the data is read.
+///
+/// Returns `false` if a malformed request has been found, or if a request exceeds the protocol
+/// limits: the stream can't be resynchronized after that, so the connection must be closed once
+/// the replies have been sent.
+fn deserialize_and_execute(
+    buf: &mut BytesMut,
+    deserializer: &mut Deserializer,
+    db: &Db,
+    stats: &Stats,
+    evictor: &Evictor,
+    client: &mut Client,
+    replies: &mut Vec<u8>,
+) -> bool {
+    loop {
+        let des = match next_frame(buf, deserializer, stats) {
+            Ok(Some(params)) => params,
+            Ok(None) => return true,
+            Err(e) => {
+                replies.extend_from_slice(&Response::error(&e).serialize(client.protocol));
+                return false;
+            }
+        };
+
+        trace!("deserialized {:?}", des);
+        if des.is_empty() {
+            // empty arrays and blank inline lines get no reply, as in Redis
+            continue;
+        }
 
-    let des = maybe_des.unwrap();
-    trace!("deserialized {:?}", des);
-    match Request::try_from(des) {
-        Err(e) => Response::SimpleError(e.to_string()),
-        Ok(cmd) => cmd.execute(db),
+        let out_of_memory = !evictor.make_room(db, stats);
+        let reply = match Request::parse(des, out_of_memory) {
+            Err(e) => Response::error(&e),
+            Ok(cmd) => cmd.execute(db, stats, evictor, client),
+        };
+        replies.extend_from_slice(&reply.serialize(client.protocol));
     }
 }
@@ -0,0 +1,83 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// System allocator keeping count of the memory allocated through it, as Redis' `zmalloc` does
+/// for `used_memory`.
+///
+/// The count is what the process asked for, not what the allocator took from the system: the
+/// difference is fragmentation and allocator overhead, which `maxmemory` can't account for.
+struct Counting;
+
+static USED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn add(size: usize) {
+    let used = USED.fetch_add(size, Ordering::Relaxed) + size;
+    // most allocations don't make a new peak, and a load is cheaper than a `fetch_max`
+    if used > PEAK.load(Ordering::Relaxed) {
+        PEAK.fetch_max(used, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            add(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            add(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        USED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            add(new_size);
+            USED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Bytes currently allocated by the process.
+pub fn used_memory() -> usize {
+    USED.load(Ordering::Relaxed)
+}
+
+/// Most bytes allocated by the process at once since it started.
+pub fn peak_memory() -> usize {
+    PEAK.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn used_memory_counts_allocations() {
+        // other tests allocate concurrently, so only a big allocation stands out
+        let before = used_memory();
+        let buffer = vec![0u8; 64 * 1024 * 1024];
+        assert!(used_memory() >= before + 32 * 1024 * 1024);
+        assert!(peak_memory() >= 64 * 1024 * 1024);
+        drop(buffer);
+        assert!(used_memory() < before + 32 * 1024 * 1024);
+    }
+}
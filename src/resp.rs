@@ -1 +1,3 @@
+pub mod error;
+pub mod protocol;
 pub mod types;
@@ -0,0 +1,76 @@
+use std::fmt::Display;
+
+/// Code an error reply starts with. Clients route on it, e.g. to tell a `WRONGTYPE` apart from a
+/// generic `ERR`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+    Err,
+    WrongType,
+    WrongPass,
+    NoProto,
+    Oom,
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = match self {
+            ErrorCode::Err => "ERR",
+            ErrorCode::WrongType => "WRONGTYPE",
+            ErrorCode::WrongPass => "WRONGPASS",
+            ErrorCode::NoProto => "NOPROTO",
+            ErrorCode::Oom => "OOM",
+        };
+        write!(f, "{}", code)
+    }
+}
+
+/// An error that can be sent to clients as an error reply.
+pub trait ErrorReply: Display {
+    fn code(&self) -> ErrorCode {
+        ErrorCode::Err
+    }
+
+    /// Full text of the reply: the code followed by the message.
+    ///
+    /// Line breaks are replaced with spaces, as the message may echo arguments of the client and
+    /// a simple error can't span multiple lines.
+    fn reply(&self) -> String {
+        format!("{} {}", self.code(), self).replace(['\r', '\n'], " ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fake(&'static str, ErrorCode);
+
+    impl Display for Fake {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl ErrorReply for Fake {
+        fn code(&self) -> ErrorCode {
+            self.1
+        }
+    }
+
+    #[test]
+    fn reply_prefixed() {
+        assert_eq!(Fake("some error", ErrorCode::Err).reply(), "ERR some error");
+        assert_eq!(
+            Fake("out of memory", ErrorCode::Oom).reply(),
+            "OOM out of memory"
+        );
+    }
+
+    #[test]
+    fn reply_single_line() {
+        assert_eq!(
+            Fake("unknown 'a\r\nb'", ErrorCode::Err).reply(),
+            "ERR unknown 'a  b'"
+        );
+    }
+}
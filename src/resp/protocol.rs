@@ -0,0 +1,30 @@
+/// Version of the protocol negotiated by a client through `HELLO`.
+///
+/// Connections start with RESP2, as Redis does, so that clients unaware of RESP3 keep working.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
+impl Protocol {
+    pub fn version(&self) -> i64 {
+        match self {
+            Protocol::Resp2 => 2,
+            Protocol::Resp3 => 3,
+        }
+    }
+}
+
+impl TryFrom<i64> for Protocol {
+    type Error = ();
+
+    fn try_from(version: i64) -> Result<Self, Self::Error> {
+        match version {
+            2 => Ok(Protocol::Resp2),
+            3 => Ok(Protocol::Resp3),
+            _ => Err(()),
+        }
+    }
+}
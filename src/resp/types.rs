@@ -4,5 +4,12 @@ pub const ERROR: u8 = b'-';
 pub const INTEGER: u8 = b':';
 pub const NULL: u8 = b'_';
 pub const SIMPLE_STRING: u8 = b'+';
+pub const MAP: u8 = b'%';
+pub const SET: u8 = b'~';
+pub const DOUBLE: u8 = b',';
+pub const BOOLEAN: u8 = b'#';
+pub const BIG_NUMBER: u8 = b'(';
+pub const VERBATIM_STRING: u8 = b'=';
+pub const PUSH: u8 = b'>';
 pub const CR: u8 = b'\r';
 pub const LF: u8 = b'\n';
@@ -0,0 +1,83 @@
+use indexmap::IndexMap;
+
+/// Visits up to `count` entries of `map` from `cursor`, as `SCAN` does, and returns the cursor
+/// to continue from: `0` once every entry has been visited, and to start.
+///
+/// Entries are visited from the last index to the first, and the cursor is the number of
+/// indexes left to visit. That's what guarantees that entries present during the whole
+/// iteration are visited at least once, although `swap_remove` moves entries around: it moves
+/// the last entry to the index of the removed one, and the last entry either has already been
+/// visited, or nothing has been yet. Entries added while iterating are appended after the
+/// visited ones, so they may be missed, and an entry may be visited twice, which `SCAN` allows.
+///
+/// It only relies on the map being changed by inserting and swap-removing entries, so it works
+/// for the keyspace as for the values of collections.
+pub fn scan<K, V>(
+    map: &IndexMap<K, V>,
+    cursor: usize,
+    count: usize,
+    mut visit: impl FnMut(&K, &V),
+) -> usize {
+    let end = if cursor == 0 {
+        map.len()
+    } else {
+        cursor.min(map.len())
+    };
+    let start = end.saturating_sub(count);
+
+    for i in (start..end).rev() {
+        let (k, v) = map.get_index(i).expect("the index is within the map");
+        visit(k, v);
+    }
+    start
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn scan_all(
+        map: &mut IndexMap<u32, ()>,
+        count: usize,
+        mut change: impl FnMut(&mut IndexMap<u32, ()>),
+    ) -> HashSet<u32> {
+        let mut visited = HashSet::new();
+        let mut cursor = 0;
+        loop {
+            cursor = scan(map, cursor, count, |k, _| {
+                visited.insert(*k);
+            });
+            if cursor == 0 {
+                return visited;
+            }
+            change(map);
+        }
+    }
+
+    #[test]
+    fn scan_visits_every_entry() {
+        let mut map: IndexMap<u32, ()> = (0..100).map(|i| (i, ())).collect();
+        assert_eq!(scan_all(&mut map, 7, |_| {}), (0..100).collect());
+        assert_eq!(scan_all(&mut map, 1000, |_| {}), (0..100).collect());
+        assert!(scan_all(&mut IndexMap::new(), 10, |_| {}).is_empty());
+    }
+
+    #[test]
+    fn scan_survives_removals_and_insertions() {
+        let mut map: IndexMap<u32, ()> = (0..1000).map(|i| (i, ())).collect();
+        let mut next = 1000;
+        // removes the first entries, whose holes get filled by the last ones
+        let visited = scan_all(&mut map, 10, |map| {
+            map.swap_remove_index(0);
+            map.swap_remove_index(map.len() / 2);
+            map.insert(next, ());
+            next += 1;
+        });
+
+        for k in map.keys().filter(|k| **k < 1000) {
+            assert!(visited.contains(k), "{k} not visited");
+        }
+    }
+}
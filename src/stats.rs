@@ -0,0 +1,101 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::deserializer::DeserializeError;
+
+/// Server-wide counters, updated by every connection.
+#[derive(Debug, Default)]
+pub struct Stats {
+    /// Requests rejected for holding a bulk string bigger than `proto-max-bulk-len`.
+    pub rejected_bulk_len: AtomicU64,
+    /// Requests rejected for holding more elements than `proto-max-multibulk-len`.
+    pub rejected_multibulk_len: AtomicU64,
+    /// Inline requests and RESP headers rejected for not ending within the inline limit.
+    pub rejected_inline_len: AtomicU64,
+    /// Clients disconnected for exceeding `client-query-buffer-limit`.
+    pub query_buffer_limit_disconnections: AtomicU64,
+    /// Keys evicted to keep memory under `maxmemory`.
+    pub evicted_keys: AtomicU64,
+    /// Time spent in active expire cycles, in microseconds.
+    pub expire_cycle_cpu_micros: AtomicU64,
+    /// Bits of the `f64` estimate of the ratio of expired keys in the keyspace, see
+    /// [`Stats::expired_stale_ratio`].
+    expired_stale_ratio: AtomicU64,
+}
+
+impl Stats {
+    /// Counts a request rejected for exceeding one of the protocol limits.
+    pub fn record_rejection(&self, e: &DeserializeError) {
+        let counter = match e {
+            DeserializeError::InvalidBulkLength => &self.rejected_bulk_len,
+            DeserializeError::InvalidMultibulkLength => &self.rejected_multibulk_len,
+            DeserializeError::InlineTooBig
+            | DeserializeError::MultibulkCountTooBig
+            | DeserializeError::BulkCountTooBig => &self.rejected_inline_len,
+            DeserializeError::QueryBufferLimit => &self.query_buffer_limit_disconnections,
+            _ => return,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Accounts for an active expire cycle that took `elapsed` and found `expired` keys out of
+    /// `sampled`. Cycles must not be recorded concurrently.
+    pub fn record_expire_cycle(&self, elapsed: Duration, sampled: usize, expired: usize) {
+        self.expire_cycle_cpu_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+        if sampled == 0 {
+            return;
+        }
+
+        // a running average, so that a single cycle doesn't move the estimate too much
+        let current = expired as f64 / sampled as f64;
+        let ratio = current * 0.05 + self.expired_stale_ratio() * 0.95;
+        self.expired_stale_ratio
+            .store(ratio.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Estimate of the ratio of keys that have expired but are still in memory, from the samples
+    /// of the last expire cycles.
+    pub fn expired_stale_ratio(&self) -> f64 {
+        f64::from_bits(self.expired_stale_ratio.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_rejection() {
+        let stats = Stats::default();
+        stats.record_rejection(&DeserializeError::InvalidBulkLength);
+        stats.record_rejection(&DeserializeError::InlineTooBig);
+        stats.record_rejection(&DeserializeError::BulkCountTooBig);
+        stats.record_rejection(&DeserializeError::MalformedArray);
+
+        assert_eq!(stats.rejected_bulk_len.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.rejected_multibulk_len.load(Ordering::Relaxed), 0);
+        assert_eq!(stats.rejected_inline_len.load(Ordering::Relaxed), 2);
+        assert_eq!(
+            stats
+                .query_buffer_limit_disconnections
+                .load(Ordering::Relaxed),
+            0
+        );
+    }
+
+    #[test]
+    fn record_expire_cycle() {
+        let stats = Stats::default();
+        stats.record_expire_cycle(Duration::from_millis(2), 20, 20);
+        stats.record_expire_cycle(Duration::from_micros(500), 0, 0);
+
+        assert_eq!(stats.expire_cycle_cpu_micros.load(Ordering::Relaxed), 2500);
+        assert!((stats.expired_stale_ratio() - 0.05).abs() < 1e-9);
+
+        stats.record_expire_cycle(Duration::ZERO, 20, 0);
+        assert!((stats.expired_stale_ratio() - 0.0475).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    net,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+};
+
+use bytes::{Bytes, BytesMut};
+use log::{error, trace, warn};
+use tokio::{
+    io::{self, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    runtime,
+    sync::{mpsc, oneshot},
+};
+
+use crate::{
+    client::Client,
+    cmd::{
+        request::{Request, keys_of},
+        response::Response,
+    },
+    config::Config,
+    db::{Databases, Db},
+    deserializer::Deserializer,
+    evict::Evictor,
+    expire::Expirer,
+    next_frame, run_slow_cycles,
+    stats::Stats,
+};
+
+/// Request sent to the core owning the keys it accesses.
+struct Forwarded {
+    request: Request,
+    /// Copy of the state of the connection, which the request can't change: only keyless
+    /// commands like `HELLO` do.
+    client: Client,
+    reply: oneshot::Sender<Response>,
+}
+
+/// State of a core, shared by the connections it serves.
+struct Core {
+    id: usize,
+    db: Db,
+    config: Arc<Config>,
+    stats: Arc<Stats>,
+    expirer: Arc<Expirer>,
+    evictor: Arc<Evictor>,
+    /// Queues of the requests to execute on each core, by id.
+    peers: Arc<[mpsc::UnboundedSender<Forwarded>]>,
+}
+
+/// Serves clients with one single-threaded runtime per core, each owning a shard of the keyspace.
+///
+/// Requests whose keys all belong to the shard of another core are forwarded to that core, and
+/// executed there after the ones before them. Shards are thus only locked by their own core on
+/// the hot path, which keeps the locks uncontended. The other requests, keyless or spanning
+/// several shards, are executed where they're received, locking what they need as in the shared
+/// mode: commands like `MSET` over several cores stay atomic.
+pub fn run(config: Arc<Config>) -> io::Result<()> {
+    warn!("thread-per-core mode is experimental, see `Config::thread_per_core`");
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    let db = Db::new(Databases::new(
+        config.databases,
+        cores,
+        config.keyspace_settings(),
+    ));
+    let stats = Arc::new(Stats::default());
+    let expirer = Arc::new(Expirer::new(&config));
+    let evictor = Arc::new(Evictor::new(&config));
+
+    let listener = net::TcpListener::bind(("127.0.0.1", config.port))?;
+    listener.set_nonblocking(true)?;
+
+    let (peers, queues): (Vec<_>, Vec<_>) = (0..cores).map(|_| mpsc::unbounded_channel()).unzip();
+    let (connection_senders, connections): (Vec<_>, Vec<_>) =
+        (0..cores).map(|_| mpsc::unbounded_channel()).unzip();
+    let peers: Arc<[_]> = peers.into();
+
+    let mut threads = vec![];
+    for (id, (queue, connections)) in queues.into_iter().zip(connections).enumerate() {
+        let core = Arc::new(Core {
+            id,
+            db: Arc::clone(&db),
+            config: Arc::clone(&config),
+            stats: Arc::clone(&stats),
+            expirer: Arc::clone(&expirer),
+            evictor: Arc::clone(&evictor),
+            peers: Arc::clone(&peers),
+        });
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        threads.push(thread::spawn(move || {
+            runtime.block_on(async {
+                tokio::spawn(execute_forwarded(Arc::clone(&core), queue));
+                serve(core, connections).await
+            })
+        }));
+    }
+
+    let accept = runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    accept.spawn(run_slow_cycles(expirer, db, stats));
+    let accepted = accept.block_on(accept_connections(listener, connection_senders));
+
+    // the cores stop once the channels of the connections are closed
+    for thread in threads {
+        let _ = thread.join();
+    }
+    accepted
+}
+
+/// Accepts connections and hands them over to the cores in turn.
+async fn accept_connections(
+    listener: net::TcpListener,
+    cores: Vec<mpsc::UnboundedSender<(net::TcpStream, Client)>>,
+) -> io::Result<()> {
+    let listener = TcpListener::from_std(listener)?;
+    let next_client_id = AtomicU64::new(1);
+
+    for core in cores.iter().cycle() {
+        let (stream, _) = listener.accept().await?;
+        if let Err(e) = stream.set_nodelay(true) {
+            warn!("failed to disable Nagle's algorithm: {}", e);
+        }
+        let client = Client::new(next_client_id.fetch_add(1, Ordering::Relaxed));
+        // the stream is registered again by the runtime of the core
+        let _ = core.send((stream.into_std()?, client));
+    }
+    Ok(())
+}
+
+async fn serve(
+    core: Arc<Core>,
+    mut connections: mpsc::UnboundedReceiver<(net::TcpStream, Client)>,
+) {
+    while let Some((stream, client)) = connections.recv().await {
+        match TcpStream::from_std(stream) {
+            Ok(stream) => {
+                tokio::spawn(handle_connection(Arc::clone(&core), stream, client));
+            }
+            Err(e) => error!("failed to register connection: {}", e),
+        }
+    }
+}
+
+async fn execute_forwarded(core: Arc<Core>, mut queue: mpsc::UnboundedReceiver<Forwarded>) {
+    while let Some(mut forwarded) = queue.recv().await {
+        let reply =
+            forwarded
+                .request
+                .execute(&core.db, &core.stats, &core.evictor, &mut forwarded.client);
+        // the connection may be gone
+        let _ = forwarded.reply.send(reply);
+    }
+}
+
+async fn handle_connection(core: Arc<Core>, mut stream: TcpStream, mut client: Client) {
+    let (mut reader, mut writer) = stream.split();
+    let mut buf = BytesMut::with_capacity(1024);
+    let mut deserializer = Deserializer::new(&core.config);
+    let mut replies = vec![];
+    let mut forwarded = vec![];
+
+    loop {
+        match reader.read_buf(&mut buf).await {
+            Ok(0) => break,
+            Ok(_) => {
+                trace!("received: {:?}", String::from_utf8(buf[..].to_vec()));
+
+                let keep_open = loop {
+                    let params = match next_frame(&mut buf, &mut deserializer, &core.stats) {
+                        Ok(Some(params)) => params,
+                        Ok(None) => break true,
+                        Err(e) => {
+                            wait_for(&mut forwarded, &client, &mut replies).await;
+                            replies
+                                .extend_from_slice(&Response::error(&e).serialize(client.protocol));
+                            break false;
+                        }
+                    };
+                    if params.is_empty() {
+                        continue;
+                    }
+
+                    let owner = owner(&core.db, &params);
+                    let out_of_memory = !core.evictor.make_room(&core.db, &core.stats);
+                    match (Request::parse(params, out_of_memory), owner) {
+                        (Ok(request), Some(owner)) if owner != core.id => {
+                            let (reply, received) = oneshot::channel();
+                            let _ = core.peers[owner].send(Forwarded {
+                                request,
+                                client: client.clone(),
+                                reply,
+                            });
+                            forwarded.push(received);
+                        }
+                        (request, _) => {
+                            // replies keep the order of the requests, which may depend on the
+                            // forwarded ones, e.g. `MSET` after `SET` on another core
+                            wait_for(&mut forwarded, &client, &mut replies).await;
+                            let reply = match request {
+                                Err(e) => Response::error(&e),
+                                Ok(cmd) => {
+                                    cmd.execute(&core.db, &core.stats, &core.evictor, &mut client)
+                                }
+                            };
+                            replies.extend_from_slice(&reply.serialize(client.protocol));
+                        }
+                    }
+                };
+                wait_for(&mut forwarded, &client, &mut replies).await;
+
+                if let Err(e) = writer.write_all(&replies).await {
+                    error!("failed to write to socket: {}", e)
+                }
+                if let Err(e) = writer.flush().await {
+                    error!("failed to flush to socket: {}", e)
+                }
+                replies.clear();
+                core.expirer.fast_cycle(&core.db, &core.stats);
+
+                if !keep_open {
+                    break;
+                }
+            }
+            Err(e) => {
+                error!("failed to read from socket: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+/// Returns the core owning all the keys of a request, `None` if it has no keys or they belong
+/// to several cores.
+fn owner(db: &Databases, params: &[Bytes]) -> Option<usize> {
+    // keys belong to the same shard in every database
+    let mut shards = keys_of(params).into_iter().map(|k| db[0].shard_of(k));
+    let first = shards.next()?;
+    shards.all(|s| s == first).then_some(first)
+}
+
+/// Appends the replies of the forwarded requests, in the order they were sent.
+async fn wait_for(
+    forwarded: &mut Vec<oneshot::Receiver<Response>>,
+    client: &Client,
+    replies: &mut Vec<u8>,
+) {
+    for received in forwarded.drain(..) {
+        let reply = received
+            .await
+            .expect("cores execute requests as long as the server runs");
+        replies.extend_from_slice(&reply.serialize(client.protocol));
+    }
+}